@@ -34,10 +34,25 @@ export default class MyPlugin extends Plugin {
     statusBarItemEl.setText('Status Bar Text');
 
     let load_file = async (file: TFile) => {
+      // Files unchanged since they were stored aren't read again.
+      if (rust.is_indexed(file.path, file.stat.mtime, file.stat.size)) {
+        return;
+      }
       let content = await this.app.vault.cachedRead(file);
-      return rust.parse_to_db(file.path, content);
+      try {
+        rust.parse_to_db(file.path, file.stat.mtime, file.stat.size, content);
+      } catch (e) {
+        new Notice(`Could not parse tasks in ${e}`);
+      }
     };
 
+    // Catch up with changes made while the plugin wasn't running.
+    this.app.workspace.onLayoutReady(() => {
+      let files = this.app.vault.getMarkdownFiles();
+      rust.retain_in_db(files.map((f) => f.path));
+      files.forEach((f) => load_file(f));
+    });
+
     this.addCommand({
       id: 'parse',
       name: 'parse',
@@ -47,6 +62,56 @@ export default class MyPlugin extends Plugin {
       }
     }),
 
+      this.addCommand({
+        id: 'quick-add',
+        name: 'Add task',
+        editorCallback: (editor: Editor, ctx) => {
+          new QuickAddModal(this.app, (text) => {
+            try {
+              let before = editor.getValue();
+              editor.replaceSelection(rust.quick_add(text) + "\n");
+              if (ctx.file) {
+                rust.record_change(ctx.file.path, "add", before, editor.getValue());
+              }
+            } catch (e) {
+              new Notice(`Could not add task: ${e}`);
+            }
+          }).open();
+        }
+      });
+
+    // Undo and redo changes the plugin made to the tasks in a note, even
+    // after a restart.
+    let step = (editor: Editor, file: TFile | null, redo: boolean) => {
+      if (!file) {
+        return;
+      }
+      try {
+        let content = redo
+          ? rust.redo(file.path, editor.getValue())
+          : rust.undo(file.path, editor.getValue());
+        if (content === undefined) {
+          new Notice(redo ? "Nothing to redo" : "Nothing to undo");
+        } else {
+          editor.setValue(content);
+        }
+      } catch (e) {
+        new Notice(`Could not ${redo ? "redo" : "undo"}: ${e}`);
+      }
+    };
+
+    this.addCommand({
+      id: 'undo',
+      name: 'Undo task change',
+      editorCallback: (editor: Editor, ctx) => step(editor, ctx.file, false)
+    });
+
+    this.addCommand({
+      id: 'redo',
+      name: 'Redo task change',
+      editorCallback: (editor: Editor, ctx) => step(editor, ctx.file, true)
+    });
+
       // This adds a simple command that can be triggered anywhere
       this.addCommand({
         id: 'open-sample-modal-simple',
@@ -60,6 +125,13 @@ export default class MyPlugin extends Plugin {
     this.addSettingTab(new SampleSettingTab(this.app, this));
 
     this.registerEvent(this.app.vault.on("modify", load_file));
+    this.registerEvent(this.app.vault.on("delete", (file) => rust.remove_from_db(file.path)));
+    this.registerEvent(this.app.vault.on("rename", (file, oldPath) => {
+      rust.remove_from_db(oldPath);
+      if (file instanceof TFile) {
+        load_file(file);
+      }
+    }));
 
     // If the plugin hooks up any global DOM events (on parts of the app that doesn't belong to this plugin)
     // Using this function will automatically remove the event listener when this plugin is disabled.
@@ -101,6 +173,47 @@ class SampleModal extends Modal {
   }
 }
 
+class QuickAddModal extends Modal {
+  onSubmit: (text: string) => void;
+
+  constructor(app: App, onSubmit: (text: string) => void) {
+    super(app);
+    this.onSubmit = onSubmit;
+  }
+
+  onOpen() {
+    const { contentEl } = this;
+    let text = '';
+    const submit = () => {
+      this.close();
+      this.onSubmit(text);
+    };
+
+    new Setting(contentEl)
+      .setName('Task')
+      .setDesc('Like: Write report tomorrow 5pm #work every monday 30m !high')
+      .addText(input => {
+        input.onChange(value => text = value);
+        input.inputEl.addEventListener('keydown', (evt: KeyboardEvent) => {
+          if (evt.key === 'Enter') {
+            evt.preventDefault();
+            submit();
+          }
+        });
+      });
+    new Setting(contentEl)
+      .addButton(button => button
+        .setButtonText('Add')
+        .setCta()
+        .onClick(submit));
+  }
+
+  onClose() {
+    const { contentEl } = this;
+    contentEl.empty();
+  }
+}
+
 class SampleSettingTab extends PluginSettingTab {
   plugin: MyPlugin;
 
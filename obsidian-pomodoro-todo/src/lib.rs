@@ -1,8 +1,11 @@
 mod obsidian;
 
-use std::collections::HashMap;
-use todo::TodoList;
+use js_sys::Array;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use todo::{Document, History, Todo, TodoList, Vault};
 use wasm_bindgen::prelude::*;
+use web_sys::Storage;
 
 #[wasm_bindgen]
 extern "C" {
@@ -28,40 +31,229 @@ macro_rules! console_log {
     ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
 }
 
-const DATABASE: &str = "pomodoro-todo-db";
+/// Prefix of the key each file's tasks are stored under, followed by its path.
+const DATABASE: &str = "pomodoro-todo-db:";
+/// Bumped when the entry format changes, older entries are parsed again.
+const VERSION: u32 = 1;
 
-#[wasm_bindgen]
-pub fn parse_to_db(path: String, content: String) {
+/// A file's tasks along with what's needed to tell if the file changed, so
+/// only changed files are parsed again, even after a restart.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    version: u32,
+    /// Modification time in milliseconds and size, as Obsidian gives them.
+    mtime: f64,
+    size: f64,
+    hash: String,
+    tasks: TodoList,
+}
+
+fn storage() -> Result<Storage, JsValue> {
     let window = web_sys::window().expect("no global `window` exists");
-    let storage = match window.session_storage() {
-        Ok(Some(e)) => e,
-        Ok(None) | Err(_) => panic!("Cant get storage"),
+    match window.local_storage() {
+        Ok(Some(e)) => Ok(e),
+        Ok(None) | Err(_) => Err(JsValue::from_str("Cant get storage")),
+    }
+}
+
+fn key(path: &str) -> String {
+    format!("{}{}", DATABASE, path)
+}
+
+fn read_entry(storage: &Storage, path: &str) -> Result<Option<Entry>, JsValue> {
+    let s = match storage.get_item(&key(path)) {
+        Ok(e) => e,
+        Err(_) => return Err(JsValue::from_str("Cant read value")),
+    };
+    // The database is only a cache, an unreadable entry is parsed again.
+    Ok(s.and_then(|x| serde_json::from_str::<Entry>(&x).ok())
+        .filter(|x| x.version == VERSION))
+}
+
+fn write_entry(storage: &Storage, path: &str, entry: &Entry) -> Result<(), JsValue> {
+    let s = match serde_json::to_string(entry) {
+        Ok(e) => e,
+        Err(_) => return Err(JsValue::from_str("Couldn't convert to json.")),
     };
+    match storage.set_item(&key(path), s.as_str()) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(JsValue::from_str("Cant save")),
+    }
+}
+
+/// Whether a file's tasks are stored and it hasn't changed since, so it
+/// needn't be read.
+#[wasm_bindgen]
+pub fn is_indexed(path: String, mtime: f64, size: f64) -> Result<bool, JsValue> {
+    Ok(match read_entry(&storage()?, &path)? {
+        Some(e) => e.mtime == mtime && e.size == size,
+        None => false,
+    })
+}
+
+/// Store a file's tasks, only writing its own entry. Files touched without
+/// changing aren't parsed again.
+#[wasm_bindgen]
+pub fn parse_to_db(path: String, mtime: f64, size: f64, content: String) -> Result<(), JsValue> {
+    let storage = storage()?;
 
-    let js = match storage.get_item(DATABASE) {
+    let hash = Vault::content_hash(&content);
+    if let Some(mut e) = read_entry(&storage, &path)? {
+        if e.hash == hash {
+            if e.mtime != mtime || e.size != size {
+                e.mtime = mtime;
+                e.size = size;
+                write_entry(&storage, &path, &e)?;
+            }
+            return Ok(());
+        }
+    }
+
+    // Each task keeps its file and line so we can jump back to it.
+    let tdl = match TodoList::from_note(content.as_str(), &path) {
         Ok(e) => e,
-        Err(_) => panic!("Cant read value"),
+        Err(e) => return Err(JsValue::from_str(&format!("{}: {}", path, e))),
     };
 
-    let mut db: HashMap<String, TodoList> = match js {
-        Some(e) => match serde_json::from_str(e.as_str()) {
-            Ok(e) => e,
-            Err(e) => panic!("Couldn't convert from json. {}", e),
-        },
-        None => HashMap::new(),
+    let entry = Entry {
+        version: VERSION,
+        mtime,
+        size,
+        hash,
+        tasks: tdl,
     };
+    write_entry(&storage, &path, &entry)
+}
 
-    let tdl = TodoList::from_mixed_markdown(content.as_str());
+/// Drop a deleted or renamed file, and its undo history, from the database.
+#[wasm_bindgen]
+pub fn remove_from_db(path: String) -> Result<(), JsValue> {
+    let storage = storage()?;
+    for e in [key(&path), history_key(&path)] {
+        if storage.remove_item(&e).is_err() {
+            return Err(JsValue::from_str("Cant remove value"));
+        }
+    }
+    Ok(())
+}
 
-    db.insert(path, tdl);
+/// Drop every file that isn't in `paths`, for files deleted while the
+/// plugin wasn't running.
+#[wasm_bindgen]
+pub fn retain_in_db(paths: Array) -> Result<(), JsValue> {
+    let storage = storage()?;
+    let paths: HashSet<String> = paths.iter().filter_map(|x| x.as_string()).collect();
 
-    let s = match serde_json::to_string(&db) {
+    let len = storage.length().unwrap_or_default();
+    let stale: Vec<String> = (0..len)
+        .filter_map(|i| storage.key(i).ok().flatten())
+        .filter(|x| {
+            let path = x.strip_prefix(DATABASE).or_else(|| x.strip_prefix(HISTORY));
+            match path {
+                Some(path) => !paths.contains(path),
+                None => false,
+            }
+        })
+        .collect();
+    for e in stale {
+        if storage.remove_item(&e).is_err() {
+            return Err(JsValue::from_str("Cant remove value"));
+        }
+    }
+    Ok(())
+}
+
+/// Prefix of the key each file's undo history is stored under, followed by
+/// its path.
+const HISTORY: &str = "pomodoro-todo-history:";
+/// Most changes to a file that can be undone.
+const HISTORY_LIMIT: usize = 100;
+
+fn history_key(path: &str) -> String {
+    format!("{}{}", HISTORY, path)
+}
+
+fn read_history(storage: &Storage, path: &str) -> Result<History, JsValue> {
+    let s = match storage.get_item(&history_key(path)) {
         Ok(e) => e,
-        Err(_) => panic!("Couldn't convert to json."),
+        Err(_) => return Err(JsValue::from_str("Cant read value")),
     };
+    // A history that can't be read is started over.
+    Ok(s.and_then(|x| History::from_json(&x).ok())
+        .unwrap_or_else(|| History::new(HISTORY_LIMIT)))
+}
 
-    match storage.set_item(DATABASE, s.as_str()) {
-        Ok(_) => return,
-        Err(_) => panic!("Cant save"),
+fn write_history(storage: &Storage, path: &str, history: &History) -> Result<(), JsValue> {
+    let s = match history.to_json() {
+        Ok(e) => e,
+        Err(_) => return Err(JsValue::from_str("Couldn't convert to json.")),
     };
+    match storage.set_item(&history_key(path), s.as_str()) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(JsValue::from_str("Cant save")),
+    }
+}
+
+fn document(path: &str, content: &str) -> Result<Document, JsValue> {
+    Document::from_markdown(content).map_err(|e| JsValue::from_str(&format!("{}: {}", path, e)))
+}
+
+/// Record a change to the tasks in a file, from its content before to after,
+/// so it can be undone.
+#[wasm_bindgen]
+pub fn record_change(
+    path: String,
+    label: String,
+    before: String,
+    after: String,
+) -> Result<(), JsValue> {
+    let storage = storage()?;
+    let before = document(&path, &before)?;
+    let after = document(&path, &after)?;
+    let mut history = read_history(&storage, &path)?;
+    match history.push(&label, before.tasks(), after.tasks()) {
+        Ok(true) => write_history(&storage, &path, &history),
+        Ok(false) => Ok(()),
+        Err(e) => Err(JsValue::from_str(&e.to_string())),
+    }
+}
+
+/// Undo, or redo, the last change to the tasks in a file, giving its new
+/// content, or undefined if there's nothing to undo.
+fn step(path: &str, content: &str, redo: bool) -> Result<Option<String>, JsValue> {
+    let storage = storage()?;
+    let mut doc = document(path, content)?;
+    let mut history = read_history(&storage, path)?;
+    let step = match redo {
+        true => history.redo(doc.tasks_mut()),
+        false => history.undo(doc.tasks_mut()),
+    };
+    match step {
+        Ok(Some(_)) => {
+            write_history(&storage, path, &history)?;
+            Ok(Some(doc.to_markdown()))
+        }
+        Ok(None) => Ok(None),
+        Err(e) => Err(JsValue::from_str(&e.to_string())),
+    }
+}
+
+#[wasm_bindgen]
+pub fn undo(path: String, content: String) -> Result<Option<String>, JsValue> {
+    step(&path, &content, false)
+}
+
+#[wasm_bindgen]
+pub fn redo(path: String, content: String) -> Result<Option<String>, JsValue> {
+    step(&path, &content, true)
+}
+
+/// The markdown for a task written as one line of free text, like
+/// `Write report tomorrow 5pm #work every monday 30m !high`.
+#[wasm_bindgen]
+pub fn quick_add(text: String) -> Result<String, JsValue> {
+    match Todo::parse_quick_add(&text) {
+        Ok(e) => Ok(e.to_markdown()),
+        Err(e) => Err(JsValue::from_str(&e.to_string())),
+    }
 }
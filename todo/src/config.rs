@@ -0,0 +1,301 @@
+use crate::{dates, Date, Error, Result};
+use chrono::{Duration, NaiveDate, NaiveDateTime, Weekday};
+use chrono_tz::Tz;
+use core::time::Duration as StdDuration;
+use duration_human::DurationHuman;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// How a task file is stored.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Json,
+    Markdown,
+    /// A journal of changes, see `Journal`.
+    Journal,
+}
+
+impl Format {
+    /// The format of a file going by its extension, JSON unless it's `.md`
+    /// or `.jsonl`.
+    pub fn of(file: &str) -> Format {
+        if file.ends_with(".md") {
+            Format::Markdown
+        } else if file.ends_with(".jsonl") {
+            Format::Journal
+        } else {
+            Format::Json
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Format> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "md" | "markdown" => Ok(Format::Markdown),
+            "jsonl" | "journal" => Ok(Format::Journal),
+            _ => Err(Error::parse(1, 1, format!("Unknown format {}", s))),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Reminders {
+    pub enabled: bool,
+    /// How long before a task is due to remind about it, like `15min`.
+    pub before: String,
+}
+
+impl Default for Reminders {
+    fn default() -> Self {
+        Reminders {
+            enabled: true,
+            before: String::from("15min"),
+        }
+    }
+}
+
+/// Settings given on the command line, see `Config::with_overrides`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Overrides {
+    pub file: Option<String>,
+    pub format: Option<Format>,
+    pub date_format: Option<String>,
+    pub week_start: Option<String>,
+    /// Reminder lead like `15min`, turning reminders on.
+    pub reminder_before: Option<String>,
+}
+
+/// Settings read from `config.json` in the XDG config directory, each of
+/// which can be overridden by a `TODO_*` environment variable, and some by
+/// flags, see `Overrides`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Task file used when none is given, `TODO_FILE`.
+    pub file: String,
+    /// Format of task files, by default going by their extension,
+    /// `TODO_FORMAT`.
+    pub format: Option<Format>,
+    /// Vault watched when none is given, `TODO_VAULT`.
+    pub vault: Option<String>,
+    /// strftime format dates are shown in, and accepted in besides
+    /// yyyy-mm-dd, `TODO_DATE_FORMAT`.
+    pub date_format: String,
+    /// First day of the week, `TODO_WEEK_START`.
+    #[serde(with = "weekday")]
+    pub week_start: Weekday,
+    pub reminders: Reminders,
+    /// Number of previous versions of a task file kept when saving it,
+    /// `TODO_BACKUPS`.
+    pub backups: usize,
+    /// Number of changes to a task file that can be undone, `TODO_HISTORY`.
+    pub history: usize,
+    /// Number of events a journal replays before it's compacted into a
+    /// snapshot, `TODO_COMPACT_AFTER`.
+    pub compact_after: usize,
+    /// IANA time zone, like `Europe/Berlin`, used for task lists that don't
+    /// have their own, `TODO_ZONE`. Defaults to the local zone.
+    pub zone: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let file = match xdg_dir("XDG_DATA_HOME", ".local/share") {
+            Some(e) => e.join("todo").join("todo.json"),
+            None => PathBuf::from("todo.json"),
+        };
+        Config {
+            file: file.to_string_lossy().into_owned(),
+            format: None,
+            vault: None,
+            date_format: String::from("%Y-%m-%d"),
+            week_start: Weekday::Mon,
+            reminders: Reminders::default(),
+            backups: 0,
+            history: 100,
+            compact_after: 1000,
+            zone: None,
+        }
+    }
+}
+
+/// An XDG base directory, falling back to a directory in `$HOME`.
+fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    match env::var_os(var) {
+        Some(e) if !e.is_empty() => Some(PathBuf::from(e)),
+        _ => env::var_os("HOME").map(|x| PathBuf::from(x).join(fallback)),
+    }
+}
+
+impl Config {
+    /// Where the config file is, `TODO_CONFIG` or `todo/config.json` in the
+    /// XDG config directory.
+    pub fn path() -> Option<PathBuf> {
+        match env::var_os("TODO_CONFIG") {
+            Some(e) => Some(PathBuf::from(e)),
+            None => {
+                xdg_dir("XDG_CONFIG_HOME", ".config").map(|x| x.join("todo").join("config.json"))
+            }
+        }
+    }
+
+    /// Read a config file, a missing file gives the defaults.
+    pub fn from_file(path: &Path) -> Result<Config> {
+        let s = match fs::read_to_string(path) {
+            Ok(e) => e,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(Error::io(path, e)),
+        };
+        let config: Config = serde_json::from_str(&s)?;
+        config.reminder_before()?;
+        config.zone()?;
+        Ok(config)
+    }
+
+    /// The config from the config file, with environment overrides applied.
+    pub fn load() -> Result<Config> {
+        let config = match Config::path() {
+            Some(e) => Config::from_file(&e)?,
+            None => Config::default(),
+        };
+        config.with_env()
+    }
+
+    /// Override settings from `TODO_*` environment variables.
+    pub fn with_env(mut self) -> Result<Config> {
+        let var = |name| env::var(name).ok().filter(|x: &String| !x.is_empty());
+        if let Some(e) = var("TODO_FILE") {
+            self.file = e;
+        }
+        if let Some(e) = var("TODO_FORMAT") {
+            self.format = Some(Format::from_str(&e)?);
+        }
+        if let Some(e) = var("TODO_VAULT") {
+            self.vault = Some(e);
+        }
+        if let Some(e) = var("TODO_DATE_FORMAT") {
+            self.date_format = e;
+        }
+        if let Some(e) = var("TODO_WEEK_START") {
+            self.week_start = weekday::parse(&e)?;
+        }
+        if let Some(e) = var("TODO_BACKUPS") {
+            self.backups = _number("TODO_BACKUPS", &e)?;
+        }
+        if let Some(e) = var("TODO_HISTORY") {
+            self.history = _number("TODO_HISTORY", &e)?;
+        }
+        if let Some(e) = var("TODO_COMPACT_AFTER") {
+            self.compact_after = _number("TODO_COMPACT_AFTER", &e)?;
+        }
+        if let Some(e) = var("TODO_ZONE") {
+            self.zone = Some(e);
+            self.zone()?;
+        }
+        Ok(self)
+    }
+
+    /// Override settings with ones given on the command line, which win over
+    /// the config file and the environment.
+    pub fn with_overrides(mut self, o: &Overrides) -> Result<Config> {
+        if let Some(e) = &o.file {
+            self.file = e.clone();
+        }
+        if let Some(e) = o.format {
+            self.format = Some(e);
+        }
+        if let Some(e) = &o.date_format {
+            self.date_format = e.clone();
+        }
+        if let Some(e) = &o.week_start {
+            self.week_start = weekday::parse(e)?;
+        }
+        if let Some(e) = &o.reminder_before {
+            self.reminders = Reminders {
+                enabled: true,
+                before: e.clone(),
+            };
+            self.reminder_before()?;
+        }
+        Ok(self)
+    }
+
+    /// Format of a task file.
+    pub fn format_of(&self, file: &str) -> Format {
+        self.format.unwrap_or_else(|| Format::of(file))
+    }
+
+    /// How long before a task is due to remind about it, if reminders are
+    /// enabled.
+    pub fn reminder_before(&self) -> Result<Option<Duration>> {
+        if !self.reminders.enabled {
+            return Ok(None);
+        }
+        let invalid = || Error::InvalidDuration(self.reminders.before.clone());
+        let d = DurationHuman::try_from(self.reminders.before.as_str()).map_err(|_| invalid())?;
+        match Duration::from_std(StdDuration::from(&d)) {
+            Ok(e) => Ok(Some(e)),
+            Err(_) => Err(invalid()),
+        }
+    }
+
+    pub fn zone(&self) -> Result<Option<Tz>> {
+        match &self.zone {
+            Some(e) => match Tz::from_str(e) {
+                Ok(e) => Ok(Some(e)),
+                Err(_) => Err(Error::parse(1, 1, format!("Unknown time zone {}", e))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Read a date in the configured format, or like `dates::parse` with
+    /// weeks starting on `week_start`.
+    pub fn read_date(&self, s: &str, now: NaiveDateTime) -> Result<Date> {
+        match NaiveDate::parse_from_str(s.trim(), &self.date_format) {
+            Ok(e) => Ok(Date::Day(e)),
+            Err(_) => dates::parse(s, now, self.week_start),
+        }
+    }
+
+    /// The last day of the week a date is in.
+    pub fn end_of_week(&self, d: NaiveDate) -> NaiveDate {
+        dates::end_of_week(d, self.week_start)
+    }
+}
+
+fn _number(name: &str, s: &str) -> Result<usize> {
+    s.parse()
+        .map_err(|_| Error::parse(1, 1, format!("{} isn't a number: {}", name, s)))
+}
+
+/// Weekdays as their names, like `monday`.
+mod weekday {
+    use crate::{Error, Result};
+    use chrono::Weekday;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn parse(s: &str) -> Result<Weekday> {
+        s.parse()
+            .map_err(|_| Error::parse(1, 1, format!("Unknown weekday {}", s)))
+    }
+
+    pub fn serialize<S: Serializer>(w: &Weekday, s: S) -> std::result::Result<S::Ok, S::Error> {
+        s.serialize_str(&w.to_string().to_lowercase())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Weekday, D::Error> {
+        let s = String::deserialize(d)?;
+        parse(&s).map_err(de::Error::custom)
+    }
+}
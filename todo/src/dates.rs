@@ -0,0 +1,280 @@
+use crate::structs::Date;
+use crate::{Error, Result};
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Weekday};
+use chrono::{Local, Utc};
+use chrono_tz::Tz;
+
+/// Read a date as people write it, relative to `now`, like `today`,
+/// `tomorrow 9am`, `friday`, `next friday`, `in 3 days`, `eow`, `nov 3` or
+/// `2026-11-01 14:00`. Days without a time are whole days, see `Date`.
+///
+/// Weekdays are the next one after today, or today for `this friday`, `eow`
+/// is the last day of the week starting on `week_start`, and month days are
+/// the next one from today.
+pub fn parse(s: &str, now: NaiveDateTime, week_start: Weekday) -> Result<Date> {
+    let s = s.trim();
+    if let Ok(e) = Date::parse(s) {
+        return Ok(e);
+    }
+    let words: Vec<(usize, String)> = _words(s);
+    if words.is_empty() {
+        return Err(Error::InvalidDate(String::from(s)));
+    }
+
+    // A time of day can follow any day, with an optional `at`.
+    let mut end = words.len();
+    let mut time = None;
+    if let Some(t) = _time(&words[end - 1].1) {
+        time = Some(t);
+        end -= 1;
+        if end > 0 && words[end - 1].1 == "at" {
+            end -= 1;
+        }
+    }
+    let words = &words[..end];
+    let today = now.date();
+
+    let day = match words {
+        // Just a time is today, or tomorrow once it's passed.
+        [] => match time {
+            Some(t) if t < now.time() => today + Duration::days(1),
+            _ => today,
+        },
+        [(_, w)] if w == "today" || w == "tonight" => today,
+        [(_, w)] if w == "tomorrow" => today + Duration::days(1),
+        [(_, w)] if w == "yesterday" => today - Duration::days(1),
+        [(_, w)] if w == "eow" => end_of_week(today, week_start),
+        [(_, w)] if w == "eom" => _end_of_month(today),
+        [(_, a), (_, b), (_, c)] if a == "end" && b == "of" && c == "week" => {
+            end_of_week(today, week_start)
+        }
+        [(_, a), (_, b), (_, c)] if a == "end" && b == "of" && c == "month" => _end_of_month(today),
+        [(_, a), (_, b)] if a == "next" && b == "week" => today + Duration::days(7),
+        [(_, a), (_, b)] if a == "next" && b == "month" => _add_months(today, 1)?,
+        [(_, a), (column, b)] if a == "next" || a == "this" => match weekday(b) {
+            Some(w) => _next_weekday(today, w, a == "this"),
+            None => return Err(_unknown(*column, b)),
+        },
+        [(column, w)] => match (weekday(w), NaiveDate::parse_from_str(w, "%Y-%m-%d")) {
+            (Some(e), _) => _next_weekday(today, e, false),
+            (_, Ok(e)) => e,
+            _ => return Err(_unknown(*column, w)),
+        },
+        [(_, a), (column, n), (unit_column, unit)] if a == "in" => {
+            let n: i64 = match n.as_str() {
+                "a" | "an" | "one" => 1,
+                n => match n.parse() {
+                    Ok(e) => e,
+                    Err(_) => return Err(_unknown(*column, n)),
+                },
+            };
+            // Amounts too big for a date are invalid rather than wrapping.
+            let invalid = || Error::InvalidDate(String::from(s));
+            let later = |d: Option<Duration>| {
+                d.and_then(|x| now.checked_add_signed(x))
+                    .ok_or_else(invalid)
+            };
+            let later_day = |d: Option<Duration>| {
+                d.and_then(|x| today.checked_add_signed(x))
+                    .ok_or_else(invalid)
+            };
+            match unit.trim_end_matches('s') {
+                "minute" | "min" => return _at(later(Duration::try_minutes(n))?, time),
+                "hour" | "hr" | "h" => return _at(later(Duration::try_hours(n))?, time),
+                "day" | "d" => later_day(Duration::try_days(n))?,
+                "week" | "w" => later_day(Duration::try_weeks(n))?,
+                "month" => _add_months(today, n)?,
+                "year" | "y" => _add_months(today, n.checked_mul(12).ok_or_else(invalid)?)?,
+                _ => return Err(_unknown(*unit_column, unit)),
+            }
+        }
+        _ => _month_day(words, today)?,
+    };
+    match time {
+        Some(t) => Ok(Date::floating(day.and_time(t))),
+        None => Ok(Date::Day(day)),
+    }
+}
+
+/// The time now on the clock in a list's zone, see `TodoList::zone`, or here
+/// for None.
+pub fn now(zone: Option<Tz>) -> NaiveDateTime {
+    match zone {
+        Some(z) => Utc::now().with_timezone(&z).naive_local(),
+        None => Local::now().naive_local(),
+    }
+}
+
+/// The last day of the week starting on `week_start` that a day is in.
+pub fn end_of_week(d: NaiveDate, week_start: Weekday) -> NaiveDate {
+    let start = week_start.num_days_from_monday() as i64;
+    let day = d.weekday().num_days_from_monday() as i64;
+    d + Duration::days(6 - (day - start).rem_euclid(7))
+}
+
+/// Lowercase words with the column they start at, commas are spaces.
+fn _words(s: &str) -> Vec<(usize, String)> {
+    let mut words = Vec::new();
+    let mut word: Option<(usize, String)> = None;
+    for (i, c) in s.chars().enumerate() {
+        match (c.is_whitespace() || c == ',', &mut word) {
+            (true, _) => words.extend(word.take()),
+            (false, Some((_, w))) => w.extend(c.to_lowercase()),
+            (false, None) => word = Some((i + 1, c.to_lowercase().collect())),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+fn _unknown(column: usize, word: &str) -> Error {
+    Error::parse(
+        1,
+        column,
+        format!(
+            "Unknown date '{}', try today, friday, in 3 days, nov 3 or 2026-11-01 14:00",
+            word
+        ),
+    )
+}
+
+/// A moment to the minute, at a time of day instead if one was given.
+fn _at(d: NaiveDateTime, time: Option<NaiveTime>) -> Result<Date> {
+    let d = match time {
+        Some(t) => d.date().and_time(t),
+        None => d
+            .with_second(0)
+            .and_then(|x| x.with_nanosecond(0))
+            .unwrap_or(d),
+    };
+    Ok(Date::floating(d))
+}
+
+/// A time of day like `14:00`, `14:00:30`, `9am`, `9:30pm` or `noon`.
+fn _time(s: &str) -> Option<NaiveTime> {
+    match s {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return Some(NaiveTime::MIN),
+        _ => (),
+    }
+    for fmt in ["%H:%M", "%H:%M:%S"] {
+        if let Ok(e) = NaiveTime::parse_from_str(s, fmt) {
+            return Some(e);
+        }
+    }
+    let (s, pm) = match (s.strip_suffix("am"), s.strip_suffix("pm")) {
+        (Some(e), _) => (e, false),
+        (_, Some(e)) => (e, true),
+        _ => return None,
+    };
+    let (h, m) = match s.split_once(':') {
+        Some((h, m)) => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?),
+        None => (s.parse::<u32>().ok()?, 0),
+    };
+    if !(1..=12).contains(&h) {
+        return None;
+    }
+    NaiveTime::from_hms_opt(h % 12 + if pm { 12 } else { 0 }, m, 0)
+}
+
+pub(crate) fn weekday(s: &str) -> Option<Weekday> {
+    match s {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tues" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thur" | "thurs" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// The next day that's a weekday, if today is one it's a week from today
+/// unless `today` counts.
+fn _next_weekday(today: NaiveDate, w: Weekday, or_today: bool) -> NaiveDate {
+    let days = (w.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64)
+        .rem_euclid(7);
+    today + Duration::days(if days == 0 && !or_today { 7 } else { days })
+}
+
+fn _month(s: &str) -> Option<u32> {
+    let months = [
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ];
+    let s = s.trim_end_matches('.');
+    months
+        .iter()
+        .position(|m| s.len() >= 3 && m.starts_with(s))
+        .map(|i| i as u32 + 1)
+}
+
+/// A day of a month like `nov 3`, `3 nov` or `november 3 2027`. Without a
+/// year it's the next one from today.
+fn _month_day(words: &[(usize, String)], today: NaiveDate) -> Result<NaiveDate> {
+    let day = |s: &str| {
+        s.trim_end_matches("st")
+            .trim_end_matches("nd")
+            .trim_end_matches("rd")
+            .trim_end_matches("th")
+            .parse::<u32>()
+            .ok()
+    };
+    let (column, month, d, year) = match words {
+        [(_, m), (dc, d)] | [(dc, d), (_, m)] if _month(m).is_some() => {
+            (*dc, _month(m), day(d), None)
+        }
+        [(_, m), (dc, d), (_, y)] | [(dc, d), (_, m), (_, y)] if _month(m).is_some() => {
+            (*dc, _month(m), day(d), Some(y))
+        }
+        [(c, w), ..] => return Err(_unknown(*c, w)),
+        [] => return Err(Error::InvalidDate(String::new())),
+    };
+    let (month, d) = match (month, d) {
+        (Some(m), Some(d)) => (m, d),
+        _ => return Err(_unknown(column, &words[1].1)),
+    };
+    let invalid = || {
+        let text: Vec<&str> = words.iter().map(|(_, w)| w.as_str()).collect();
+        Error::InvalidDate(text.join(" "))
+    };
+    match year {
+        Some(y) => match y.parse() {
+            Ok(y) => NaiveDate::from_ymd_opt(y, month, d).ok_or_else(invalid),
+            Err(_) => Err(invalid()),
+        },
+        // The next one from today, a leap day can be up to 8 years away.
+        None => (0..=8)
+            .filter_map(|n| NaiveDate::from_ymd_opt(today.year().checked_add(n)?, month, d))
+            .find(|e| *e >= today)
+            .ok_or_else(invalid),
+    }
+}
+
+fn _end_of_month(d: NaiveDate) -> NaiveDate {
+    let first = d.with_day(1).unwrap_or(d);
+    match first.checked_add_months(Months::new(1)) {
+        Some(e) => e - Duration::days(1),
+        None => d,
+    }
+}
+
+fn _add_months(d: NaiveDate, n: i64) -> Result<NaiveDate> {
+    let months = Months::new(n.unsigned_abs().min(u32::MAX as u64) as u32);
+    let e = match n < 0 {
+        true => d.checked_sub_months(months),
+        false => d.checked_add_months(months),
+    };
+    e.ok_or_else(|| Error::InvalidDate(format!("in {} months", n)))
+}
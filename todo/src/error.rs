@@ -0,0 +1,126 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Malformed input, positions are 1 based.
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    InvalidDate(String),
+    InvalidRepeat(String),
+    InvalidDuration(String),
+    InvalidPriority(String),
+    InvalidStatus(String),
+    /// A title that wouldn't read back from markdown, see `Todo::set_title`.
+    InvalidTitle(String),
+    /// A tag that wouldn't read back from markdown, see `Todo::add_tag`.
+    InvalidTag(String),
+    /// No task with the given id.
+    NotFound(String),
+    /// An operation would break a dependency constraint, like completing a
+    /// task that is still blocked.
+    Dependency(String),
+    /// The tasks aren't in the state an operation expected, like undoing a
+    /// change after the file was edited elsewhere.
+    Conflict(String),
+    /// Another process holds the lock on a file, see `storage::Lock`.
+    Locked(PathBuf),
+}
+
+impl Error {
+    pub fn parse(line: usize, column: usize, message: impl Into<String>) -> Error {
+        Error::Parse {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Error {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// Attach a position to an error raised while parsing a value.
+    pub(crate) fn at(self, line: usize, column: usize) -> Error {
+        match self {
+            Error::Parse { .. } | Error::Io { .. } => self,
+            e => Error::parse(line, column, e.to_string()),
+        }
+    }
+
+    /// Shift a parse error, used when parsing a slice of a larger document.
+    pub(crate) fn offset(self, lines: usize, columns: usize) -> Error {
+        match self {
+            Error::Parse {
+                line,
+                column,
+                message,
+            } => Error::Parse {
+                line: line + lines,
+                column: column + columns,
+                message,
+            },
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse {
+                line,
+                column,
+                message,
+            } => write!(f, "parse error at {}:{}: {}", line, column, message),
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::InvalidDate(s) => write!(f, "invalid date '{}'", s),
+            Error::InvalidRepeat(s) => write!(f, "invalid repeat rule '{}'", s),
+            Error::InvalidDuration(s) => write!(f, "invalid duration '{}'", s),
+            Error::InvalidPriority(s) => write!(f, "invalid priority '{}'", s),
+            Error::InvalidStatus(s) => write!(f, "invalid status '{}'", s),
+            Error::InvalidTitle(s) => write!(
+                f,
+                "invalid title '{}', titles can't have line breaks, #tags or task emoji",
+                s
+            ),
+            Error::InvalidTag(s) => write!(
+                f,
+                "invalid tag '{}', tags can't have whitespace or task emoji or be only digits",
+                s
+            ),
+            Error::NotFound(s) => write!(f, "no task with id {}", s),
+            Error::Dependency(s) => write!(f, "dependency violation: {}", s),
+            Error::Conflict(s) => write!(f, "conflict: {}", s),
+            Error::Locked(p) => write!(f, "{} is locked by another process", p.display()),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Error {
+        Error::parse(e.line(), e.column(), e.to_string())
+    }
+}
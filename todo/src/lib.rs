@@ -1,4 +1,13 @@
+pub mod config;
+pub mod dates;
+pub mod error;
+pub mod query;
+pub mod storage;
 pub mod structs;
 
-pub use structs::Todo;
-pub use structs::TodoList;
+pub use config::Config;
+pub use error::{Error, Result};
+pub use query::Query;
+pub use structs::{Date, DependencyGraph, Document, History, Journal};
+pub use structs::{Dialect, Priority, Source, Status, Todo};
+pub use structs::{SortKey, TodoList, Vault};
@@ -1,244 +1,734 @@
-mod structs;
+use chrono::{Local, NaiveDate};
+use chrono_tz::Tz;
+use clap::{Args, Parser, Subcommand};
+use notify::{RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::error::Error as StdError;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration as StdDuration;
+use todo::config::{Format, Overrides};
+use todo::storage::{self, Lock};
+use todo::{
+    dates, Config, Date, Document, Error, History, Journal, Priority, Query, SortKey, Status, Todo,
+    TodoList, Vault,
+};
 
-use crate::structs::Todo;
-use crate::structs::TodoList;
-use std::io;
+/// Manage tasks in a JSON file or a markdown note.
+#[derive(Parser)]
+#[command(
+    name = "todo",
+    after_help = "Exit status is 0 on success, 1 on errors, 2 on usage errors, \
+                  3 when a task isn't found, 4 when a dependency blocks the change and \
+                  5 when another process has the file locked."
+)]
+struct Cli {
+    /// Task file, `.md` notes are edited in place, anything else is JSON.
+    /// Defaults to the `file` setting.
+    #[arg(short, long, global = true)]
+    file: Option<String>,
+    /// Config file, defaults to `todo/config.json` in the XDG config
+    /// directory.
+    #[arg(long, global = true)]
+    config: Option<String>,
+    /// Format of the task file, json, md or journal. Overrides the `format`
+    /// setting.
+    #[arg(long, global = true)]
+    file_format: Option<Format>,
+    /// strftime format dates are shown in. Overrides the `date_format`
+    /// setting.
+    #[arg(long, global = true)]
+    date_format: Option<String>,
+    /// First day of the week, like `sunday`. Overrides the `week_start`
+    /// setting.
+    #[arg(long, global = true)]
+    week_start: Option<String>,
+    /// How long before a task is due to remind about it, like `15min`.
+    /// Overrides the `reminders` settings.
+    #[arg(long, global = true)]
+    remind_before: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
 
-fn main() {
-    let mut tdl = TodoList::new();
+#[derive(Subcommand)]
+enum Command {
+    /// Add a task, printing its id.
+    Add {
+        title: String,
+        /// Read the date, repeat, tags, duration and priority from the
+        /// title, like `Write report tomorrow 5pm #work every monday 30m !high`.
+        #[arg(long)]
+        quick: bool,
+        #[command(flatten)]
+        fields: Fields,
+        /// Add it as a sub task of this task.
+        #[arg(long)]
+        parent: Option<String>,
+    },
+    /// Complete tasks, adding the next instance of repeating ones.
+    Done {
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Cancel tasks, they don't repeat.
+    Cancel {
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Remove tasks along with their sub tasks.
+    Rm {
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Change a task.
+    Edit {
+        id: String,
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long)]
+        status: Option<Status>,
+        #[command(flatten)]
+        fields: Fields,
+        /// Remove a tag.
+        #[arg(long)]
+        untag: Vec<String>,
+        /// Remove a dependency.
+        #[arg(long)]
+        no_depends_on: Vec<String>,
+    },
+    /// List tasks.
+    List {
+        /// Only tasks, at any depth, matching a query like `tag:work due<today`.
+        #[arg(short = 'q', long)]
+        filter: Option<String>,
+        /// Sort by due, start, scheduled, duration, title, completed or priority.
+        #[arg(short, long)]
+        sort: Option<SortKey>,
+        /// Only open tasks with nothing blocking them.
+        #[arg(long, conflicts_with = "order")]
+        ready: bool,
+        /// Open tasks in the order their dependencies allow.
+        #[arg(long)]
+        order: bool,
+        /// List the tasks in every note of a vault instead of the file.
+        #[arg(long)]
+        vault: Option<String>,
+    },
+    /// Undo the last change.
+    Undo,
+    /// Redo the last undone change.
+    Redo,
+    /// List changes that can be undone, the latest first.
+    History,
+    /// Show the time zone of the task list, or set it, like `Europe/Berlin`.
+    Zone {
+        zone: Option<String>,
+        /// Go back to using the `zone` setting or the local zone.
+        #[arg(long, conflicts_with = "zone")]
+        clear: bool,
+    },
+    /// Show what happened to tasks in a journal, or to one task.
+    Log { id: Option<String> },
+    /// Print every task.
+    Export {
+        /// json or md.
+        #[arg(long, default_value = "md")]
+        format: Format,
+    },
+    /// Watch a vault, printing the agenda whenever its tasks change.
+    Watch {
+        /// Defaults to the `vault` setting.
+        #[arg(long)]
+        vault: Option<String>,
+        /// Only show tasks matching this query.
+        query: Vec<String>,
+    },
+}
 
-    loop {
-        println!("{}", tdl.to_string());
+/// Fields shared by `add` and `edit`.
+#[derive(Args)]
+struct Fields {
+    /// Due date, like `friday`, `in 3 days`, `nov 3`, `tomorrow 9am`,
+    /// yyyy-mm-dd with an optional hh:mm, or in the `date_format` setting.
+    #[arg(long)]
+    due: Option<String>,
+    /// Start date, read like the due date.
+    #[arg(long)]
+    start: Option<String>,
+    /// Scheduled date, read like the due date.
+    #[arg(long)]
+    scheduled: Option<String>,
+    #[arg(short, long)]
+    priority: Option<Priority>,
+    /// Like `every weekday`, `every month on the 15th`, `every 2nd tuesday
+    /// for 3 times`, `every week when done`, an `RRULE:FREQ=...`, `1 week`,
+    /// `3 days from completed` or a cron expression.
+    #[arg(short, long)]
+    repeat: Option<String>,
+    /// Like `30min` or `1h 30min`.
+    #[arg(long)]
+    duration: Option<String>,
+    #[arg(short, long = "tag")]
+    tags: Vec<String>,
+    /// Id of a task that must be done first.
+    #[arg(long)]
+    depends_on: Vec<String>,
+}
 
-        let mut inp = String::new();
-        io::stdin()
-            .read_line(&mut inp)
-            .expect("Failed to read line.");
+impl Fields {
+    fn apply(&self, tdl: &mut TodoList, id: &str, config: &Config) -> todo::Result<()> {
+        let now = tdl.now();
+        let t = match tdl.get_by_id_mut(id) {
+            Some(e) => e,
+            None => return Err(Error::NotFound(String::from(id))),
+        };
+        if let Some(e) = &self.due {
+            t.set_due(config.read_date(e, now)?);
+        }
+        if let Some(e) = &self.start {
+            t.set_start(config.read_date(e, now)?);
+        }
+        if let Some(e) = &self.scheduled {
+            t.set_scheduled(config.read_date(e, now)?);
+        }
+        if let Some(e) = self.priority {
+            t.set_priority(e);
+        }
+        if let Some(e) = &self.repeat {
+            t.set_repeat(e.clone())?;
+        }
+        if let Some(e) = &self.duration {
+            t.set_duration(e.clone())?;
+        }
+        for e in &self.tags {
+            let tag = String::from(e.trim_start_matches('#'));
+            if !t.tags().contains(&tag) {
+                t.add_tag(tag)?;
+            }
+        }
+        for e in &self.depends_on {
+            let dependency = resolve(tdl, e)?;
+            tdl.add_dependency(id, &dependency)?;
+        }
+        Ok(())
+    }
+}
+
+/// A task file, markdown notes keep everything around their tasks.
+enum Store {
+    Json(TodoList),
+    Markdown(Document),
+    Journal(Journal),
+}
+
+impl Store {
+    /// Open a task file, a missing file is an empty list.
+    fn open(file: &str, format: Format) -> todo::Result<Store> {
+        match (Path::new(file).exists(), format) {
+            (false, Format::Json) => Ok(Store::Json(TodoList::new())),
+            (false, Format::Markdown) => Ok(Store::Markdown(Document::from_markdown("")?)),
+            (true, Format::Json) => Ok(Store::Json(TodoList::from_json_file(file)?)),
+            (true, Format::Markdown) => Ok(Store::Markdown(Document::from_markdown_file(file)?)),
+            (_, Format::Journal) => Ok(Store::Journal(Journal::open(file)?)),
+        }
+    }
+
+    fn save(&mut self, file: &str, config: &Config) -> todo::Result<()> {
+        match self {
+            Store::Json(tdl) => tdl.to_json_file_with(file, config.backups),
+            Store::Markdown(doc) => doc.to_markdown_file_with(file, config.backups),
+            Store::Journal(j) => {
+                j.save()?;
+                if j.pending() > config.compact_after {
+                    j.compact()?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn tasks(&self) -> &TodoList {
+        match self {
+            Store::Json(tdl) => tdl,
+            Store::Markdown(doc) => doc.tasks(),
+            Store::Journal(j) => j.tasks(),
+        }
+    }
 
-        let (action, n) = match inp.find(' ') {
-            Some(e) => (String::from(&inp[..e]), String::from(&inp[e + 1..])),
-            None => (String::from(inp.trim()), String::from("")),
+    fn tasks_mut(&mut self) -> &mut TodoList {
+        match self {
+            Store::Json(tdl) => tdl,
+            Store::Markdown(doc) => doc.tasks_mut(),
+            Store::Journal(j) => j.tasks_mut(),
+        }
+    }
+
+    /// Complete a task, putting its next instance before it.
+    fn complete(&mut self, id: &str) -> todo::Result<()> {
+        let tdl = match self {
+            Store::Markdown(doc) => return doc.complete(id),
+            Store::Json(tdl) => tdl,
+            Store::Journal(j) => j.tasks_mut(),
+        };
+        match tdl.complete(id)? {
+            Some(next) => tdl.insert_before(id, next),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Resolve a task id, falling back to its index in the top level list.
+fn resolve(tdl: &TodoList, key: &str) -> todo::Result<String> {
+    if tdl.get_by_id(key).is_some() {
+        return Ok(String::from(key));
+    }
+    match key.parse::<usize>() {
+        Ok(i) if i < tdl.len() => Ok(String::from(tdl[i].id())),
+        _ => Err(Error::NotFound(String::from(key))),
+    }
+}
+
+fn find<'a>(tdl: &'a mut TodoList, key: &str) -> todo::Result<&'a mut Todo> {
+    let id = resolve(tdl, key)?;
+    match tdl.get_by_id_mut(&id) {
+        Some(e) => Ok(e),
+        None => Err(Error::NotFound(id)),
+    }
+}
+
+/// Open tasks with a due or scheduled date up to the end of next week, and
+/// any that are overdue, with the note they're in.
+fn print_agenda(v: &Vault, q: &Query, config: &Config, zone: Option<Tz>) {
+    let now = dates::now(zone);
+    let today = now.date();
+    let this_week = config.end_of_week(today);
+    let next_week = this_week + chrono::Duration::days(7);
+    let mut tasks: Vec<(NaiveDate, &Todo)> = v
+        .walk()
+        .filter(|t| t.is_open() && q.matches_in(t, zone, now))
+        .filter_map(|t| {
+            let d = t.due_date().or(t.scheduled_date());
+            d.map(|x| (x.date_in(zone), t))
+        })
+        .filter(|(d, _)| *d <= next_week)
+        .collect();
+    tasks.sort_by_key(|(d, _)| *d);
+
+    println!("== {} ==", now.format("%Y-%m-%d %H:%M:%S"));
+    for (path, e) in v.errors() {
+        println!("{}: {}", path, e);
+    }
+    for (heading, from, to) in [
+        ("Overdue", NaiveDate::MIN, today.pred_opt().unwrap_or(today)),
+        ("Today", today, today),
+        ("This week", today.succ_opt().unwrap_or(today), this_week),
+        (
+            "Next week",
+            this_week.succ_opt().unwrap_or(this_week),
+            next_week,
+        ),
+    ] {
+        let section: Vec<&(NaiveDate, &Todo)> = tasks
+            .iter()
+            .filter(|(d, _)| from <= *d && *d <= to)
+            .collect();
+        if section.is_empty() {
+            continue;
+        }
+        println!("{}:", heading);
+        for (d, t) in section {
+            let d = d.format(&config.date_format);
+            match t.source() {
+                Some(s) => println!(
+                    "  {} {} ({}:{})",
+                    d,
+                    t.title(),
+                    s.file.as_deref().unwrap_or(""),
+                    s.line
+                ),
+                None => println!("  {} {}", d, t.title()),
+            }
+        }
+    }
+}
+
+/// Print a reminder for open tasks due within `before`, or due today for
+/// tasks due on a whole day, once per task.
+fn remind(v: &Vault, zone: Option<Tz>, before: chrono::Duration, reminded: &mut HashSet<String>) {
+    let now = dates::now(zone);
+    for t in v.walk().filter(|t| t.is_open()) {
+        let (due, when) = match t.due_date() {
+            Some(Date::Day(d)) => (*d == now.date(), String::from("today")),
+            Some(d) => {
+                let due = d.naive_in(zone);
+                let soon = now <= due && due - before <= now;
+                (soon, format!("at {}", due.format("%H:%M")))
+            }
+            None => continue,
         };
+        if due && reminded.insert(String::from(t.id())) {
+            println!("Reminder: {} is due {}", t.title(), when);
+        }
+    }
+}
+
+/// Keep a vault's index up to date as notes change, printing the agenda,
+/// filtered by a query, whenever tasks change.
+fn watch(dir: &str, filter: &str, config: &Config) -> Result<(), Box<dyn StdError>> {
+    let q = Query::from_str(filter)?;
+    let before = config.reminder_before()?;
+    let zone = config.zone()?;
+    let index = format!("{}/.todo-index.json", dir);
+    let mut v = open_vault(dir)?;
+    print_agenda(&v, &q, config, zone);
 
-        let n: Option<usize> = match n.trim().parse() {
-            Ok(e) => Some(e),
-            Err(_) => None,
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(Path::new(dir), RecursiveMode::Recursive)?;
+
+    let mut reminded = HashSet::new();
+    loop {
+        if let Some(e) = before {
+            remind(&v, zone, e, &mut reminded);
+        }
+        // Wake up now and then to check for reminders.
+        let event: notify::Event = match rx.recv_timeout(StdDuration::from_secs(30)) {
+            Ok(e) => e?,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
         };
+        let note = event.paths.iter().any(|p| {
+            let hidden = p.file_name().map(|x| x.to_string_lossy().starts_with('.'));
+            p.extension().map(|x| x == "md") == Some(true) && hidden == Some(false)
+        });
+        if !note {
+            continue;
+        }
+        // Editors often write a file several times when saving.
+        while rx.recv_timeout(StdDuration::from_millis(200)).is_ok() {}
+
+        let changes = v.refresh()?;
+        if changes.is_empty() {
+            continue;
+        }
+        for (sym, paths) in [
+            ('+', &changes.added),
+            ('~', &changes.modified),
+            ('-', &changes.removed),
+        ] {
+            for p in paths {
+                println!("{} {}", sym, p);
+            }
+        }
+        v.save_index(&index)?;
+        print_agenda(&v, &q, config, zone);
+    }
+}
+
+/// Print the tasks matching `filter`, at any depth. With `ready` or `order`
+/// only the open ones, the ready ones or in dependency order.
+fn list(
+    mut tdl: TodoList,
+    filter: &Option<String>,
+    sort: Option<SortKey>,
+    ready: bool,
+    order: bool,
+) -> Result<(), Box<dyn StdError>> {
+    if let Some(e) = sort {
+        tdl.sort_by(e);
+    }
+    let q = match filter {
+        Some(e) => Some(Query::from_str(e)?),
+        None => None,
+    };
+    let mut tasks = match (ready, order, &q) {
+        (true, _, _) => tdl.ready(),
+        (_, true, _) => tdl.topological_order()?,
+        (_, _, Some(e)) => tdl.query(e, true),
+        _ => tdl.iter().collect(),
+    };
+    if let Some(e) = &q {
+        let now = tdl.now();
+        tasks.retain(|t| e.matches_in(t, tdl.zone(), now));
+    }
+    let mut out = io::stdout().lock();
+    for t in tasks {
+        writeln!(out, "{}", t)?;
+    }
+    Ok(())
+}
 
-        match (action.as_str(), n) {
-            ("n", _) => {
-                println!("enter new title: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                inp = String::from(inp.trim());
-                tdl.add(Todo::from_title(inp));
-            }
-            ("c", Some(e)) => match tdl[e].complete() {
-                Some(e) => tdl.add(e),
-                None => (),
-            },
-            ("cd", Some(e)) => {
-                println!("Enter yyyy-mm-dd: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                inp = String::from(inp.trim());
-                tdl[e].set_completed_iso8601(inp)
-            }
-            ("sd", Some(e)) => {
-                println!("Enter yyyy-mm-dd: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                inp = String::from(inp.trim());
-                tdl[e].set_start_iso8601(inp)
-            }
-            ("d", Some(e)) => {
-                println!("Enter yyyy-mm-dd: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                inp = String::from(inp.trim());
-                tdl[e].set_due_iso8601(inp)
-            }
-            ("r", Some(e)) => {
-                println!("Enter duration: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                inp = String::from(inp.trim());
-                tdl[e].set_repeat(inp)
-            }
-            ("t", Some(e)) => {
-                println!("enter new title: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                inp = String::from(inp.trim());
-                tdl[e].set_title(inp);
-            }
-            ("dur", Some(e)) => {
-                println!("Enter duration: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                inp = String::from(inp.trim());
-                tdl[e].set_duration(inp)
-            }
-            ("tag", Some(e)) => {
-                println!("Enter tag: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                inp = String::from(inp.trim());
-                tdl[e].add_tag(inp);
-            }
-            ("utag", Some(e)) => {
-                println!("Enter tag: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                inp = String::from(inp.trim());
-                tdl[e].remove_tag(inp);
-            }
-            ("dep", Some(e)) => {
-                println!("path: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                let split = inp.trim().split(" ").collect::<Vec<&str>>();
-                let path: Vec<usize> = match split[0].len() {
-                    0 => Vec::new(),
-                    _ => split
-                        .into_iter()
-                        .map(|x| match x.trim().parse() {
-                            Ok(e) => e,
-                            Err(_) => panic!("Invalid path"),
-                        })
-                        .collect(),
-                };
-                println!("Depends on: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                let n: usize = match inp.trim().parse() {
-                    Ok(e) => e,
-                    Err(_) => continue,
-                };
-                let t = tdl.remove(n);
-                let mut p = &mut tdl[e];
-                for i in path {
-                    p = &mut p.get_dependencies()[i];
+/// Open a vault, using and updating the index `watch` keeps in it.
+fn open_vault(dir: &str) -> todo::Result<Vault> {
+    // The index is hidden so it isn't indexed itself.
+    let index = format!("{}/.todo-index.json", dir);
+    let v = Vault::open_with_index(dir, &index)?;
+    v.save_index(&index)?;
+    Ok(v)
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn StdError>> {
+    let config = match &cli.config {
+        Some(e) => Config::from_file(Path::new(e))?.with_env()?,
+        None => Config::load()?,
+    };
+    let config = config.with_overrides(&Overrides {
+        file: cli.file,
+        format: cli.file_format,
+        date_format: cli.date_format,
+        week_start: cli.week_start,
+        reminder_before: cli.remind_before,
+    })?;
+    let file = config.file.clone();
+    // Hold the lock from reading the file until the changes are written, so
+    // another process can't change it in between.
+    let _lock = match &cli.command {
+        Command::Watch { vault, query } => {
+            let vault = match vault.as_ref().or(config.vault.as_ref()) {
+                Some(e) => e,
+                None => return Err("no vault given and no vault setting".into()),
+            };
+            return watch(vault, &query.join(" "), &config);
+        }
+        Command::List {
+            filter,
+            sort,
+            ready,
+            order,
+            vault: Some(dir),
+        } => {
+            let v = open_vault(dir)?;
+            for (path, e) in v.errors() {
+                eprintln!("todo: {}: {}", path, e);
+            }
+            // Notes use the configured zone.
+            let mut tdl = v.merged();
+            tdl.set_zone(config.zone()?);
+            return list(tdl, filter, *sort, *ready, *order);
+        }
+        Command::List { .. } | Command::Export { .. } | Command::History | Command::Log { .. } => {
+            None
+        }
+        _ => {
+            // The default file lives in a directory that may not exist yet.
+            if let Some(dir) = Path::new(&file).parent() {
+                if !dir.as_os_str().is_empty() {
+                    fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
                 }
-                p.get_dependencies().add(t);
-            }
-            ("cdep", Some(e)) => {
-                println!("path: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                let split = inp.trim().split(" ").collect::<Vec<&str>>();
-                let path: Vec<usize> = match split[0].len() {
-                    0 => Vec::new(),
-                    _ => split
-                        .into_iter()
-                        .map(|x| match x.trim().parse() {
-                            Ok(e) => e,
-                            Err(_) => panic!("Invalid path"),
-                        })
-                        .collect(),
-                };
-                let mut t = &mut tdl[e];
-                for i in path {
-                    t = &mut t.get_dependencies()[i]
+            }
+            Some(Lock::acquire(&file, StdDuration::from_secs(5))?)
+        }
+    };
+    let mut store = Store::open(&file, config.format_of(&file))?;
+    let history_file = storage::history_path(&file).to_string_lossy().into_owned();
+    let mut history = History::from_file(&history_file, config.history)?;
+    // Lists without their own zone use the configured one, which isn't
+    // saved with them.
+    let mut default_zone = match store.tasks().zone() {
+        Some(_) => None,
+        None => config.zone()?,
+    };
+    if default_zone.is_some() {
+        store.tasks_mut().set_zone(default_zone);
+    }
+    let before = store.tasks().clone();
+    let label = match &cli.command {
+        Command::Add { title, .. } => format!("add {}", title),
+        Command::Done { ids } => format!("done {}", ids.join(" ")),
+        Command::Cancel { ids } => format!("cancel {}", ids.join(" ")),
+        Command::Rm { ids } => format!("rm {}", ids.join(" ")),
+        Command::Edit { id, .. } => format!("edit {}", id),
+        _ => String::new(),
+    };
+
+    match cli.command {
+        Command::Add {
+            title,
+            quick,
+            fields,
+            parent,
+        } => {
+            let t = match quick {
+                true => {
+                    let now = store.tasks().now();
+                    Todo::parse_quick_add_at(&title, now, config.week_start)?
                 }
-                t.complete();
-            }
-            ("sub", Some(e)) => {
-                println!("path: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                let split = inp.trim().split(" ").collect::<Vec<&str>>();
-                let path: Vec<usize> = match split[0].len() {
-                    0 => Vec::new(),
-                    _ => split
-                        .into_iter()
-                        .map(|x| match x.trim().parse() {
-                            Ok(e) => e,
-                            Err(_) => panic!("Invalid path"),
-                        })
-                        .collect(),
-                };
-                println!("sub task: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                let n: usize = match inp.trim().parse() {
-                    Ok(e) => e,
-                    Err(_) => continue,
-                };
-                let t = tdl.remove(n);
-                let mut p = &mut tdl[e];
-                for i in path {
-                    p = &mut p.get_sub_tasks()[i];
+                false => {
+                    let mut t = Todo::from_title(String::new());
+                    t.set_title(title)?;
+                    t
                 }
-                p.get_sub_tasks().add(t);
-            }
-            ("csub", Some(e)) => {
-                println!("path: ");
-                inp = String::new();
-                io::stdin()
-                    .read_line(&mut inp)
-                    .expect("Failed to read line");
-                let split = inp.trim().split(" ").collect::<Vec<&str>>();
-                let path: Vec<usize> = match split[0].len() {
-                    0 => Vec::new(),
-                    _ => split
-                        .into_iter()
-                        .map(|x| match x.trim().parse() {
-                            Ok(e) => e,
-                            Err(_) => panic!("Invalid path"),
-                        })
-                        .collect(),
-                };
-                let mut t = &mut tdl[e];
-                for i in path {
-                    t = &mut t.get_sub_tasks()[i];
+            };
+            let id = String::from(t.id());
+            let tdl = store.tasks_mut();
+            match parent {
+                Some(e) => find(tdl, &e)?.get_sub_tasks().add(t),
+                None => tdl.add(t),
+            }
+            fields.apply(tdl, &id, &config)?;
+            println!("{}", id);
+        }
+        Command::Done { ids } => {
+            for key in ids {
+                let id = resolve(store.tasks(), &key)?;
+                store.complete(&id)?;
+            }
+        }
+        Command::Cancel { ids } => {
+            for key in ids {
+                find(store.tasks_mut(), &key)?.cancel();
+            }
+        }
+        Command::Rm { ids } => {
+            for key in ids {
+                let id = resolve(store.tasks(), &key)?;
+                store.tasks_mut().remove_by_id(&id);
+            }
+        }
+        Command::Edit {
+            id,
+            title,
+            status,
+            fields,
+            untag,
+            no_depends_on,
+        } => {
+            let tdl = store.tasks_mut();
+            let id = resolve(tdl, &id)?;
+            if let Some(e) = status {
+                tdl.set_status(&id, e)?;
+            }
+            let t = find(tdl, &id)?;
+            if let Some(e) = title {
+                t.set_title(e)?;
+            }
+            for e in untag {
+                t.remove_tag(String::from(e.trim_start_matches('#')));
+            }
+            for e in no_depends_on {
+                t.remove_dependency(&e);
+            }
+            fields.apply(tdl, &id, &config)?;
+        }
+        Command::List {
+            filter,
+            sort,
+            ready,
+            order,
+            ..
+        } => {
+            return list(store.tasks().clone(), &filter, sort, ready, order);
+        }
+        Command::Undo => match history.undo(store.tasks_mut())? {
+            Some(e) => println!("Undid {}", e.label()),
+            None => return Err("nothing to undo".into()),
+        },
+        Command::Redo => match history.redo(store.tasks_mut())? {
+            Some(e) => println!("Redid {}", e.label()),
+            None => return Err("nothing to redo".into()),
+        },
+        Command::History => {
+            let mut out = io::stdout().lock();
+            for e in history.undo_steps() {
+                writeln!(
+                    out,
+                    "{} {}",
+                    e.time().format("%Y-%m-%d %H:%M:%S"),
+                    e.label()
+                )?;
+            }
+            return Ok(());
+        }
+        Command::Zone {
+            zone: None,
+            clear: false,
+        } => {
+            match store.tasks().zone() {
+                Some(e) => println!("{}", e),
+                None => println!("local ({})", Local::now().offset()),
+            }
+            return Ok(());
+        }
+        Command::Zone { zone, .. } => {
+            if matches!(store, Store::Markdown(_)) {
+                return Err("notes can't have a zone, use the zone setting".into());
+            }
+            let zone = match zone {
+                Some(e) => match Tz::from_str(&e) {
+                    Ok(e) => Some(e),
+                    Err(_) => return Err(format!("unknown time zone {}", e).into()),
+                },
+                None => None,
+            };
+            store.tasks_mut().set_zone(zone);
+            // Changing the zone is saved, unlike the configured one.
+            default_zone = None;
+        }
+        Command::Log { id } => {
+            if !matches!(store, Store::Journal(_)) {
+                return Err("only journals have a log, use a .jsonl file".into());
+            }
+            // Deleted tasks can only be given by id.
+            let id = id.map(|x| resolve(store.tasks(), &x).unwrap_or(x));
+            let mut out = io::stdout().lock();
+            for e in Journal::entries(&file)? {
+                if id.is_none() || id.as_deref() == Some(e.event().id()) {
+                    writeln!(
+                        out,
+                        "{} {}",
+                        e.time().format("%Y-%m-%d %H:%M:%S"),
+                        e.event()
+                    )?;
+                }
+            }
+            return Ok(());
+        }
+        Command::Export { format } => {
+            let s = match format {
+                Format::Json => store.tasks().to_json()?,
+                Format::Markdown => store.tasks().to_markdown(),
+                Format::Journal => return Err("can't export as a journal, use json".into()),
+            };
+            writeln!(io::stdout(), "{}", s)?;
+            return Ok(());
+        }
+        Command::Watch { .. } => (),
+    }
+
+    if !label.is_empty() {
+        history.push(&label, &before, store.tasks())?;
+    }
+    if default_zone.is_some() {
+        store.tasks_mut().set_zone(None);
+    }
+    store.save(&file, &config)?;
+    history.to_file(&history_file)?;
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // Output piped into something like `head` that stopped reading.
+            if let Some(e) = e.downcast_ref::<io::Error>() {
+                if e.kind() == io::ErrorKind::BrokenPipe {
+                    return ExitCode::SUCCESS;
                 }
-                t.complete();
-            }
-            ("save", _) => match tdl.to_json_file("test.json") {
-                Err(e) => print!("{}", e),
-                Ok(_) => continue,
-            },
-            ("load", _) => {
-                tdl = TodoList::from_json_file("test.json");
-            }
-            ("savemd", _) => match tdl.to_markdown_file("test.md") {
-                Err(e) => print!("{}", e),
-                Ok(_) => continue,
-            },
-            ("loadmd", _) => {
-                tdl = TodoList::from_markdown_file("test.md");
-            }
-            ("q", _) => break,
-            (_, _) => continue,
+            }
+            eprintln!("todo: {}", e);
+            ExitCode::from(match e.downcast_ref::<Error>() {
+                Some(Error::NotFound(_)) => 3,
+                Some(Error::Dependency(_)) => 4,
+                Some(Error::Locked(_)) => 5,
+                _ => 1,
+            })
         }
     }
 }
@@ -0,0 +1,455 @@
+use crate::{Error, Priority, Result, Status, Todo, TodoList};
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime};
+use chrono_tz::Tz;
+use std::fmt;
+use std::str::FromStr;
+
+/// Comparison operator used by date terms.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cmp {
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    Ge,
+    Gt,
+}
+
+impl Cmp {
+    fn test<T: PartialOrd>(&self, a: T, b: T) -> bool {
+        match self {
+            Cmp::Lt => a < b,
+            Cmp::Le => a <= b,
+            Cmp::Eq => a == b,
+            Cmp::Ne => a != b,
+            Cmp::Ge => a >= b,
+            Cmp::Gt => a > b,
+        }
+    }
+}
+
+impl fmt::Display for Cmp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Cmp::Lt => "<",
+            Cmp::Le => "<=",
+            Cmp::Eq => "=",
+            Cmp::Ne => "!=",
+            Cmp::Ge => ">=",
+            Cmp::Gt => ">",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DateField {
+    Due,
+    Start,
+    Scheduled,
+    Created,
+    Completed,
+    Cancelled,
+}
+
+/// A date in a query, relative dates are resolved when the query is run.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DateValue {
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    /// Days from today.
+    Relative(i64),
+}
+
+impl FromStr for DateValue {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<DateValue> {
+        match s.to_lowercase().as_str() {
+            "today" => return Ok(DateValue::Relative(0)),
+            "tomorrow" => return Ok(DateValue::Relative(1)),
+            "yesterday" => return Ok(DateValue::Relative(-1)),
+            _ => (),
+        }
+        if let Ok(e) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return Ok(DateValue::Date(e));
+        }
+        for fmt in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"] {
+            if let Ok(e) = NaiveDateTime::parse_from_str(s, fmt) {
+                return Ok(DateValue::DateTime(e));
+            }
+        }
+        Err(Error::InvalidDate(String::from(s)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Field {
+    Due,
+    Start,
+    Scheduled,
+    Created,
+    Completed,
+    Cancelled,
+    Repeat,
+    Duration,
+    Tags,
+    Dependencies,
+    SubTasks,
+}
+
+impl FromStr for Field {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Field> {
+        match s.to_lowercase().as_str() {
+            "due" => Ok(Field::Due),
+            "start" => Ok(Field::Start),
+            "scheduled" => Ok(Field::Scheduled),
+            "created" => Ok(Field::Created),
+            "completed" | "done" => Ok(Field::Completed),
+            "cancelled" => Ok(Field::Cancelled),
+            "repeat" => Ok(Field::Repeat),
+            "duration" => Ok(Field::Duration),
+            "tag" | "tags" => Ok(Field::Tags),
+            "dep" | "deps" | "dependencies" => Ok(Field::Dependencies),
+            "sub" | "subs" | "subtasks" => Ok(Field::SubTasks),
+            _ => Err(Error::parse(1, 1, format!("Unknown field {}", s))),
+        }
+    }
+}
+
+/// A predicate tree over tasks, parsed from text like
+/// `tag:work due<2026-11-01 not done has:repeat title~"report" priority>=high`
+/// or `section:"Sprint 12" open`.
+///
+/// Terms separated by spaces must all match, `or` separates alternatives,
+/// `not` (or `-`) negates the next term and parentheses group terms.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Query {
+    All,
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    Done,
+    /// Not done or cancelled.
+    Open,
+    /// Open and past its due date, see `Todo::is_overdue_at`.
+    Overdue,
+    Status(Status),
+    Tag(String),
+    Id(String),
+    /// Case insensitive substring of the title.
+    Title(String),
+    /// Under a heading with this text, at any level, ignoring case.
+    Section(String),
+    Has(Field),
+    Date(DateField, Cmp, DateValue),
+    Priority(Cmp, Priority),
+}
+
+impl Query {
+    pub fn matches(&self, t: &Todo) -> bool {
+        self.matches_at(t, Local::now().naive_local())
+    }
+
+    /// Run the query with relative dates resolved against `now`.
+    pub fn matches_at(&self, t: &Todo, now: NaiveDateTime) -> bool {
+        self.matches_in(t, None, now)
+    }
+
+    /// Run the query with times on the clock in a list's zone, see
+    /// `TodoList::zone`, and relative dates resolved against `now` there.
+    pub fn matches_in(&self, t: &Todo, zone: Option<Tz>, now: NaiveDateTime) -> bool {
+        match self {
+            Query::All => true,
+            Query::And(v) => v.iter().all(|q| q.matches_in(t, zone, now)),
+            Query::Or(v) => v.iter().any(|q| q.matches_in(t, zone, now)),
+            Query::Not(q) => !q.matches_in(t, zone, now),
+            Query::Done => t.is_completed(),
+            Query::Open => t.is_open(),
+            Query::Overdue => t.is_overdue_in(zone, now),
+            Query::Status(s) => t.status() == *s,
+            Query::Tag(tag) => t.tags().iter().any(|x| {
+                let x = x.to_lowercase();
+                x == *tag || x.starts_with(&format!("{}/", tag))
+            }),
+            Query::Id(id) => t.id() == id,
+            Query::Title(s) => t.title().to_lowercase().contains(s),
+            Query::Section(s) => t.source().map(|x| x.is_under(s)) == Some(true),
+            Query::Has(f) => match f {
+                Field::Due => t.due().is_some(),
+                Field::Start => t.start().is_some(),
+                Field::Scheduled => t.scheduled().is_some(),
+                Field::Created => t.created().is_some(),
+                Field::Completed => t.completed().is_some(),
+                Field::Cancelled => t.cancelled().is_some(),
+                Field::Repeat => t.has_repeat(),
+                Field::Duration => t.duration().is_some(),
+                Field::Tags => !t.tags().is_empty(),
+                Field::Dependencies => !t.dependencies().is_empty(),
+                Field::SubTasks => !t.sub_tasks().is_empty(),
+            },
+            Query::Priority(cmp, p) => cmp.test(t.priority(), *p),
+            Query::Date(f, cmp, v) => {
+                let d = match f {
+                    DateField::Due => t.due_date(),
+                    DateField::Start => t.start_date(),
+                    DateField::Scheduled => t.scheduled_date(),
+                    DateField::Created => t.created_date(),
+                    DateField::Completed => t.completed_date(),
+                    DateField::Cancelled => t.cancelled_date(),
+                };
+                let d = match d {
+                    Some(e) => e.naive_in(zone),
+                    None => return false,
+                };
+                match v {
+                    DateValue::Date(e) => cmp.test(d.date(), *e),
+                    DateValue::DateTime(e) => cmp.test(d, *e),
+                    DateValue::Relative(n) => cmp.test(d.date(), now.date() + Duration::days(*n)),
+                }
+            }
+        }
+    }
+
+    /// Tasks in the list matching the query, optionally including sub tasks,
+    /// with times on the clock in the list's zone.
+    pub fn run<'a>(&self, tdl: &'a TodoList, recursive: bool) -> Vec<&'a Todo> {
+        let zone = tdl.zone();
+        let now = tdl.now();
+        if recursive {
+            tdl.walk()
+                .map(|(_, t)| t)
+                .filter(|t| self.matches_in(t, zone, now))
+                .collect()
+        } else {
+            tdl.filter(|t| self.matches_in(t, zone, now))
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Query> {
+        let mut p = Parser {
+            tokens: tokenize(s)?,
+            i: 0,
+        };
+        if p.tokens.is_empty() {
+            return Ok(Query::All);
+        }
+        let q = p.or()?;
+        match p.tokens.get(p.i) {
+            Some(t) => Err(Error::parse(1, t.column, format!("Unexpected {}", t.text))),
+            None => Ok(q),
+        }
+    }
+}
+
+struct Token {
+    text: String,
+    column: usize,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().enumerate().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c == '(' || c == ')' {
+            tokens.push(Token {
+                text: String::from(c),
+                column: i + 1,
+            });
+            continue;
+        }
+        let mut text = String::new();
+        let mut quoted = c == '"';
+        if !quoted {
+            text.push(c);
+        }
+        while let Some((_, c)) = chars.peek() {
+            let c = *c;
+            if !quoted && (c.is_whitespace() || c == '(' || c == ')') {
+                break;
+            }
+            chars.next();
+            if c == '"' {
+                quoted = !quoted;
+            } else {
+                text.push(c);
+            }
+        }
+        if quoted {
+            return Err(Error::parse(1, i + 1, "Unterminated quote"));
+        }
+        tokens.push(Token {
+            text,
+            column: i + 1,
+        });
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    i: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.i).map(|t| t.text.as_str())
+    }
+
+    fn or(&mut self) -> Result<Query> {
+        let mut v = vec![self.and()?];
+        while self.peek().map(|x| x.to_lowercase()) == Some(String::from("or")) {
+            self.i += 1;
+            v.push(self.and()?);
+        }
+        Ok(match v.len() {
+            1 => v.remove(0),
+            _ => Query::Or(v),
+        })
+    }
+
+    fn and(&mut self) -> Result<Query> {
+        let mut v = Vec::new();
+        while let Some(t) = self.peek() {
+            if t == ")" || t.to_lowercase() == "or" {
+                break;
+            }
+            v.push(self.unary()?);
+        }
+        Ok(match v.len() {
+            0 => {
+                let column = self.tokens.get(self.i).map(|t| t.column).unwrap_or(1);
+                return Err(Error::parse(1, column, "Expected a term"));
+            }
+            1 => v.remove(0),
+            _ => Query::And(v),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Query> {
+        let t = &self.tokens[self.i];
+        let column = t.column;
+        if t.text.to_lowercase() == "not" {
+            self.i += 1;
+            if self.peek().is_none() {
+                return Err(Error::parse(1, column, "Expected a term after not"));
+            }
+            return Ok(Query::Not(Box::new(self.unary()?)));
+        }
+        if t.text == "(" {
+            self.i += 1;
+            let q = self.or()?;
+            if self.peek() != Some(")") {
+                return Err(Error::parse(1, column, "Unclosed parenthesis"));
+            }
+            self.i += 1;
+            return Ok(q);
+        }
+        let text = t.text.clone();
+        self.i += 1;
+        if let Some(rest) = text.strip_prefix('-') {
+            return Ok(Query::Not(Box::new(term(rest, column + 1)?)));
+        }
+        term(&text, column)
+    }
+}
+
+fn term(s: &str, column: usize) -> Result<Query> {
+    let lower = s.to_lowercase();
+    match lower.as_str() {
+        "done" | "completed" => return Ok(Query::Done),
+        "cancelled" => return Ok(Query::Status(Status::Cancelled)),
+        "open" | "todo" => return Ok(Query::Open),
+        "overdue" => return Ok(Query::Overdue),
+        _ => (),
+    }
+
+    let split = match s.find([':', '~', '<', '>', '=', '!']) {
+        Some(e) => e,
+        None => return Err(Error::parse(1, column, format!("Unknown term {}", s))),
+    };
+    let field = lower[..split].to_string();
+    let rest = &s[split..];
+    let (op, value) = ["<=", ">=", "!=", "<", ">", "=", ":", "~"]
+        .iter()
+        .find(|op| rest.starts_with(**op))
+        .map(|op| (*op, &rest[op.len()..]))
+        .unwrap_or(("", rest));
+    let value_column = column + s[..s.len() - value.len()].chars().count();
+    if value.is_empty() {
+        return Err(Error::parse(
+            1,
+            value_column,
+            format!("Missing value for {}", field),
+        ));
+    }
+
+    let cmp = match op {
+        "<" => Some(Cmp::Lt),
+        "<=" => Some(Cmp::Le),
+        "=" | ":" => Some(Cmp::Eq),
+        "!=" => Some(Cmp::Ne),
+        ">=" => Some(Cmp::Ge),
+        ">" => Some(Cmp::Gt),
+        _ => None,
+    };
+    let date_field = match field.as_str() {
+        "due" => Some(DateField::Due),
+        "start" => Some(DateField::Start),
+        "scheduled" => Some(DateField::Scheduled),
+        "created" => Some(DateField::Created),
+        "completed" | "done" => Some(DateField::Completed),
+        "cancelled" => Some(DateField::Cancelled),
+        _ => None,
+    };
+
+    match (field.as_str(), op, date_field) {
+        ("tag", ":", _) => Ok(Query::Tag(value.trim_start_matches('#').to_lowercase())),
+        ("id", ":", _) => Ok(Query::Id(String::from(value))),
+        ("status", ":", _) => Status::from_str(value)
+            .map(Query::Status)
+            .map_err(|e| e.at(1, value_column)),
+        ("title", ":" | "~", _) => Ok(Query::Title(value.to_lowercase())),
+        ("section" | "heading", ":", _) => Ok(Query::Section(String::from(
+            value.trim_start_matches('#').trim(),
+        ))),
+        ("has", ":", _) => match Field::from_str(value) {
+            Ok(f) => Ok(Query::Has(f)),
+            Err(_) => Err(Error::parse(
+                1,
+                value_column,
+                format!("Unknown field {}", value),
+            )),
+        },
+        ("priority", _, _) => match (cmp, Priority::from_str(value)) {
+            (Some(cmp), Ok(p)) => Ok(Query::Priority(cmp, p)),
+            (None, _) => Err(Error::parse(
+                1,
+                column,
+                format!("Can't compare {} with {}", field, op),
+            )),
+            (_, Err(e)) => Err(e.at(1, value_column)),
+        },
+        (_, _, Some(f)) => match cmp {
+            Some(cmp) => {
+                let v = DateValue::from_str(value).map_err(|e| e.at(1, value_column))?;
+                Ok(Query::Date(f, cmp, v))
+            }
+            None => Err(Error::parse(
+                1,
+                column,
+                format!("Can't compare {} with {}", field, op),
+            )),
+        },
+        _ => Err(Error::parse(1, column, format!("Unknown term {}", s))),
+    }
+}
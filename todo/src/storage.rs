@@ -0,0 +1,156 @@
+use crate::{Error, Result};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Write a file so that it's either entirely the old or the new content,
+/// even if we crash or the disk fills up halfway. The content goes to a
+/// temporary file next to it, which is synced then renamed over it.
+///
+/// The previous `backups` versions are kept as `<file>.1`, the newest, to
+/// `<file>.<backups>`.
+pub fn write_atomic(file: &str, content: &str, backups: usize) -> Result<()> {
+    let path = Path::new(file);
+    let tmp = _sibling(path, &format!(".{}.tmp", std::process::id()), true);
+
+    let written = File::create(&tmp).and_then(|mut f| {
+        f.write_all(content.as_bytes())?;
+        f.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(Error::io(&tmp, e));
+    }
+
+    if backups > 0 && path.exists() {
+        if let Err(e) = _rotate(path, backups) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+    }
+    if let Err(e) = fs::rename(&tmp, path) {
+        let _ = fs::remove_file(&tmp);
+        return Err(Error::io(path, e));
+    }
+    _sync_dir(path)
+}
+
+/// Where the nth backup of a file is kept.
+pub fn backup_path(file: &str, n: usize) -> PathBuf {
+    _sibling(Path::new(file), &format!(".{}", n), false)
+}
+
+/// Where the undo history of a file is kept, hidden next to it.
+pub fn history_path(file: &str) -> PathBuf {
+    _sibling(Path::new(file), ".history", true)
+}
+
+/// Shift the backups up by one, dropping the oldest, and copy the file to
+/// the first. The file itself is left alone until it's replaced.
+fn _rotate(path: &Path, backups: usize) -> Result<()> {
+    let file = path.to_string_lossy();
+    for n in (1..backups).rev() {
+        let from = backup_path(&file, n);
+        if from.exists() {
+            let to = backup_path(&file, n + 1);
+            fs::rename(&from, &to).map_err(|e| Error::io(&from, e))?;
+        }
+    }
+    let first = backup_path(&file, 1);
+    fs::copy(path, &first).map_err(|e| Error::io(&first, e))?;
+    Ok(())
+}
+
+/// A path in the same directory, so renaming it is atomic, with a suffix
+/// added to the file name, optionally hidden.
+fn _sibling(path: &Path, suffix: &str, hidden: bool) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|x| x.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let dot = if hidden { "." } else { "" };
+    path.with_file_name(format!("{}{}{}", dot, name, suffix))
+}
+
+/// Sync the directory a file is in, so a rename into it is on disk too.
+#[cfg(unix)]
+fn _sync_dir(path: &Path) -> Result<()> {
+    let dir = match path.parent() {
+        Some(e) if !e.as_os_str().is_empty() => e,
+        _ => Path::new("."),
+    };
+    File::open(dir)
+        .and_then(|f| f.sync_all())
+        .map_err(|e| Error::io(dir, e))
+}
+
+#[cfg(not(unix))]
+fn _sync_dir(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// An exclusive lock on a task file, held until dropped, so two processes
+/// can't both read, change and write it at once. It's taken on a hidden
+/// `.<file>.lock` file next to it, since saving replaces the file itself,
+/// which is removed again when the lock is dropped.
+pub struct Lock {
+    path: PathBuf,
+    _file: File,
+}
+
+impl Lock {
+    /// Lock a file, waiting up to `timeout` for another process to let go
+    /// of it.
+    pub fn acquire(file: &str, timeout: Duration) -> Result<Lock> {
+        let path = _sibling(Path::new(file), ".lock", true);
+        let start = Instant::now();
+        loop {
+            let f = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&path)
+                .map_err(|e| Error::io(&path, e))?;
+            match f.try_lock() {
+                // The holder before us may have removed the lock file after
+                // we opened it, then it's not the lock anymore.
+                Ok(()) if _same_file(&f, &path) => return Ok(Lock { path, _file: f }),
+                Ok(()) => (),
+                Err(TryLockError::WouldBlock) if start.elapsed() < timeout => {
+                    thread::sleep(Duration::from_millis(50))
+                }
+                Err(TryLockError::WouldBlock) => return Err(Error::Locked(PathBuf::from(file))),
+                Err(TryLockError::Error(e)) => return Err(Error::io(&path, e)),
+            }
+        }
+    }
+
+    /// The lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        // Removed while still locked, so no one else can have taken it.
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Whether an open file is still the one at a path.
+#[cfg(unix)]
+fn _same_file(f: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (f.metadata(), fs::metadata(path)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn _same_file(_f: &File, path: &Path) -> bool {
+    path.exists()
+}
@@ -0,0 +1,223 @@
+use crate::storage;
+use crate::structs::markdown::{self, Location};
+use crate::{DependencyGraph, Dialect, Error, Result, Todo, TodoList};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// A markdown note with tasks in it, keeping everything around the tasks so
+/// it can be written back with only the changed task lines touched.
+///
+/// Tasks are edited through `tasks_mut`. When writing, tasks are matched to
+/// the lines they were read from by id:
+/// - unchanged tasks keep their line exactly as it was,
+/// - changed tasks have their line rewritten, keeping its indentation and
+///   bullet,
+/// - removed tasks have their line dropped,
+/// - new tasks are inserted before the next task in their list, or after
+///   the last one.
+pub struct Document {
+    lines: Vec<String>,
+    newline: &'static str,
+    trailing_newline: bool,
+    tasks: TodoList,
+    locations: HashMap<String, Location>,
+    /// Each read task as it was when first parsed, without its sub tasks,
+    /// to tell which tasks changed.
+    parsed: HashMap<String, Todo>,
+}
+
+impl Document {
+    pub fn from_markdown(s: &str) -> Result<Document> {
+        Document::from_markdown_with(s, Dialect::Compat)
+    }
+
+    pub fn from_markdown_with(s: &str, dialect: Dialect) -> Result<Document> {
+        let (tasks, locations) = markdown::parse_located(s, dialect, true, None)?;
+        let locations: HashMap<String, Location> =
+            locations.into_iter().map(|l| (l.id.clone(), l)).collect();
+        let parsed = tasks
+            .walk()
+            .map(|(_, t)| (String::from(t.id()), t.details()))
+            .collect();
+
+        Ok(Document {
+            lines: s.lines().map(String::from).collect(),
+            newline: if s.contains("\r\n") { "\r\n" } else { "\n" },
+            trailing_newline: s.ends_with('\n'),
+            tasks,
+            locations,
+            parsed,
+        })
+    }
+
+    pub fn from_markdown_file(file_name: &str) -> Result<Document> {
+        let path = Path::new(file_name);
+
+        let mut file = File::open(path).map_err(|e| Error::io(path, e))?;
+        let mut s = String::new();
+        file.read_to_string(&mut s)
+            .map_err(|e| Error::io(path, e))?;
+        let mut doc = Document::from_markdown(s.as_str())?;
+        doc.tasks.set_source_file(file_name);
+        Ok(doc)
+    }
+
+    pub fn to_markdown_file(&self, file_name: &str) -> Result<()> {
+        self.to_markdown_file_with(file_name, 0)
+    }
+
+    /// Save the note, keeping the previous `backups` versions of it.
+    pub fn to_markdown_file_with(&self, file_name: &str, backups: usize) -> Result<()> {
+        storage::write_atomic(file_name, &self.to_markdown(), backups)
+    }
+
+    pub fn tasks(&self) -> &TodoList {
+        &self.tasks
+    }
+
+    pub fn tasks_mut(&mut self) -> &mut TodoList {
+        &mut self.tasks
+    }
+
+    /// Complete a task, putting its next instance, if it repeats, on the
+    /// line above it like Obsidian Tasks does.
+    pub fn complete(&mut self, id: &str) -> Result<()> {
+        if let Some(next) = self.tasks.complete(id)? {
+            self.tasks.insert_before(id, next)?;
+        }
+        Ok(())
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut w = Writer {
+            doc: self,
+            graph: self.tasks.dependency_graph(),
+            written: HashMap::new(),
+            inserts: HashMap::new(),
+        };
+        w.place(&self.tasks, "", self.lines.len());
+
+        let ids: HashMap<usize, &str> = self
+            .locations
+            .values()
+            .map(|l| (l.line, l.id.as_str()))
+            .collect();
+        let mut out: Vec<&str> = Vec::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            if let Some(e) = w.inserts.get(&i) {
+                out.extend(e.iter().map(|x| x.as_str()));
+            }
+            match ids.get(&i) {
+                // Removed tasks aren't written.
+                Some(id) => out.extend(w.written.get(*id).map(|x| x.as_str())),
+                None => out.push(line),
+            }
+        }
+        if let Some(e) = w.inserts.get(&self.lines.len()) {
+            out.extend(e.iter().map(|x| x.as_str()));
+        }
+
+        let mut s = out.join(self.newline);
+        if self.trailing_newline || (self.lines.is_empty() && !s.is_empty()) {
+            s.push_str(self.newline);
+        }
+        s
+    }
+
+    /// The line after a task and anything indented under it.
+    fn _end(&self, line: usize) -> usize {
+        let indent = whitespace(&self.lines[line]).len();
+        let mut end = line + 1;
+        while end < self.lines.len()
+            && !self.lines[end].trim().is_empty()
+            && whitespace(&self.lines[end]).len() > indent
+        {
+            end += 1;
+        }
+        end
+    }
+}
+
+/// Leading whitespace of a line.
+fn whitespace(s: &str) -> &str {
+    &s[..s.len() - s.trim_start().len()]
+}
+
+struct Writer<'a> {
+    doc: &'a Document,
+    graph: DependencyGraph,
+    /// Line for each task still in the document, by id.
+    written: HashMap<String, String>,
+    /// New lines to insert before an original line.
+    inserts: HashMap<usize, Vec<String>>,
+}
+
+impl<'a> Writer<'a> {
+    /// Write out a list. New tasks are inserted before the next task already
+    /// in the note, or after the last one, or at line `at` if there are none.
+    /// Returns the line after the list.
+    fn place(&mut self, tdl: &TodoList, indent: &str, mut at: usize) -> usize {
+        let doc = self.doc;
+        // New tasks are indented like the tasks already in the list.
+        let indent = match tdl.iter().find_map(|t| doc.locations.get(t.id())) {
+            Some(l) => whitespace(&l.prefix),
+            None => indent,
+        };
+
+        let mut new = Vec::new();
+        for t in tdl {
+            let l = match doc.locations.get(t.id()) {
+                Some(e) => e,
+                None => {
+                    new.push(t);
+                    continue;
+                }
+            };
+            for n in new.drain(..) {
+                self._insert(n, indent, l.line);
+            }
+
+            // Ids are only added to existing tasks if another task refers to
+            // them, or if they were renamed, since a task without one gets it
+            // from its title. New tasks always get one so they can be found
+            // again.
+            let parsed = doc.parsed.get(t.id());
+            let renamed = parsed.map(|x| x.title()) != Some(t.title());
+            let with_id = l.has_id || renamed || self._referenced(t);
+            let unchanged = with_id == l.has_id
+                && parsed.map(|x| x.markdown_line(true)) == Some(t.markdown_line(true));
+            let line = match unchanged {
+                true => doc.lines[l.line].clone(),
+                false => format!("{}{}", l.prefix, t.markdown_line(with_id)),
+            };
+            self.written.insert(String::from(t.id()), line);
+            let child_indent = format!("{}\t", whitespace(&l.prefix));
+            let end = self.place(t.sub_tasks(), &child_indent, l.line + 1);
+            at = end.max(doc._end(l.line));
+        }
+        for n in new {
+            self._insert(n, indent, at);
+        }
+        at
+    }
+
+    /// Insert a new task and its sub tasks before line `at`.
+    fn _insert(&mut self, t: &Todo, indent: &str, at: usize) {
+        let line = format!("{}- {}", indent, t.markdown_line(true));
+        self.inserts.entry(at).or_default().push(line);
+        self.place(t.sub_tasks(), &format!("{}\t", indent), at);
+    }
+
+    fn _referenced(&self, t: &Todo) -> bool {
+        !self.graph.dependents(t.id()).is_empty()
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_markdown())
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{Error, Result, TodoList};
+use std::collections::{HashMap, HashSet};
+
+/// Dependency graph over every task in a list, including nested sub tasks.
+///
+/// Edges go from a task to the tasks it depends on. Dependencies on ids that
+/// are not in the list are ignored.
+pub struct DependencyGraph {
+    /// Task ids in list order, so results are deterministic.
+    ids: Vec<String>,
+    dependencies: HashMap<String, Vec<String>>,
+    dependents: HashMap<String, Vec<String>>,
+    open: HashSet<String>,
+}
+
+impl DependencyGraph {
+    pub fn new(tdl: &TodoList) -> DependencyGraph {
+        let mut g = DependencyGraph {
+            ids: Vec::new(),
+            dependencies: HashMap::new(),
+            dependents: HashMap::new(),
+            open: HashSet::new(),
+        };
+        g._add_nodes(tdl);
+
+        let known: HashSet<String> = g.ids.iter().cloned().collect();
+        for id in &g.ids {
+            let deps = match g.dependencies.get_mut(id) {
+                Some(e) => e,
+                None => continue,
+            };
+            deps.retain(|d| known.contains(d));
+            for d in deps.iter() {
+                g.dependents.entry(d.clone()).or_default().push(id.clone());
+            }
+        }
+        g
+    }
+
+    fn _add_nodes(&mut self, tdl: &TodoList) {
+        for (_, t) in tdl.walk() {
+            let id = String::from(t.id());
+            if t.is_open() {
+                self.open.insert(id.clone());
+            }
+            self.dependencies
+                .insert(id.clone(), Vec::from(t.dependencies()));
+            self.ids.push(id);
+        }
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.dependencies.contains_key(id)
+    }
+
+    /// Direct dependencies of a task.
+    pub fn dependencies(&self, id: &str) -> &[String] {
+        match self.dependencies.get(id) {
+            Some(e) => e,
+            None => &[],
+        }
+    }
+
+    /// Tasks that directly depend on a task.
+    pub fn dependents(&self, id: &str) -> &[String] {
+        match self.dependents.get(id) {
+            Some(e) => e,
+            None => &[],
+        }
+    }
+
+    /// Every task reachable by following `next` from `id`, not including
+    /// `id` itself unless it is part of a cycle.
+    fn _reachable<'a>(&'a self, id: &str, next: impl Fn(&str) -> &'a [String]) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![String::from(id)];
+        while let Some(e) = stack.pop() {
+            for n in next(&e) {
+                if seen.insert(n.clone()) {
+                    stack.push(n.clone());
+                }
+            }
+        }
+        self.ids
+            .iter()
+            .filter(|x| seen.contains(*x))
+            .cloned()
+            .collect()
+    }
+
+    /// Uncompleted tasks that transitively block a task.
+    pub fn blocked_by(&self, id: &str) -> Vec<String> {
+        self._reachable(id, |e| self.dependencies(e))
+            .into_iter()
+            .filter(|x| self.open.contains(x))
+            .collect()
+    }
+
+    /// Tasks that are transitively blocked by a task.
+    pub fn blocks(&self, id: &str) -> Vec<String> {
+        self._reachable(id, |e| self.dependents(e))
+    }
+
+    /// Whether a task no longer blocks the tasks depending on it, that is
+    /// it's completed, or not in the graph, and nothing it depends on is open.
+    pub fn is_done(&self, id: &str) -> bool {
+        !self.open.contains(id) && self.blocked_by(id).is_empty()
+    }
+
+    /// Uncompleted tasks with every transitive dependency done.
+    pub fn ready(&self) -> Vec<String> {
+        self.ids
+            .iter()
+            .filter(|x| self.open.contains(*x) && self.blocked_by(x).is_empty())
+            .cloned()
+            .collect()
+    }
+
+    /// Find a cycle, returned as the ids along it, starting and ending with
+    /// the same task.
+    pub fn find_cycle(&self) -> Option<Vec<String>> {
+        // 0 unvisited, 1 on the current path, 2 done.
+        let mut state: HashMap<&str, u8> = HashMap::new();
+        for root in &self.ids {
+            if state.contains_key(root.as_str()) {
+                continue;
+            }
+            let mut path: Vec<(&str, usize)> = vec![(root, 0)];
+            state.insert(root, 1);
+            while let Some(&(id, i)) = path.last() {
+                let deps = self.dependencies(id);
+                if i == deps.len() {
+                    state.insert(id, 2);
+                    path.pop();
+                    continue;
+                }
+                let next = deps[i].as_str();
+                if let Some(e) = path.last_mut() {
+                    e.1 += 1;
+                }
+                match state.get(next) {
+                    Some(1) => {
+                        let start = path.iter().position(|(x, _)| *x == next).unwrap_or(0);
+                        let mut cycle: Vec<String> = path[start..]
+                            .iter()
+                            .map(|(x, _)| String::from(*x))
+                            .collect();
+                        cycle.push(String::from(next));
+                        return Some(cycle);
+                    }
+                    Some(_) => (),
+                    None => {
+                        state.insert(next, 1);
+                        path.push((next, 0));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Uncompleted tasks ordered so every task comes after the tasks it
+    /// depends on. Errors if the dependencies contain a cycle.
+    pub fn topological_order(&self) -> Result<Vec<String>> {
+        if let Some(cycle) = self.find_cycle() {
+            return Err(Error::Dependency(format!(
+                "dependency cycle {}",
+                cycle.join(" -> ")
+            )));
+        }
+
+        let mut order = Vec::new();
+        let mut placed = HashSet::new();
+        // Depth first, visiting dependencies before the task itself.
+        for root in &self.ids {
+            let mut stack = vec![(root.as_str(), false)];
+            while let Some((id, expanded)) = stack.pop() {
+                if placed.contains(id) {
+                    continue;
+                }
+                if expanded {
+                    placed.insert(id);
+                    if self.open.contains(id) {
+                        order.push(String::from(id));
+                    }
+                    continue;
+                }
+                stack.push((id, true));
+                for d in self.dependencies(id).iter().rev() {
+                    if !placed.contains(d.as_str()) {
+                        stack.push((d, false));
+                    }
+                }
+            }
+        }
+        Ok(order)
+    }
+}
@@ -0,0 +1,387 @@
+use crate::structs::{content_hash, DateTime};
+use crate::{storage, Error, Result, Todo, TodoList};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+
+/// One part of a change to a list. Added and removed tasks carry their sub
+/// tasks along, which aren't recorded separately.
+#[derive(Clone, Serialize, Deserialize)]
+enum Change {
+    Added {
+        parent: Option<String>,
+        index: usize,
+        task: Todo,
+    },
+    Removed {
+        parent: Option<String>,
+        index: usize,
+        task: Todo,
+    },
+    /// A task's details, leaving out its sub tasks, before and after.
+    Edited { before: Box<Todo>, after: Box<Todo> },
+    /// Tasks in a list that were moved around, in their order before and
+    /// after.
+    Reordered {
+        parent: Option<String>,
+        before: Vec<String>,
+        after: Vec<String>,
+    },
+}
+
+/// A change made to a list by a single operation, that can be undone and
+/// redone.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Step {
+    label: String,
+    time: DateTime,
+    changes: Vec<Change>,
+    /// Hashes of the list before and after, so a step is only undone or
+    /// redone on the list it was made to.
+    before: String,
+    after: String,
+}
+
+impl Step {
+    /// What the change was, like `done 3`.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// When the change was made.
+    pub fn time(&self) -> chrono::NaiveDateTime {
+        self.time.naive_local()
+    }
+}
+
+/// Changes made to a task list, to undo and redo them. Each change is kept
+/// as the difference between the list before and after it, so any edit to
+/// the list can be recorded, and the history can be saved alongside the
+/// list to undo changes made in an earlier session.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    /// Most steps kept, the oldest are dropped past it.
+    limit: usize,
+}
+
+impl History {
+    pub fn new(limit: usize) -> History {
+        History {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            limit,
+        }
+    }
+
+    /// Run an operation on a list and record what it changed. If the
+    /// operation fails the list is put back as it was.
+    pub fn record<T>(
+        &mut self,
+        tdl: &mut TodoList,
+        label: &str,
+        f: impl FnOnce(&mut TodoList) -> Result<T>,
+    ) -> Result<T> {
+        let before = tdl.clone();
+        match f(tdl) {
+            Ok(e) => {
+                self.push(label, &before, tdl)?;
+                Ok(e)
+            }
+            Err(e) => {
+                *tdl = before;
+                Err(e)
+            }
+        }
+    }
+
+    /// Record a change from one state of a list to another, returning false
+    /// if nothing changed. Anything that could be redone is forgotten.
+    pub fn push(&mut self, label: &str, before: &TodoList, after: &TodoList) -> Result<bool> {
+        let changes = _diff(before, after)?;
+        if changes.is_empty() {
+            return Ok(false);
+        }
+        self.undo.push(Step {
+            label: String::from(label),
+            time: DateTime::now(&chrono::Local),
+            changes,
+            before: _hash(before),
+            after: _hash(after),
+        });
+        if self.undo.len() > self.limit {
+            self.undo.drain(..self.undo.len() - self.limit);
+        }
+        self.redo.clear();
+        Ok(true)
+    }
+
+    /// Undo the last change, returning it, or None if there's nothing to
+    /// undo. Fails, leaving the list alone, if the list was changed since
+    /// without being recorded.
+    pub fn undo(&mut self, tdl: &mut TodoList) -> Result<Option<&Step>> {
+        let step = match self.undo.pop() {
+            Some(e) => e,
+            None => return Ok(None),
+        };
+        if let Err(e) = _apply(tdl, &step, false) {
+            self.undo.push(step);
+            return Err(e);
+        }
+        self.redo.push(step);
+        Ok(self.redo.last())
+    }
+
+    /// Redo the last undone change, returning it, or None if there's
+    /// nothing to redo.
+    pub fn redo(&mut self, tdl: &mut TodoList) -> Result<Option<&Step>> {
+        let step = match self.redo.pop() {
+            Some(e) => e,
+            None => return Ok(None),
+        };
+        if let Err(e) = _apply(tdl, &step, true) {
+            self.redo.push(step);
+            return Err(e);
+        }
+        self.undo.push(step);
+        Ok(self.undo.last())
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Changes that can be undone, the latest first.
+    pub fn undo_steps(&self) -> impl Iterator<Item = &Step> {
+        self.undo.iter().rev()
+    }
+
+    /// Changes that can be redone, the next first.
+    pub fn redo_steps(&self) -> impl Iterator<Item = &Step> {
+        self.redo.iter().rev()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(s: &str) -> Result<History> {
+        Ok(serde_json::from_str(s)?)
+    }
+
+    /// Read a saved history, a missing file is an empty one.
+    pub fn from_file(file_name: &str, limit: usize) -> Result<History> {
+        match fs::read_to_string(file_name) {
+            Ok(s) => {
+                let mut h = History::from_json(&s)?;
+                h.limit = limit;
+                Ok(h)
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(History::new(limit)),
+            Err(e) => Err(Error::io(file_name, e)),
+        }
+    }
+
+    pub fn to_file(&self, file_name: &str) -> Result<()> {
+        storage::write_atomic(file_name, &self.to_json()?, 0)
+    }
+}
+
+/// Hash of a list as markdown, which leaves out where the tasks were read
+/// from, since that changes as the lines in a note move around.
+fn _hash(tdl: &TodoList) -> String {
+    content_hash(&tdl.to_markdown())
+}
+
+/// Tasks in a list that aren't where they were in the other list, or
+/// aren't in it at all, skipping their sub tasks.
+fn _moved(
+    tdl: &TodoList,
+    parent: Option<&str>,
+    other: &HashMap<String, Option<String>>,
+    moved: &mut Vec<(Option<String>, usize, Todo)>,
+) {
+    for (i, t) in tdl.iter().enumerate() {
+        let same = other.get(t.id()).map(|x| x.as_deref()) == Some(parent);
+        if same {
+            _moved(t.sub_tasks(), Some(t.id()), other, moved);
+        } else {
+            moved.push((parent.map(String::from), i, t.clone()));
+        }
+    }
+}
+
+fn _diff(before: &TodoList, after: &TodoList) -> Result<Vec<Change>> {
+    let parents_before = before.parents();
+    let parents_after = after.parents();
+
+    let mut removed = Vec::new();
+    _moved(before, None, &parents_after, &mut removed);
+    let mut added = Vec::new();
+    _moved(after, None, &parents_before, &mut added);
+
+    // Anything under a task that moved is covered by it being removed and
+    // added, the rest that stayed in the same list are compared.
+    let mut covered = HashSet::new();
+    for (_, _, t) in removed.iter().chain(added.iter()) {
+        for (_, e) in t.sub_tasks().walk() {
+            covered.insert(String::from(e.id()));
+        }
+    }
+    let kept = |t: &Todo| {
+        let same = parents_before.get(t.id()) == parents_after.get(t.id());
+        same && !covered.contains(t.id())
+    };
+
+    let mut changes: Vec<Change> = removed
+        .into_iter()
+        .map(|(parent, index, task)| Change::Removed {
+            parent,
+            index,
+            task,
+        })
+        .collect();
+
+    let old: HashMap<&str, &Todo> = before.walk().map(|(_, t)| (t.id(), t)).collect();
+    for (_, t) in after.walk().filter(|(_, t)| kept(t)) {
+        let (b, a) = (old[t.id()].details(), t.details());
+        if serde_json::to_value(&b)? != serde_json::to_value(&a)? {
+            changes.push(Change::Edited {
+                before: Box::new(b),
+                after: Box::new(a),
+            });
+        }
+    }
+
+    // Lists whose tasks were moved around, compared by the tasks in them
+    // both before and after.
+    let mut lists = vec![(None, before, after)];
+    for (_, t) in after.walk().filter(|(_, t)| kept(t)) {
+        lists.push((Some(t.id()), old[t.id()].sub_tasks(), t.sub_tasks()));
+    }
+    for (parent, b, a) in lists {
+        let order = |l: &TodoList| -> Vec<String> {
+            l.iter()
+                .filter(|t| kept(t))
+                .map(|t| String::from(t.id()))
+                .collect()
+        };
+        let (b, a) = (order(b), order(a));
+        if b != a {
+            changes.push(Change::Reordered {
+                parent: parent.map(String::from),
+                before: b,
+                after: a,
+            });
+        }
+    }
+
+    changes.extend(
+        added
+            .into_iter()
+            .map(|(parent, index, task)| Change::Added {
+                parent,
+                index,
+                task,
+            }),
+    );
+    Ok(changes)
+}
+
+/// The list a task with the given parent is in.
+fn _list<'a>(tdl: &'a mut TodoList, parent: &Option<String>) -> Result<&'a mut TodoList> {
+    match parent {
+        Some(id) => match tdl.get_by_id_mut(id) {
+            Some(e) => Ok(e.get_sub_tasks()),
+            None => Err(Error::NotFound(id.clone())),
+        },
+        None => Ok(tdl),
+    }
+}
+
+/// Apply a step to a list, forwards to redo it or backwards to undo it.
+/// Tasks are taken out first and put back last, so every index is into the
+/// list as it ends up.
+fn _apply(tdl: &mut TodoList, step: &Step, forward: bool) -> Result<()> {
+    let (from, to) = match forward {
+        true => (&step.before, &step.after),
+        false => (&step.after, &step.before),
+    };
+    if _hash(tdl) != *from {
+        return Err(Error::Conflict(format!(
+            "the tasks changed since '{}'",
+            step.label
+        )));
+    }
+
+    let mut new = tdl.clone();
+    for c in &step.changes {
+        let task = match (c, forward) {
+            (Change::Removed { task, .. }, true) | (Change::Added { task, .. }, false) => task,
+            _ => continue,
+        };
+        if new.remove_by_id(task.id()).is_none() {
+            return Err(Error::NotFound(String::from(task.id())));
+        }
+    }
+    for c in &step.changes {
+        match c {
+            Change::Edited { before, after } => {
+                let e = if forward { after } else { before };
+                match new.get_by_id_mut(e.id()) {
+                    Some(t) => t.replace_details(Todo::clone(e)),
+                    None => return Err(Error::NotFound(String::from(e.id()))),
+                }
+            }
+            Change::Reordered {
+                parent,
+                before,
+                after,
+            } => {
+                let ids = if forward { after } else { before };
+                _list(&mut new, parent)?.reorder(ids);
+            }
+            _ => (),
+        }
+    }
+    for c in &step.changes {
+        match (c, forward) {
+            (
+                Change::Added {
+                    parent,
+                    index,
+                    task,
+                },
+                true,
+            )
+            | (
+                Change::Removed {
+                    parent,
+                    index,
+                    task,
+                },
+                false,
+            ) => {
+                let list = _list(&mut new, parent)?;
+                list.insert((*index).min(list.len()), task.clone());
+            }
+            _ => (),
+        }
+    }
+
+    // Only a list that's exactly as it was is a complete undo or redo.
+    if _hash(&new) != *to {
+        return Err(Error::Conflict(format!(
+            "couldn't restore the tasks for '{}'",
+            step.label
+        )));
+    }
+    *tdl = new;
+    Ok(())
+}
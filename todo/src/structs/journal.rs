@@ -0,0 +1,486 @@
+use crate::structs::DateTime;
+use crate::{storage, Error, Result, Status, Todo, TodoList};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::prelude::*;
+use std::io::{ErrorKind, SeekFrom};
+
+/// Something that happened to a task. Tasks are placed in their list after
+/// the task `after`, or first if it's None.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event {
+    Created {
+        parent: Option<String>,
+        after: Option<String>,
+        task: Todo,
+    },
+    /// A field of a task set to a new value, as it's named in JSON.
+    Edited {
+        id: String,
+        field: String,
+        value: Value,
+    },
+    Completed {
+        id: String,
+        completed: Value,
+    },
+    /// The next instance of a repeating task was created.
+    Repeated {
+        id: String,
+        parent: Option<String>,
+        after: Option<String>,
+        task: Todo,
+    },
+    Moved {
+        id: String,
+        parent: Option<String>,
+        after: Option<String>,
+    },
+    Deleted {
+        id: String,
+    },
+}
+
+impl Event {
+    /// The task the event happened to.
+    pub fn id(&self) -> &str {
+        match self {
+            Event::Created { task, .. } => task.id(),
+            Event::Edited { id, .. }
+            | Event::Completed { id, .. }
+            | Event::Repeated { id, .. }
+            | Event::Moved { id, .. }
+            | Event::Deleted { id } => id,
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Created { task, .. } => write!(f, "created {} {}", task.id(), task.title()),
+            Event::Edited { id, field, value } => write!(f, "edited {} {} = {}", id, field, value),
+            Event::Completed { id, .. } => write!(f, "completed {}", id),
+            Event::Repeated { id, task, .. } => write!(f, "repeated {} as {}", id, task.id()),
+            Event::Moved { id, .. } => write!(f, "moved {}", id),
+            Event::Deleted { id } => write!(f, "deleted {}", id),
+        }
+    }
+}
+
+/// An event with when it happened, one line of the journal.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entry {
+    time: DateTime,
+    #[serde(flatten)]
+    event: Event,
+}
+
+impl Entry {
+    pub fn time(&self) -> chrono::NaiveDateTime {
+        self.time.naive_local()
+    }
+
+    pub fn event(&self) -> &Event {
+        &self.event
+    }
+}
+
+/// The state of the tasks after some number of events in a journal.
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    events: usize,
+    tasks: TodoList,
+}
+
+/// Tasks stored as a JSONL journal of events, one per line, rather than a
+/// snapshot of the list. Saving only appends what changed since the last
+/// save, and the list is rebuilt by replaying the events, so the journal
+/// keeps the full history of every task.
+///
+/// Replaying gets slower as the journal grows, so `compact` saves the list
+/// to `<file>.snapshot` and only the events after it are replayed. The
+/// journal itself is never rewritten.
+pub struct Journal {
+    file: String,
+    tasks: TodoList,
+    /// The tasks as last saved, what changes are found against.
+    saved: TodoList,
+    /// Number of events in the journal.
+    events: usize,
+    /// Number of events the snapshot covers.
+    snapshot: usize,
+    /// Where the last complete line ends. Anything after it was cut off by
+    /// a crash while appending and is overwritten by the next save.
+    length: u64,
+}
+
+impl Journal {
+    /// Open a journal, replaying it from the last snapshot. A missing
+    /// journal is an empty list.
+    pub fn open(file: &str) -> Result<Journal> {
+        let snapshot = Journal::snapshot_path(file);
+        let (mut tasks, covered) = match fs::read_to_string(&snapshot) {
+            Ok(s) => {
+                let e: Snapshot = serde_json::from_str(&s)?;
+                (e.tasks, e.events)
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => (TodoList::new(), 0),
+            Err(e) => return Err(Error::io(&snapshot, e)),
+        };
+
+        let s = match fs::read_to_string(file) {
+            Ok(e) => e,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(Error::io(file, e)),
+        };
+        let complete = s.rfind('\n').map(|x| x + 1).unwrap_or(0);
+        let lines: Vec<&str> = s[..complete].lines().collect();
+        if lines.len() < covered {
+            return Err(Error::Conflict(format!(
+                "{} has {} events but its snapshot covers {}",
+                file,
+                lines.len(),
+                covered
+            )));
+        }
+        for (i, line) in lines.iter().enumerate().skip(covered) {
+            let entry: Entry =
+                serde_json::from_str(line).map_err(|e| Error::from(e).offset(i, 0))?;
+            _replay(&mut tasks, &entry.event).map_err(|e| e.at(i + 1, 1))?;
+        }
+
+        Ok(Journal {
+            file: String::from(file),
+            saved: tasks.clone(),
+            tasks,
+            events: lines.len(),
+            snapshot: covered,
+            length: complete as u64,
+        })
+    }
+
+    pub fn snapshot_path(file: &str) -> String {
+        format!("{}.snapshot", file)
+    }
+
+    pub fn tasks(&self) -> &TodoList {
+        &self.tasks
+    }
+
+    pub fn tasks_mut(&mut self) -> &mut TodoList {
+        &mut self.tasks
+    }
+
+    /// Number of events since the last snapshot.
+    pub fn pending(&self) -> usize {
+        self.events - self.snapshot
+    }
+
+    /// Append what changed since the last save to the journal, returning the
+    /// number of events written.
+    pub fn save(&mut self) -> Result<usize> {
+        let events = match _events(&self.saved, &self.tasks)? {
+            Some(e) => e,
+            // Changes that can't be told apart as events are still saved.
+            None => {
+                self._snapshot()?;
+                return Ok(0);
+            }
+        };
+        if events.is_empty() {
+            return Ok(0);
+        }
+
+        let time = DateTime::now(&chrono::Local);
+        let mut s = String::new();
+        for event in &events {
+            let entry = Entry {
+                time: time.clone(),
+                event: event.clone(),
+            };
+            s.push_str(&serde_json::to_string(&entry)?);
+            s.push('\n');
+        }
+
+        let written = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.file)
+            .and_then(|mut f| {
+                f.set_len(self.length)?;
+                f.seek(SeekFrom::End(0))?;
+                f.write_all(s.as_bytes())?;
+                f.sync_all()
+            });
+        written.map_err(|e| Error::io(&self.file, e))?;
+
+        self.length += s.len() as u64;
+        self.events += events.len();
+        self.saved = self.tasks.clone();
+        Ok(events.len())
+    }
+
+    /// Save any changes, then a snapshot of the tasks, so opening the
+    /// journal only replays the events after it.
+    pub fn compact(&mut self) -> Result<()> {
+        self.save()?;
+        self._snapshot()
+    }
+
+    fn _snapshot(&mut self) -> Result<()> {
+        let s = serde_json::to_string(&Snapshot {
+            events: self.events,
+            tasks: self.tasks.clone(),
+        })?;
+        storage::write_atomic(&Journal::snapshot_path(&self.file), &s, 0)?;
+        self.saved = self.tasks.clone();
+        self.snapshot = self.events;
+        Ok(())
+    }
+
+    /// Every event in a journal, oldest first, to see what happened to a
+    /// task and when.
+    pub fn entries(file: &str) -> Result<Vec<Entry>> {
+        let s = match fs::read_to_string(file) {
+            Ok(e) => e,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::io(file, e)),
+        };
+        let complete = s.rfind('\n').map(|x| x + 1).unwrap_or(0);
+        s[..complete]
+            .lines()
+            .enumerate()
+            .map(|(i, x)| serde_json::from_str(x).map_err(|e| Error::from(e).offset(i, 0)))
+            .collect()
+    }
+}
+
+/// Take a task out of wherever it is and put it in a list after another.
+fn _place(
+    tdl: &mut TodoList,
+    parent: &Option<String>,
+    after: &Option<String>,
+    t: Todo,
+) -> Result<()> {
+    let list = match parent {
+        Some(id) => match tdl.get_by_id_mut(id) {
+            Some(e) => e.get_sub_tasks(),
+            None => return Err(Error::NotFound(id.clone())),
+        },
+        None => tdl,
+    };
+    let i = match after {
+        Some(id) => match list.position(id) {
+            Some(e) => e + 1,
+            None => return Err(Error::NotFound(id.clone())),
+        },
+        None => 0,
+    };
+    list.insert(i, t);
+    Ok(())
+}
+
+/// Set fields of a task by their names in JSON.
+fn _set(t: &mut Todo, fields: Map<String, Value>) -> Result<()> {
+    let mut v = serde_json::to_value(t.details())?;
+    if let Value::Object(e) = &mut v {
+        e.extend(fields);
+    }
+    t.replace_details(serde_json::from_value(v)?);
+    Ok(())
+}
+
+fn _replay(tdl: &mut TodoList, event: &Event) -> Result<()> {
+    let not_found = || Error::NotFound(String::from(event.id()));
+    match event {
+        Event::Created {
+            parent,
+            after,
+            task,
+        }
+        | Event::Repeated {
+            parent,
+            after,
+            task,
+            ..
+        } => _place(tdl, parent, after, task.clone()),
+        Event::Moved { id, parent, after } => {
+            let t = tdl.remove_by_id(id).ok_or_else(not_found)?;
+            _place(tdl, parent, after, t)
+        }
+        Event::Deleted { id } => tdl.remove_by_id(id).map(|_| ()).ok_or_else(not_found),
+        Event::Edited { id, field, value } => {
+            let t = tdl.get_by_id_mut(id).ok_or_else(not_found)?;
+            _set(t, Map::from_iter([(field.clone(), value.clone())]))
+        }
+        Event::Completed { id, completed } => {
+            let t = tdl.get_by_id_mut(id).ok_or_else(not_found)?;
+            let fields = [
+                (String::from("status"), serde_json::to_value(Status::Done)?),
+                (String::from("completed"), completed.clone()),
+            ];
+            _set(t, Map::from_iter(fields))
+        }
+    }
+}
+
+/// The events that turn one list into another, or None if replaying them
+/// wouldn't give exactly the same list.
+///
+/// Every task is placed after the one before it in its list, skipping tasks
+/// that belong in other lists or are deleted, which are moved or deleted in
+/// turn. Only tasks that aren't already there are moved.
+fn _events(before: &TodoList, after: &TodoList) -> Result<Option<Vec<Event>>> {
+    let mut events = Vec::new();
+    let mut work = before.clone();
+    let parents_before = before.parents();
+    let parents_after = after.parents();
+    let old: HashMap<&str, &Todo> = before.walk().map(|(_, t)| (t.id(), t)).collect();
+
+    let mut lists = vec![(None, after)];
+    while let Some((parent, list)) = lists.pop() {
+        let mut prev: Option<String> = None;
+        for t in list {
+            let id = t.id();
+            if !parents_before.contains_key(id) {
+                // Its sub tasks are placed after it, some may have been moved
+                // into it.
+                let mut task = t.clone();
+                *task.get_sub_tasks() = TodoList::new();
+                let event = match _repeat_of(list, t, &old) {
+                    Some(e) => Event::Repeated {
+                        id: String::from(e),
+                        parent: parent.clone(),
+                        after: prev.clone(),
+                        task,
+                    },
+                    None => Event::Created {
+                        parent: parent.clone(),
+                        after: prev.clone(),
+                        task,
+                    },
+                };
+                _replay(&mut work, &event)?;
+                events.push(event);
+                lists.push((Some(String::from(id)), t.sub_tasks()));
+                prev = Some(String::from(id));
+                continue;
+            }
+
+            let placed = match _list(&work, &parent) {
+                Some(l) => {
+                    let siblings: Vec<&str> = l
+                        .iter()
+                        .map(|x| x.id())
+                        .filter(|x| parents_after.get(*x) == Some(&parent))
+                        .collect();
+                    match siblings.iter().position(|x| *x == id) {
+                        Some(0) => prev.is_none(),
+                        Some(i) => prev.as_deref() == Some(siblings[i - 1]),
+                        None => false,
+                    }
+                }
+                None => false,
+            };
+            if !placed {
+                let event = Event::Moved {
+                    id: String::from(id),
+                    parent: parent.clone(),
+                    after: prev.clone(),
+                };
+                _replay(&mut work, &event)?;
+                events.push(event);
+            }
+
+            for event in _edits(old[id], t)? {
+                _replay(&mut work, &event)?;
+                events.push(event);
+            }
+            lists.push((Some(String::from(id)), t.sub_tasks()));
+            prev = Some(String::from(id));
+        }
+    }
+
+    // Whatever's left that isn't in the new list was deleted, along with
+    // its sub tasks.
+    for (_, t) in before.walk() {
+        let deleted = !parents_after.contains_key(t.id());
+        let parent_deleted = match &parents_before[t.id()] {
+            Some(p) => !parents_after.contains_key(p),
+            None => false,
+        };
+        if deleted && !parent_deleted {
+            let event = Event::Deleted {
+                id: String::from(t.id()),
+            };
+            _replay(&mut work, &event)?;
+            events.push(event);
+        }
+    }
+
+    match work.to_json()? == after.to_json()? {
+        true => Ok(Some(events)),
+        false => Ok(None),
+    }
+}
+
+/// The list a task with the given parent is in.
+fn _list<'a>(tdl: &'a TodoList, parent: &Option<String>) -> Option<&'a TodoList> {
+    match parent {
+        Some(id) => tdl.get_by_id(id).map(|x| x.sub_tasks()),
+        None => Some(tdl),
+    }
+}
+
+/// The task a new task is the next instance of, the repeating task it was
+/// added just before when that was completed.
+fn _repeat_of<'a>(list: &'a TodoList, t: &Todo, old: &HashMap<&str, &Todo>) -> Option<&'a str> {
+    let i = list.position(t.id())?;
+    let next = list.iter().nth(i + 1)?;
+    let was_open = old.get(next.id()).map(|x| x.is_open()) == Some(true);
+    match was_open && next.is_completed() && next.has_repeat() && next.title() == t.title() {
+        true => Some(next.id()),
+        false => None,
+    }
+}
+
+/// Events for every field of a task that changed, leaving out its sub
+/// tasks.
+fn _edits(before: &Todo, after: &Todo) -> Result<Vec<Event>> {
+    let (b, a) = (
+        serde_json::to_value(before.details())?,
+        serde_json::to_value(after.details())?,
+    );
+    let (b, a) = match (b, a) {
+        (Value::Object(b), Value::Object(a)) => (b, a),
+        _ => return Ok(Vec::new()),
+    };
+
+    let mut events = Vec::new();
+    let completed = before.is_open() && after.is_completed();
+    if completed {
+        events.push(Event::Completed {
+            id: String::from(after.id()),
+            completed: a.get("completed").cloned().unwrap_or(Value::Null),
+        });
+    }
+    for (field, value) in a {
+        if completed && (field == "status" || field == "completed") {
+            continue;
+        }
+        if b.get(&field) != Some(&value) {
+            events.push(Event::Edited {
+                id: String::from(after.id()),
+                field,
+                value,
+            });
+        }
+    }
+    Ok(events)
+}
@@ -0,0 +1,267 @@
+use crate::structs::{derive_id, generate_id};
+use crate::{Dialect, Error, Result, Source, Status, Todo, TodoList};
+use std::collections::{HashMap, HashSet};
+
+/// Columns a tab counts for when comparing indentation.
+const TAB_WIDTH: usize = 4;
+
+/// A list item line, `- text`, `* text`, `+ text` or `1. text`.
+pub(crate) struct Item<'a> {
+    /// Indentation width, with tabs expanded.
+    pub indent: usize,
+    /// Byte offset of the text after the bullet.
+    pub offset: usize,
+    pub text: &'a str,
+}
+
+impl<'a> Item<'a> {
+    pub fn parse(line: &'a str) -> Option<Item<'a>> {
+        let body = line.trim_start();
+        let indent = line[..line.len() - body.len()]
+            .chars()
+            .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+            .sum();
+
+        let digits = body.chars().take_while(|c| c.is_ascii_digit()).count();
+        let rest = match digits {
+            0 => body.strip_prefix(['-', '*', '+'])?,
+            _ => body[digits..].strip_prefix(['.', ')'])?,
+        };
+        let text = match rest.strip_prefix([' ', '\t']) {
+            Some(e) => e,
+            None if rest.is_empty() => rest,
+            None => return None,
+        };
+        Some(Item {
+            indent,
+            offset: line.len() - text.len(),
+            text,
+        })
+    }
+
+    /// The item as a task, `[x] text`, giving its status, the task text and
+    /// the byte offset of the task text in the line.
+    pub fn task(&self) -> Option<(Status, &'a str, usize)> {
+        let rest = self.text.strip_prefix('[')?;
+        let mut chars = rest.char_indices();
+        let (_, c) = chars.next()?;
+        let (i, close) = chars.next()?;
+        let text = &rest[i + close.len_utf8()..];
+        if close != ']' || !(text.is_empty() || text.starts_with([' ', '\t'])) {
+            return None;
+        }
+        let body = text.trim();
+        let offset = self.offset + self.text.len() - text.trim_start().len();
+        Some((Status::from_symbol(c), body, offset))
+    }
+}
+
+enum Kind {
+    Task(Box<Todo>),
+    /// `- Sub Tasks:`, the tasks below it are sub tasks of the task above.
+    SubTasks,
+    /// `- Dependencies:`, written by older versions which nested the tasks a
+    /// task depended on. They are moved to the top level list and referenced
+    /// by id instead.
+    Dependencies,
+    /// Any other list item, only allowed in mixed markdown.
+    Other,
+}
+
+struct Frame {
+    indent: usize,
+    kind: Kind,
+}
+
+/// Where a task was read from.
+pub(crate) struct Location {
+    pub id: String,
+    /// 0 based line number.
+    pub line: usize,
+    /// Indentation and bullet before the checkbox.
+    pub prefix: String,
+    /// Whether the line had an explicit `🆔`.
+    pub has_id: bool,
+}
+
+/// Parse a markdown list of tasks, nesting by indentation. With `mixed`, any
+/// lines that aren't tasks are skipped, otherwise they are an error.
+///
+/// Tasks without an id get one from their title, and the path of the `note`
+/// they're in if given, so the same task in two notes of a vault gets two ids.
+pub(crate) fn parse(
+    s: &str,
+    dialect: Dialect,
+    mixed: bool,
+    note: Option<&str>,
+) -> Result<TodoList> {
+    parse_located(s, dialect, mixed, note).map(|(tdl, _)| tdl)
+}
+
+/// Like `parse`, also giving the location of every task in document order.
+pub(crate) fn parse_located(
+    s: &str,
+    dialect: Dialect,
+    mixed: bool,
+    note: Option<&str>,
+) -> Result<(TodoList, Vec<Location>)> {
+    let mut root = TodoList::new();
+    let mut locations = Vec::new();
+    let mut ids = HashSet::new();
+    // How many tasks without ids had the same title so far.
+    let mut copies: HashMap<String, usize> = HashMap::new();
+    let mut stack: Vec<Frame> = Vec::new();
+    // Headings above the current line as (level, text).
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for (i, line) in s.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if mixed {
+            // Nothing in code blocks is a task or heading.
+            let trimmed = line.trim_start();
+            match fence {
+                Some(f) => {
+                    if trimmed.starts_with(f) {
+                        fence = None;
+                    }
+                    continue;
+                }
+                None => {
+                    if let Some(f) = ["```", "~~~"].into_iter().find(|f| trimmed.starts_with(*f)) {
+                        fence = Some(f);
+                        continue;
+                    }
+                }
+            }
+            if let Some((level, text)) = heading(line) {
+                close(&mut stack, &mut root, 0);
+                headings.retain(|(l, _)| *l < level);
+                headings.push((level, String::from(text)));
+                continue;
+            }
+        }
+        let item = match Item::parse(line) {
+            Some(e) => e,
+            None if mixed => {
+                // Prose and headings end any list above them.
+                if !line.starts_with([' ', '\t']) {
+                    close(&mut stack, &mut root, 0);
+                }
+                continue;
+            }
+            None => return Err(Error::parse(i + 1, 1, "Expected a list item")),
+        };
+
+        close(&mut stack, &mut root, item.indent);
+
+        let kind = match item.task() {
+            Some((status, body, offset)) => {
+                let column = line[..offset].chars().count();
+                let mut t =
+                    Todo::from_markdown_line(body, dialect).map_err(|e| e.offset(i, column))?;
+                t.set_parsed_status(status);
+                t.set_source(Some(Source {
+                    file: None,
+                    line: i + 1,
+                    headings: headings.iter().map(|(_, x)| x.clone()).collect(),
+                }));
+                let has_id = body.contains('🆔');
+                if !has_id {
+                    // Tasks without an id get one from their title, so it
+                    // stays the same while the title doesn't change, even
+                    // as the task is completed or its dates change.
+                    let n = copies.entry(String::from(t.title())).or_insert(0);
+                    let text = match note {
+                        Some(p) => format!("{}\n{}{}", p, n, t.title()),
+                        None => format!("{}{}", n, t.title()),
+                    };
+                    t.set_id(derive_id(&text));
+                    *n += 1;
+                }
+                // Copied tasks keep their id, give the copies new ones.
+                if !ids.insert(String::from(t.id())) {
+                    t.set_id(generate_id());
+                }
+                locations.push(Location {
+                    id: String::from(t.id()),
+                    line: i,
+                    prefix: String::from(&line[..item.offset]),
+                    has_id,
+                });
+                Kind::Task(Box::new(t))
+            }
+            None => match item.text.trim() {
+                "Sub Tasks:" => Kind::SubTasks,
+                "Dependencies:" => Kind::Dependencies,
+                _ if mixed => Kind::Other,
+                _ => {
+                    let column = line[..item.offset].chars().count() + 1;
+                    return Err(Error::parse(i + 1, column, "Expected a task"));
+                }
+            },
+        };
+        stack.push(Frame {
+            indent: item.indent,
+            kind,
+        });
+    }
+    close(&mut stack, &mut root, 0);
+
+    Ok((root, locations))
+}
+
+/// An ATX heading, `## text`, giving its level and text.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[level..];
+    if level == 0 || level > 6 || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    // Closing hashes aren't part of the text.
+    Some((level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+/// Pop every frame indented at least `indent`, attaching finished tasks to
+/// the task above them.
+fn close(stack: &mut Vec<Frame>, root: &mut TodoList, indent: usize) {
+    while stack.last().map(|f| f.indent >= indent) == Some(true) {
+        let t = match stack.pop() {
+            Some(Frame {
+                kind: Kind::Task(t),
+                ..
+            }) => *t,
+            _ => continue,
+        };
+
+        let mut dependency = false;
+        let mut parent = None;
+        for (i, f) in stack.iter().enumerate().rev() {
+            match f.kind {
+                Kind::Task(_) => {
+                    parent = Some(i);
+                    break;
+                }
+                Kind::Dependencies => dependency = true,
+                Kind::SubTasks | Kind::Other => (),
+            }
+        }
+
+        match (parent, dependency) {
+            (Some(i), false) => {
+                if let Kind::Task(p) = &mut stack[i].kind {
+                    p.get_sub_tasks().add(t);
+                }
+            }
+            (Some(i), true) => {
+                if let Kind::Task(p) = &mut stack[i].kind {
+                    p.add_dependency(String::from(t.id()));
+                }
+                root.add(t);
+            }
+            (None, _) => root.add(t),
+        }
+    }
+}
@@ -1,9 +1,28 @@
+pub mod document;
+pub mod graph;
+pub mod history;
+pub mod journal;
+mod markdown;
+mod quick_add;
+mod recurrence;
 pub mod todo;
 pub mod todo_list;
 mod utils;
+pub mod vault;
 
-pub use self::todo::Todo;
-pub use self::todo_list::TodoList;
+pub use self::document::Document;
+pub use self::graph::DependencyGraph;
+pub use self::history::{History, Step};
+pub use self::journal::Journal;
+use self::recurrence::Rule;
+pub use self::todo::{Dialect, Priority, Source, Status, Todo};
+pub use self::todo_list::{SortKey, TodoList};
+use self::utils::content_hash;
+use self::utils::derive_id;
+use self::utils::generate_id;
+pub use self::utils::Date;
+use self::utils::DateTime;
 use self::utils::Duration;
-use self::utils::NaiveDateTime;
 use self::utils::Schedule;
+use self::utils::Zone;
+pub use self::vault::{Changes, Vault};
@@ -0,0 +1,212 @@
+use crate::dates;
+use crate::structs::Date;
+use crate::{Error, Priority, Result, Todo};
+use chrono::{NaiveDateTime, Weekday};
+use std::str::FromStr;
+
+/// Longest run of words tried as a date, like `jan 5 2028 at 9am`.
+const DATE_WORDS: usize = 5;
+/// Longest run of words tried as a repeat, like
+/// `month on the last weekday for 6 times when done`.
+const REPEAT_WORDS: usize = 12;
+
+/// Read a whole task from a line like
+/// `Write report tomorrow 5pm #work every monday 30m !high`.
+///
+/// Tags are `#words`, priorities `!high` or a priority emoji, durations like
+/// `30m` or `1h30m`, repeats start with `every` and the due date is any
+/// date `dates::parse` reads, optionally after `by`, `on` or `due`. The rest
+/// is the title.
+pub(crate) fn parse(s: &str, now: NaiveDateTime, week_start: Weekday) -> Result<Todo> {
+    let mut words: Vec<&str> = Vec::new();
+    let mut tags = Vec::new();
+    let mut priority = None;
+    let mut duration = None;
+    let mut repeat = None;
+
+    let mut iter = s.split_whitespace();
+    while let Some(w) = iter.next() {
+        // The most words after `every` that are a repeat rule.
+        if w.eq_ignore_ascii_case("every") {
+            let rest: Vec<&str> = iter.clone().take(REPEAT_WORDS).collect();
+            let rule = (1..=rest.len()).rev().find_map(|n| {
+                let rule = format!("{} {}", w, rest[..n].join(" "));
+                let mut t = Todo::from_title(String::new());
+                t.set_repeat(rule.clone()).ok().map(|_| (n, rule))
+            });
+            match rule {
+                Some((n, rule)) => {
+                    iter.nth(n - 1);
+                    repeat = Some(rule);
+                }
+                None => words.push(w),
+            }
+            continue;
+        }
+        // Other `!words`, like `!urgent`, are part of the title.
+        if let Some(p) = w.strip_prefix('!').and_then(|x| Priority::from_str(x).ok()) {
+            priority = Some(p);
+            continue;
+        }
+        if let Some(p) = _single(w).and_then(Priority::from_emoji) {
+            priority = Some(p);
+            continue;
+        }
+        if let Some(m) = _minutes(w) {
+            duration = Some(m);
+            continue;
+        }
+        let rest = Todo::take_tags(w, &mut tags);
+        if !rest.is_empty() {
+            words.push(w);
+        }
+    }
+
+    // The longest run of words that's a date, the last one if there are
+    // several as long.
+    let mut due = None;
+    // Just a time of day, like `at 10am`.
+    let mut time_only = false;
+    'find: for len in (1..=DATE_WORDS.min(words.len())).rev() {
+        for start in (0..=words.len() - len).rev() {
+            if let Ok(d) = dates::parse(&words[start..start + len].join(" "), now, week_start) {
+                let from = match start.checked_sub(1).map(|i| words[i].to_lowercase()) {
+                    Some(e) if ["by", "on", "due", "at"].contains(&e.as_str()) => start - 1,
+                    _ => start,
+                };
+                let span = words.drain(from..start + len);
+                time_only =
+                    !d.is_all_day() && span.filter(|x| !x.eq_ignore_ascii_case("at")).count() == 1;
+                due = Some(d);
+                break 'find;
+            }
+        }
+    }
+
+    if words.is_empty() {
+        return Err(Error::parse(1, 1, "Missing a title"));
+    }
+    let mut t = Todo::from_title(String::new());
+    t.set_title(words.join(" "))?;
+    for e in tags {
+        if !t.tags().contains(&e) {
+            t.add_tag(e)?;
+        }
+    }
+    if let Some(p) = priority {
+        t.set_priority(p);
+    }
+    if let Some(m) = duration {
+        t.set_duration(format!("{}min", m))?;
+    }
+    if let Some(rule) = repeat {
+        t.set_repeat(rule)?;
+        // Repeating on given days with no date starts on the first of them,
+        // at the time given if there's one.
+        match (&due, t.first_due(now.date())) {
+            (None, first) => due = first,
+            (Some(d), Some(first)) if time_only => {
+                let time = d.naive_local().time();
+                due = Some(Date::floating(first.date().and_time(time)));
+            }
+            _ => (),
+        }
+    }
+    if let Some(d) = due {
+        t.set_due(d);
+    }
+    Ok(t)
+}
+
+/// The only character in a word, ignoring emoji variation selectors.
+fn _single(w: &str) -> Option<char> {
+    let mut chars = w.chars().filter(|c| *c != '\u{fe0f}');
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// Minutes in a duration like `30m`, `2h`, `1h30m` or `90min`.
+fn _minutes(w: &str) -> Option<u32> {
+    let w = w.to_lowercase();
+    let mut total: u32 = 0;
+    let mut rest = w.as_str();
+    while !rest.is_empty() {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let n: u32 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit = rest.len() - rest.trim_start_matches(|c: char| c.is_alphabetic()).len();
+        // Too long a duration isn't one, rather than wrapping around.
+        let minutes = match &rest[..unit] {
+            "h" | "hr" | "hrs" | "hour" | "hours" => n.checked_mul(60)?,
+            "m" | "min" | "mins" | "minute" | "minutes" => n,
+            _ => return None,
+        };
+        total = total.checked_add(minutes)?;
+        rest = &rest[unit..];
+    }
+    match total {
+        0 => None,
+        e => Some(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// Sunday 2026-10-18, 10:00.
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn minutes() {
+        assert_eq!(_minutes("30m"), Some(30));
+        assert_eq!(_minutes("2h"), Some(120));
+        assert_eq!(_minutes("1h30m"), Some(90));
+        assert_eq!(_minutes("90MIN"), Some(90));
+        for w in ["", "0m", "h", "30", "30s", "milk", "-5m"] {
+            assert_eq!(_minutes(w), None, "{}", w);
+        }
+        for w in ["99999999h", "4294967296m", "71582789h", "71582788h59m99m"] {
+            assert_eq!(_minutes(w), None, "{}", w);
+        }
+    }
+
+    #[test]
+    fn parses_a_whole_task() {
+        let t = parse(
+            "Write report tomorrow 5pm #work every monday 30m !high",
+            now(),
+            Weekday::Mon,
+        )
+        .unwrap();
+        assert_eq!(
+            t.markdown_line(false),
+            "[ ] Write report #work ⏫ 🕒 30min 🔁 every week on Monday 📅 2026-10-19 17:00:00"
+        );
+    }
+
+    #[test]
+    fn other_exclamations_are_part_of_the_title() {
+        let t = parse("Fix bug !urgent tomorrow !", now(), Weekday::Mon).unwrap();
+        assert_eq!(t.title(), "Fix bug !urgent !");
+        assert_eq!(t.priority(), Priority::Normal);
+        assert_eq!(t.due_date().map(|x| x.to_string()).unwrap(), "2026-10-19");
+        let t = parse("Fix bug !HIGH", now(), Weekday::Mon).unwrap();
+        assert_eq!(t.priority(), Priority::High);
+    }
+
+    #[test]
+    fn long_durations_are_part_of_the_title() {
+        let t = parse("Buy milk 99999999h", now(), Weekday::Mon).unwrap();
+        assert_eq!(t.title(), "Buy milk 99999999h");
+        assert!(t.duration().is_none());
+    }
+}
@@ -0,0 +1,716 @@
+use crate::{Error, Result};
+use chrono::{Datelike, Duration, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Periods looked through for the next time, so a rule that never happens
+/// again doesn't loop forever.
+const MAX_PERIODS: u32 = 5000;
+
+/// Most periods between times, far more than any task needs, so counting
+/// periods can't overflow.
+const MAX_INTERVAL: u32 = 1000;
+
+const WEEKDAYS: [Weekday; 5] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+];
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Freq {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// When a task repeats, in the style of an RFC 5545 `RRULE`, like every
+/// 2nd Tuesday of the month, the last weekday of the month or weekdays
+/// until the end of the year.
+///
+/// It's written as text like `every month on the 15th` or
+/// `every 2 weeks on Tuesday for 3 times`, or as an `RRULE:FREQ=...` for
+/// rules text can't say, and read back from either.
+#[derive(Clone, PartialEq)]
+pub struct Rule {
+    freq: Freq,
+    interval: u32,
+    /// Weekdays with which of them in the month or year, like the 2nd (2)
+    /// or last (-1) Tuesday, or 0 for all of them.
+    by_day: Vec<(i32, Weekday)>,
+    /// Days of the month, the last is -1.
+    by_month_day: Vec<i32>,
+    by_month: Vec<u32>,
+    /// Which of the days picked in each period, like the last (-1).
+    by_set_pos: Vec<i32>,
+    /// Times left, counting this one.
+    count: Option<u32>,
+    until: Option<NaiveDate>,
+    /// Repeats from when it's done instead of when it was due.
+    when_done: bool,
+}
+
+impl Rule {
+    pub fn is_when_done(&self) -> bool {
+        self.when_done
+    }
+
+    /// The rule for the next time, with one time less, or None if this
+    /// was the last.
+    pub fn next_rule(&self) -> Option<Rule> {
+        let mut r = self.clone();
+        r.count = match self.count {
+            Some(0) | Some(1) => return None,
+            Some(n) => Some(n - 1),
+            None => None,
+        };
+        Some(r)
+    }
+
+    /// The first day it happens after `after`, counting periods from
+    /// `start`, or None if it doesn't happen again before its end date.
+    pub fn next_after(&self, start: NaiveDate, after: NaiveDate) -> Option<NaiveDate> {
+        for k in 0..MAX_PERIODS {
+            let period = self._period(start, k.checked_mul(self.interval)?)?;
+            if self.until.is_some_and(|u| period > u) {
+                return None;
+            }
+            let next = self
+                ._days(period, start)
+                .into_iter()
+                .find(|d| *d > after && *d >= start);
+            if let Some(d) = next {
+                return match self.until {
+                    Some(u) if d > u => None,
+                    _ => Some(d),
+                };
+            }
+        }
+        None
+    }
+
+    /// The rule with the day of the month `start` is on, and its month for
+    /// yearly rules, if it goes by them and they're days some months don't
+    /// have. So a series from the 31st is on the last day of shorter months
+    /// and back on the 31st after, rather than staying on the 28th after
+    /// February.
+    pub fn anchored(&self, start: NaiveDate) -> Rule {
+        let mut r = self.clone();
+        let by_start = r.by_day.is_empty() && r.by_month_day.is_empty() && !r.when_done;
+        if by_start && start.day() > 28 {
+            match r.freq {
+                Freq::Monthly => r.by_month_day.push(start.day() as i32),
+                Freq::Yearly => {
+                    r.by_month_day.push(start.day() as i32);
+                    if r.by_month.is_empty() {
+                        r.by_month.push(start.month());
+                    }
+                }
+                Freq::Daily | Freq::Weekly => (),
+            }
+        }
+        r
+    }
+
+    /// The first day it happens from `today` on, which starts the periods
+    /// the interval counts.
+    pub fn first_from(&self, today: NaiveDate) -> Option<NaiveDate> {
+        let mut r = self.clone();
+        r.interval = 1;
+        r.next_after(today, today.pred_opt()?)
+    }
+
+    /// The first day of the nth period from the one `start` is in.
+    fn _period(&self, start: NaiveDate, n: u32) -> Option<NaiveDate> {
+        match self.freq {
+            Freq::Daily => start.checked_add_signed(Duration::try_days(n as i64)?),
+            Freq::Weekly => {
+                let monday = start - Duration::days(start.weekday().num_days_from_monday() as i64);
+                monday.checked_add_signed(Duration::try_weeks(n as i64)?)
+            }
+            Freq::Monthly => start.with_day(1)?.checked_add_months(Months::new(n)),
+            Freq::Yearly => {
+                let year = start.year().checked_add(i32::try_from(n).ok()?)?;
+                NaiveDate::from_ymd_opt(year, 1, 1)
+            }
+        }
+    }
+
+    /// The days it happens in a period, in order.
+    fn _days(&self, period: NaiveDate, start: NaiveDate) -> Vec<NaiveDate> {
+        let mut days = match self.freq {
+            Freq::Daily => vec![period],
+            Freq::Weekly => (0..7)
+                .map(|i| period + Duration::days(i))
+                .filter(|d| match self.by_day.is_empty() {
+                    true => d.weekday() == start.weekday(),
+                    false => true,
+                })
+                .collect(),
+            Freq::Monthly => self._month_days(period.year(), period.month(), start),
+            Freq::Yearly => {
+                let months = match self.by_month.is_empty() {
+                    true => vec![start.month()],
+                    false => self.by_month.clone(),
+                };
+                months
+                    .into_iter()
+                    .flat_map(|m| self._month_days(period.year(), m, start))
+                    .collect()
+            }
+        };
+        days.retain(|d| {
+            let weekday = self.by_day.is_empty()
+                || self.by_day.iter().any(|(_, w)| *w == d.weekday())
+                || self.freq == Freq::Monthly
+                || self.freq == Freq::Yearly;
+            let month = self.by_month.is_empty() || self.by_month.contains(&d.month());
+            weekday && month
+        });
+        days.sort();
+        days.dedup();
+        match self.by_set_pos.is_empty() {
+            true => days,
+            false => self
+                .by_set_pos
+                .iter()
+                .filter_map(|p| _nth(&days, *p).copied())
+                .collect(),
+        }
+    }
+
+    /// The days it happens in a month. Days past the end of a shorter month
+    /// are its last day, so the 31st is the 30th in April.
+    fn _month_days(&self, year: i32, month: u32, start: NaiveDate) -> Vec<NaiveDate> {
+        let first = match NaiveDate::from_ymd_opt(year, month, 1) {
+            Some(e) => e,
+            None => return Vec::new(),
+        };
+        let len = _month_len(first) as i32;
+        let day = |n: i32| first + Duration::days(n as i64 - 1);
+        let all: Vec<NaiveDate> = (1..=len).map(day).collect();
+
+        if !self.by_month_day.is_empty() {
+            let days = self.by_month_day.iter().filter_map(|n| match *n {
+                n if n > 0 => Some(day(n.min(len))),
+                n if n < 0 && -n <= len => Some(day(len + 1 + n)),
+                _ => None,
+            });
+            // Days of the month on given weekdays, like Friday the 13th.
+            return days
+                .filter(|d| {
+                    self.by_day.is_empty() || self.by_day.iter().any(|(_, w)| *w == d.weekday())
+                })
+                .collect();
+        }
+        if !self.by_day.is_empty() {
+            return self
+                .by_day
+                .iter()
+                .flat_map(|(n, w)| {
+                    let matching: Vec<NaiveDate> =
+                        all.iter().filter(|d| d.weekday() == *w).copied().collect();
+                    match n {
+                        0 => matching,
+                        n => _nth(&matching, *n).copied().into_iter().collect(),
+                    }
+                })
+                .collect();
+        }
+        vec![day((start.day() as i32).min(len))]
+    }
+
+    /// As an RFC 5545 `RRULE` value, without when it's done.
+    pub fn to_rrule(&self) -> String {
+        let freq = match self.freq {
+            Freq::Daily => "DAILY",
+            Freq::Weekly => "WEEKLY",
+            Freq::Monthly => "MONTHLY",
+            Freq::Yearly => "YEARLY",
+        };
+        let mut parts = vec![format!("FREQ={}", freq)];
+        if self.interval != 1 {
+            parts.push(format!("INTERVAL={}", self.interval));
+        }
+        let join = |v: Vec<String>| v.join(",");
+        if !self.by_day.is_empty() {
+            let days = self.by_day.iter().map(|(n, w)| match n {
+                0 => _rrule_day(*w).to_string(),
+                n => format!("{}{}", n, _rrule_day(*w)),
+            });
+            parts.push(format!("BYDAY={}", join(days.collect())));
+        }
+        if !self.by_month_day.is_empty() {
+            let days = self.by_month_day.iter().map(|x| x.to_string());
+            parts.push(format!("BYMONTHDAY={}", join(days.collect())));
+        }
+        if !self.by_month.is_empty() {
+            let months = self.by_month.iter().map(|x| x.to_string());
+            parts.push(format!("BYMONTH={}", join(months.collect())));
+        }
+        if !self.by_set_pos.is_empty() {
+            let pos = self.by_set_pos.iter().map(|x| x.to_string());
+            parts.push(format!("BYSETPOS={}", join(pos.collect())));
+        }
+        if let Some(n) = self.count {
+            parts.push(format!("COUNT={}", n));
+        }
+        if let Some(d) = self.until {
+            parts.push(format!("UNTIL={}", d.format("%Y%m%d")));
+        }
+        format!("RRULE:{}", parts.join(";"))
+    }
+
+    /// Read an RFC 5545 `RRULE` value, like `FREQ=MONTHLY;BYDAY=2TU`.
+    pub fn from_rrule(s: &str) -> Result<Rule> {
+        let invalid = |why: &str| Error::InvalidRepeat(format!("{}: {}", s, why));
+        let body = s.trim();
+        let body = match body.get(..6) {
+            Some(e) if e.eq_ignore_ascii_case("rrule:") => &body[6..],
+            _ => body,
+        };
+        let mut r = Rule::new(None);
+        for part in body.split(';').filter(|x| !x.is_empty()) {
+            let (key, value) = match part.split_once('=') {
+                Some((k, v)) => (k.trim().to_uppercase(), v.trim().to_uppercase()),
+                None => return Err(invalid(part)),
+            };
+            let numbers = |v: &str| -> Result<Vec<i32>> {
+                v.split(',')
+                    .map(|x| x.trim().parse().map_err(|_| invalid(part)))
+                    .collect()
+            };
+            match key.as_str() {
+                "FREQ" => {
+                    r.freq = match value.as_str() {
+                        "DAILY" => Freq::Daily,
+                        "WEEKLY" => Freq::Weekly,
+                        "MONTHLY" => Freq::Monthly,
+                        "YEARLY" => Freq::Yearly,
+                        _ => return Err(invalid("only daily, weekly, monthly and yearly")),
+                    }
+                }
+                "INTERVAL" => r.interval = value.parse().map_err(|_| invalid(part))?,
+                "COUNT" => r.count = Some(value.parse().map_err(|_| invalid(part))?),
+                "UNTIL" => {
+                    let date = value.get(..8).unwrap_or(&value);
+                    r.until =
+                        Some(NaiveDate::parse_from_str(date, "%Y%m%d").map_err(|_| invalid(part))?);
+                }
+                "BYDAY" => {
+                    for d in value.split(',').map(|x| x.trim()) {
+                        // Like `2TU`, the weekday is the last two letters.
+                        let split = d.len().saturating_sub(2);
+                        let (n, w) = match (d.get(..split), d.get(split..)) {
+                            (Some(n), Some(w)) => (n, w),
+                            _ => return Err(invalid(part)),
+                        };
+                        let w = _weekday(w).ok_or_else(|| invalid(part))?;
+                        let n = match n {
+                            "" => 0,
+                            n => n
+                                .trim_start_matches('+')
+                                .parse()
+                                .map_err(|_| invalid(part))?,
+                        };
+                        r.by_day.push((n, w));
+                    }
+                }
+                "BYMONTHDAY" => r.by_month_day = numbers(&value)?,
+                "BYMONTH" => r.by_month = numbers(&value)?.into_iter().map(|x| x as u32).collect(),
+                "BYSETPOS" => r.by_set_pos = numbers(&value)?,
+                "WKST" => (),
+                _ => return Err(invalid(&format!("{} isn't supported", key))),
+            }
+        }
+        r._check().map_err(|e| invalid(&e))?;
+        Ok(r)
+    }
+
+    fn new(freq: Option<Freq>) -> Rule {
+        Rule {
+            freq: freq.unwrap_or(Freq::Daily),
+            interval: 1,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+            by_set_pos: Vec::new(),
+            count: None,
+            until: None,
+            when_done: false,
+        }
+    }
+
+    fn _check(&self) -> std::result::Result<(), String> {
+        if self.interval == 0 {
+            return Err(String::from("the interval must be at least 1"));
+        }
+        if self.interval > MAX_INTERVAL {
+            return Err(format!("the interval is at most {}", MAX_INTERVAL));
+        }
+        let month_day = |d: &i32| (1..=31).contains(&d.unsigned_abs());
+        if !self.by_month_day.iter().all(month_day) {
+            return Err(String::from("days of the month are 1 to 31"));
+        }
+        if self.by_month.iter().any(|m| *m == 0 || *m > 12) {
+            return Err(String::from("months are 1 to 12"));
+        }
+        if self.by_day.iter().any(|(n, _)| n.unsigned_abs() > 53) {
+            return Err(String::from("weekdays are at most the 53rd"));
+        }
+        if self.by_set_pos.contains(&0) {
+            return Err(String::from("positions start at 1"));
+        }
+        Ok(())
+    }
+
+    /// Read the text after `every`, like `2nd tuesday`, `weekday`,
+    /// `month on the 15th` or `last weekday of the month`.
+    fn _from_text(s: &str) -> Result<Rule> {
+        let invalid = || Error::InvalidRepeat(format!("every {}", s));
+        let words: Vec<String> = s
+            .to_lowercase()
+            .replace(',', " ")
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+
+        let mut freq = None;
+        let mut r = Rule::new(None);
+        let mut i = 0;
+        let word = |i: usize| words.get(i).map(|x| x.as_str());
+        while let Some(w) = word(i) {
+            i += 1;
+            match w {
+                "and" | "on" | "the" | "of" | "in" => (),
+                "other" => r.interval = 2,
+                "day" | "days" => freq = Some(Freq::Daily),
+                "week" | "weeks" => freq = Some(Freq::Weekly),
+                "month" | "months" => freq = Some(Freq::Monthly),
+                "year" | "years" => freq = Some(Freq::Yearly),
+                "weekday" | "weekdays" => r.by_day.extend(WEEKDAYS.map(|x| (0, x))),
+                "until" => {
+                    let d = word(i).ok_or_else(invalid)?;
+                    r.until =
+                        Some(NaiveDate::parse_from_str(d, "%Y-%m-%d").map_err(|_| invalid())?);
+                    i += 1;
+                }
+                "for" => (),
+                "times" | "time" => return Err(invalid()),
+                w if w.parse::<u32>().is_ok() => {
+                    let n: u32 = w.parse().map_err(|_| invalid())?;
+                    match word(i) {
+                        Some("times" | "time") => {
+                            r.count = Some(n);
+                            i += 1;
+                        }
+                        // A day of a month, like `january 15`.
+                        _ if i >= 2 && word(i - 2).and_then(_month).is_some() => {
+                            r.by_month_day.push(n as i32)
+                        }
+                        _ if i == 1 => r.interval = n,
+                        _ => return Err(invalid()),
+                    }
+                }
+                w if _month(w).is_some() => r.by_month.extend(_month(w)),
+                w if _weekday(w).is_some() => r.by_day.extend(_weekday(w).map(|x| (0, x))),
+                w => {
+                    let n = _ordinal(w).ok_or_else(invalid)?;
+                    match word(i) {
+                        Some("weekday") => {
+                            r.by_day.extend(WEEKDAYS.map(|x| (0, x)));
+                            r.by_set_pos.push(n);
+                            i += 1;
+                        }
+                        Some("day") => {
+                            r.by_month_day.push(n);
+                            i += 1;
+                        }
+                        Some(e) if _weekday(e).is_some() => {
+                            r.by_day.extend(_weekday(e).map(|x| (n, x)));
+                            i += 1;
+                        }
+                        _ => r.by_month_day.push(n),
+                    }
+                }
+            }
+        }
+
+        // Without a period, the days it's on say what it is, like the 2nd
+        // Tuesday being monthly.
+        let ordinal = r.by_day.iter().any(|(n, _)| *n != 0);
+        r.freq = match freq {
+            Some(e) => e,
+            None if !r.by_month.is_empty() => Freq::Yearly,
+            None if ordinal || !r.by_month_day.is_empty() || !r.by_set_pos.is_empty() => {
+                Freq::Monthly
+            }
+            None if !r.by_day.is_empty() => Freq::Weekly,
+            None => return Err(invalid()),
+        };
+        let daily = r.freq == Freq::Daily || r.freq == Freq::Weekly;
+        if (daily && (ordinal || !r.by_month_day.is_empty())) || r._check().is_err() {
+            return Err(invalid());
+        }
+        Ok(r)
+    }
+
+    /// As text like `every 2 weeks on Tuesday`, or None if text can't say
+    /// it.
+    fn _text(&self) -> Option<String> {
+        let unit = match self.freq {
+            Freq::Daily => "day",
+            Freq::Weekly => "week",
+            Freq::Monthly => "month",
+            Freq::Yearly => "year",
+        };
+        let mut s = match self.interval {
+            1 => format!("every {}", unit),
+            n => format!("every {} {}s", n, unit),
+        };
+        let plain = self.by_day.iter().all(|(n, _)| *n == 0);
+        let weekdays = self.by_day.len() == 5 && plain && WEEKDAYS.iter().all(|w| self._on(*w));
+        let months = self.by_month.len();
+        let month_days = self.by_month_day.len();
+        let set_pos = self.by_set_pos.len();
+
+        match self.freq {
+            _ if self.by_day.is_empty() && months == 0 && month_days == 0 && set_pos == 0 => (),
+            Freq::Weekly if weekdays && self.interval == 1 && months + set_pos == 0 => {
+                s = String::from("every weekday")
+            }
+            Freq::Weekly if plain && months + month_days + set_pos == 0 => {
+                let days = self.by_day.iter().map(|(_, w)| _weekday_name(*w));
+                s = format!("{} on {}", s, _list(days.collect()));
+            }
+            Freq::Monthly if self.by_day.is_empty() && months + set_pos == 0 => {
+                let days = self.by_month_day.iter().map(|n| match n {
+                    -1 => Some(String::from("last day")),
+                    n if *n > 0 => Some(_ordinal_name(*n)),
+                    _ => None,
+                });
+                s = format!("{} on the {}", s, _list(days.collect::<Option<_>>()?));
+            }
+            Freq::Monthly if !plain && months + month_days + set_pos == 0 => {
+                let days = self.by_day.iter().map(|(n, w)| match n {
+                    -1 | 1..=5 => Some(format!("{} {}", _ordinal_name(*n), _weekday_name(*w))),
+                    _ => None,
+                });
+                s = format!("{} on the {}", s, _list(days.collect::<Option<_>>()?));
+            }
+            Freq::Monthly if weekdays && set_pos == 1 && months + month_days == 0 => {
+                match self.by_set_pos[0] {
+                    n @ (-1 | 1..=5) => s = format!("{} on the {} weekday", s, _ordinal_name(n)),
+                    _ => return None,
+                }
+            }
+            Freq::Yearly if self.by_day.is_empty() && months == 1 && month_days == 1 => {
+                let (m, d) = (self.by_month[0], self.by_month_day[0]);
+                match d > 0 {
+                    true => s = format!("{} on {} {}", s, _month_name(m), d),
+                    false => return None,
+                }
+            }
+            _ => return None,
+        }
+        match self.count {
+            Some(1) => s = format!("{} for 1 time", s),
+            Some(n) => s = format!("{} for {} times", s, n),
+            None => (),
+        }
+        if let Some(d) = self.until {
+            s = format!("{} until {}", s, d.format("%Y-%m-%d"));
+        }
+        Some(s)
+    }
+
+    fn _on(&self, w: Weekday) -> bool {
+        self.by_day.iter().any(|(_, x)| *x == w)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self._text() {
+            Some(e) => write!(f, "{}", e)?,
+            None => write!(f, "{}", self.to_rrule())?,
+        }
+        match self.when_done {
+            true => write!(f, " when done"),
+            false => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = Error;
+
+    /// Read text like `every weekday` or `every month on the 15th when
+    /// done`, or an `RRULE:FREQ=...`.
+    fn from_str(s: &str) -> Result<Rule> {
+        let s = s.trim();
+        let lower = s.to_lowercase();
+        let (s, when_done) = match ["when done", "from completed", "after completed"]
+            .iter()
+            .find(|x| lower.ends_with(*x))
+        {
+            Some(e) => (s.get(..s.len() - e.len()).unwrap_or(s).trim_end(), true),
+            None => (s, false),
+        };
+        let lower = s.to_lowercase();
+        let mut r = match lower.strip_prefix("every ") {
+            _ if lower.starts_with("rrule:") || lower.contains("freq=") => Rule::from_rrule(s)?,
+            Some(_) => Rule::_from_text(&s[6..])?,
+            None => return Err(Error::InvalidRepeat(String::from(s))),
+        };
+        r.when_done = when_done;
+        Ok(r)
+    }
+}
+
+impl Serialize for Rule {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Rule {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Rule, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Rule::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The nth item, counting from the end for negative n.
+fn _nth<T>(v: &[T], n: i32) -> Option<&T> {
+    match n {
+        n if n > 0 => v.get(n as usize - 1),
+        n if n < 0 => v
+            .len()
+            .checked_sub(n.unsigned_abs() as usize)
+            .map(|i| &v[i]),
+        _ => None,
+    }
+}
+
+fn _month_len(first: NaiveDate) -> u32 {
+    match first.checked_add_months(Months::new(1)) {
+        Some(e) => (e - first).num_days() as u32,
+        None => 31,
+    }
+}
+
+fn _weekday(s: &str) -> Option<Weekday> {
+    match s.to_lowercase().as_str() {
+        "mo" | "mon" | "monday" | "mondays" => Some(Weekday::Mon),
+        "tu" | "tue" | "tues" | "tuesday" | "tuesdays" => Some(Weekday::Tue),
+        "we" | "wed" | "wednesday" | "wednesdays" => Some(Weekday::Wed),
+        "th" | "thu" | "thur" | "thurs" | "thursday" | "thursdays" => Some(Weekday::Thu),
+        "fr" | "fri" | "friday" | "fridays" => Some(Weekday::Fri),
+        "sa" | "sat" | "saturday" | "saturdays" => Some(Weekday::Sat),
+        "su" | "sun" | "sunday" | "sundays" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn _rrule_day(w: Weekday) -> &'static str {
+    match w {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn _weekday_name(w: Weekday) -> String {
+    let name = match w {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    };
+    String::from(name)
+}
+
+fn _month(s: &str) -> Option<u32> {
+    let s = s.trim_end_matches('.');
+    MONTHS
+        .iter()
+        .position(|m| s.len() >= 3 && m.starts_with(s))
+        .map(|i| i as u32 + 1)
+}
+
+fn _month_name(m: u32) -> String {
+    let name = MONTHS[(m as usize - 1) % 12];
+    format!("{}{}", name[..1].to_uppercase(), &name[1..])
+}
+
+/// Ordinals like `2nd`, `second` or `last`.
+fn _ordinal(s: &str) -> Option<i32> {
+    let words = ["first", "second", "third", "fourth", "fifth"];
+    if s == "last" {
+        return Some(-1);
+    }
+    if let Some(i) = words.iter().position(|x| *x == s) {
+        return Some(i as i32 + 1);
+    }
+    let digits = s.trim_end_matches(|c: char| c.is_alphabetic());
+    match &s[digits.len()..] {
+        "st" | "nd" | "rd" | "th" => digits.parse().ok(),
+        _ => None,
+    }
+}
+
+fn _ordinal_name(n: i32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    match n {
+        -1 => String::from("last"),
+        n => format!("{}{}", n, suffix),
+    }
+}
+
+/// Items like `a, b and c`.
+fn _list(v: Vec<String>) -> String {
+    match v.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
@@ -1,330 +1,1019 @@
+use crate::structs::generate_id;
+use crate::structs::quick_add;
 use crate::structs::Duration;
-use crate::structs::NaiveDateTime;
+use crate::structs::Rule;
 use crate::structs::Schedule;
-use crate::TodoList;
-use chrono::{offset::TimeZone, Local, LocalResult};
+use crate::structs::{Date, DateTime};
+use crate::{Error, Result, TodoList};
+use chrono::{offset::TimeZone, Local, NaiveDateTime, Utc, Weekday};
+use chrono_tz::Tz;
 use core::time::Duration as StdDuration;
 use duration_human::DurationHuman;
-use serde::{Deserialize, Serialize};
+use serde::de::{value::MapAccessDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp::max;
+use std::fmt;
 use std::str::FromStr;
 
 #[derive(Clone, Serialize, Deserialize)]
 enum Repeat {
     FromCompleted(Duration),
     FromDue(Duration),
-    Every(Schedule),
+    Every(Box<Schedule>),
+    Rule(Box<Rule>),
 }
 
-impl ToString for Repeat {
-    fn to_string(&self) -> String {
+/// Human readable duration, falling back to milliseconds for values that
+/// can't be represented, like negative durations read from json.
+fn human_duration(d: &Duration) -> String {
+    match d.to_std() {
+        Ok(d) => DurationHuman::from(d).to_string(),
+        Err(_) => format!("{}ms", d.num_milliseconds()),
+    }
+}
+
+impl fmt::Display for Repeat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Repeat::FromCompleted(d) => match d.to_std() {
-                Ok(d) => format!("{} after completed", DurationHuman::from(d)),
-                Err(_) => panic!("Unexpected"),
-            },
-            Repeat::FromDue(d) => match d.to_std() {
-                Ok(d) => format!("after {}", DurationHuman::from(d)),
-                Err(_) => panic!("Unexpected"),
-            },
-            Repeat::Every(s) => format!("every {}", s.to_string()),
+            Repeat::FromCompleted(d) => write!(f, "{} after completed", human_duration(d)),
+            Repeat::FromDue(d) => write!(f, "after {}", human_duration(d)),
+            Repeat::Every(s) => write!(f, "every {}", s),
+            Repeat::Rule(r) => write!(f, "{}", r),
+        }
+    }
+}
+
+/// Task priority, using the markers of the Obsidian Tasks plugin.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Priority {
+    Lowest,
+    Low,
+    #[default]
+    Normal,
+    Medium,
+    High,
+    Highest,
+}
+
+impl Priority {
+    /// The Obsidian Tasks emoji, normal priority has none.
+    pub fn emoji(&self) -> Option<char> {
+        match self {
+            Priority::Lowest => Some('⏬'),
+            Priority::Low => Some('🔽'),
+            Priority::Normal => None,
+            Priority::Medium => Some('🔼'),
+            Priority::High => Some('⏫'),
+            Priority::Highest => Some('🔺'),
         }
     }
+
+    pub fn from_emoji(c: char) -> Option<Priority> {
+        match c {
+            '⏬' => Some(Priority::Lowest),
+            '🔽' => Some(Priority::Low),
+            '🔼' => Some(Priority::Medium),
+            '⏫' => Some(Priority::High),
+            '🔺' => Some(Priority::Highest),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Priority::Lowest => "lowest",
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+            Priority::Highest => "highest",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for Priority {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Priority> {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if let Some(p) = Priority::from_emoji(c) {
+                return Ok(p);
+            }
+        }
+        match s.to_lowercase().as_str() {
+            "lowest" => Ok(Priority::Lowest),
+            "low" => Ok(Priority::Low),
+            "normal" | "none" => Ok(Priority::Normal),
+            "medium" | "med" => Ok(Priority::Medium),
+            "high" => Ok(Priority::High),
+            "highest" => Ok(Priority::Highest),
+            _ => Err(Error::InvalidPriority(String::from(s))),
+        }
+    }
+}
+
+/// Task status, written as the character in the markdown checkbox.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Status {
+    #[default]
+    Open,
+    InProgress,
+    Done,
+    Cancelled,
+    /// Any other checkbox character, like `>` for deferred. Custom statuses
+    /// count as open.
+    Custom(char),
+}
+
+impl Status {
+    pub fn symbol(&self) -> char {
+        match self {
+            Status::Open => ' ',
+            Status::InProgress => '/',
+            Status::Done => 'x',
+            Status::Cancelled => '-',
+            Status::Custom(c) => *c,
+        }
+    }
+
+    pub fn from_symbol(c: char) -> Status {
+        match c {
+            ' ' => Status::Open,
+            '/' => Status::InProgress,
+            'x' | 'X' => Status::Done,
+            '-' => Status::Cancelled,
+            c => Status::Custom(c),
+        }
+    }
+
+    /// Whether the task still needs doing, so it can block others.
+    pub fn is_open(&self) -> bool {
+        !matches!(self, Status::Done | Status::Cancelled)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Status::Open => write!(f, "open"),
+            Status::InProgress => write!(f, "in progress"),
+            Status::Done => write!(f, "done"),
+            Status::Cancelled => write!(f, "cancelled"),
+            Status::Custom(c) => write!(f, "[{}]", c),
+        }
+    }
+}
+
+impl FromStr for Status {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Status> {
+        match s.to_lowercase().as_str() {
+            "open" | "todo" => Ok(Status::Open),
+            "in progress" | "in-progress" | "inprogress" | "doing" => Ok(Status::InProgress),
+            "done" | "completed" => Ok(Status::Done),
+            "cancelled" | "canceled" => Ok(Status::Cancelled),
+            _ => {
+                let s = s.trim_start_matches('[').trim_end_matches(']');
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Ok(Status::from_symbol(c)),
+                    _ => Err(Error::InvalidStatus(String::from(s))),
+                }
+            }
+        }
+    }
+}
+
+/// Markdown vocabulary accepted when parsing.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Dialect {
+    /// Only the Obsidian Tasks plugin emoji.
+    Tasks,
+    /// The Tasks emoji plus older markers written by this crate, like ✈️ for
+    /// the start date.
+    #[default]
+    Compat,
+}
+
+/// Emoji that start a field of a task line, see `Todo::from_markdown_line`.
+/// Titles and tags can't have them, or the ✈ older versions used for the
+/// start date.
+const SIGNIFIERS: [char; 15] = [
+    '🆔', '⛔', '🔺', '⏫', '🔼', '🔽', '⏬', '🕒', '🔁', '➕', '🛫', '⏳', '📅', '❌', '✅',
+];
+
+/// Where a task was read from.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Source {
+    /// Path of the file, if it was read from one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// 1 based line number.
+    pub line: usize,
+    /// Text of the headings the task is under, outermost first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headings: Vec<String>,
+}
+
+impl Source {
+    /// The heading directly above the task.
+    pub fn section(&self) -> Option<&str> {
+        self.headings.last().map(|x| x.as_str())
+    }
+
+    /// Whether the task is under a heading, at any level, ignoring case.
+    pub fn is_under(&self, heading: &str) -> bool {
+        self.headings
+            .iter()
+            .any(|x| x.to_lowercase() == heading.to_lowercase())
+    }
+}
+
+/// Ids of the tasks a task depends on, stored as a list of ids.
+///
+/// Files written before ids existed nested the tasks themselves as a
+/// `TodoList`. Those are kept in `nested` until the list holding the task
+/// moves them next to it, see `Todo::take_nested_dependencies`.
+#[derive(Clone, Default)]
+struct Dependencies {
+    ids: Vec<String>,
+    nested: Vec<Todo>,
+}
+
+impl Serialize for Dependencies {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.ids.serialize(serializer)
+    }
+}
+
+/// The `{"items": [...]}` list older versions wrote.
+#[derive(Deserialize)]
+struct NestedDependencies {
+    items: Vec<Todo>,
+}
+
+struct DependenciesVisitor;
+
+impl<'de> Visitor<'de> for DependenciesVisitor {
+    type Value = Dependencies;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "A list of task ids")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, v: A) -> std::result::Result<Self::Value, A::Error> {
+        let ids = Vec::deserialize(serde::de::value::SeqAccessDeserializer::new(v))?;
+        Ok(Dependencies {
+            ids,
+            nested: Vec::new(),
+        })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, v: A) -> std::result::Result<Self::Value, A::Error> {
+        let nested = NestedDependencies::deserialize(MapAccessDeserializer::new(v))?.items;
+        Ok(Dependencies {
+            ids: nested.iter().map(|x| String::from(x.id())).collect(),
+            nested,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Dependencies {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_any(DependenciesVisitor)
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Todo {
-    completed: Option<NaiveDateTime>,
+    #[serde(default = "generate_id")]
+    id: String,
+    /// Files written before statuses existed only have the dates, see
+    /// `Todo::status`.
+    #[serde(default)]
+    status: Option<Status>,
+    completed: Option<Date>,
     title: String,
-    due: Option<NaiveDateTime>,
-    start: Option<NaiveDateTime>,
+    due: Option<Date>,
+    start: Option<Date>,
+    #[serde(default)]
+    scheduled: Option<Date>,
+    #[serde(default)]
+    created: Option<Date>,
+    #[serde(default)]
+    cancelled: Option<Date>,
     repeat: Option<Repeat>,
     tags: Vec<String>,
     sub_tasks: TodoList,
-    dependencies: TodoList,
+    /// Ids of the tasks that must be completed before this one.
+    #[serde(default)]
+    dependencies: Dependencies,
     duration: Option<Duration>,
+    #[serde(default)]
+    priority: Priority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<Source>,
 }
 
 impl Todo {
+    /// A new open task. The title isn't checked, see `Todo::set_title` for
+    /// titles that read back from markdown.
     pub fn from_title(title: String) -> Todo {
-        return Todo {
+        Todo {
+            id: generate_id(),
+            status: Some(Status::Open),
             completed: None,
             title,
             due: None,
             start: None,
+            scheduled: None,
+            created: None,
+            cancelled: None,
             repeat: None,
             tags: Vec::new(),
             sub_tasks: TodoList::new(),
-            dependencies: TodoList::new(),
+            dependencies: Dependencies::default(),
             duration: None,
-        };
+            priority: Priority::Normal,
+            source: None,
+        }
     }
 
-    pub fn set_title(&mut self, t: String) {
-        self.title = t;
+    /// Set the title, with runs of whitespace made one space. Titles can't
+    /// have line breaks, `#tags` or the emoji that start task fields, as
+    /// they wouldn't read back from markdown.
+    pub fn set_title(&mut self, t: String) -> Result<()> {
+        let mut tags = Vec::new();
+        let title = Todo::take_tags(&t, &mut tags);
+        let emoji = t.contains(SIGNIFIERS) || t.contains('✈');
+        if t.contains(['\n', '\r']) || emoji || !tags.is_empty() {
+            return Err(Error::InvalidTitle(t));
+        }
+        self.title = title;
+        Ok(())
     }
 
-    fn _parse_iso8601(s: String) -> Option<NaiveDateTime> {
-        let fmt = "%Y-%m-%d %H:%M:%S";
-        match NaiveDateTime::parse_from_str(s.as_str(), fmt) {
-            Ok(e) => Some(e),
-            Err(_) => match NaiveDateTime::parse_from_str(&format!("{} 11:59:59", s), fmt) {
-                Ok(e) => Some(e),
-                Err(_) => None,
-            },
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// When it's due, a task due on a whole day is due from midnight, see
+    /// `Todo::due_date`. The other dates are the same.
+    pub fn due(&self) -> Option<chrono::NaiveDateTime> {
+        self.due.as_ref().map(|d| d.naive_local())
+    }
+
+    pub fn start(&self) -> Option<chrono::NaiveDateTime> {
+        self.start.as_ref().map(|d| d.naive_local())
+    }
+
+    pub fn completed(&self) -> Option<chrono::NaiveDateTime> {
+        self.completed.as_ref().map(|d| d.naive_local())
+    }
+
+    pub fn scheduled(&self) -> Option<chrono::NaiveDateTime> {
+        self.scheduled.as_ref().map(|d| d.naive_local())
+    }
+
+    pub fn created(&self) -> Option<chrono::NaiveDateTime> {
+        self.created.as_ref().map(|d| d.naive_local())
+    }
+
+    pub fn cancelled(&self) -> Option<chrono::NaiveDateTime> {
+        self.cancelled.as_ref().map(|d| d.naive_local())
+    }
+
+    /// The due date, which may be a whole day rather than a time.
+    pub fn due_date(&self) -> Option<&Date> {
+        self.due.as_ref()
+    }
+
+    pub fn start_date(&self) -> Option<&Date> {
+        self.start.as_ref()
+    }
+
+    pub fn scheduled_date(&self) -> Option<&Date> {
+        self.scheduled.as_ref()
+    }
+
+    pub fn created_date(&self) -> Option<&Date> {
+        self.created.as_ref()
+    }
+
+    pub fn completed_date(&self) -> Option<&Date> {
+        self.completed.as_ref()
+    }
+
+    pub fn cancelled_date(&self) -> Option<&Date> {
+        self.cancelled.as_ref()
+    }
+
+    /// Whether it's still open after it was due. Tasks due on a day are
+    /// overdue once that day is over.
+    pub fn is_overdue_at(&self, now: chrono::NaiveDateTime) -> bool {
+        self.is_overdue_in(None, now)
+    }
+
+    /// Like `is_overdue_at`, with `now` and the due time on the clock in a
+    /// list's zone, see `TodoList::zone`.
+    pub fn is_overdue_in(&self, zone: Option<Tz>, now: chrono::NaiveDateTime) -> bool {
+        match &self.due {
+            Some(d) => self.is_open() && d.is_past_in(zone, now),
+            None => false,
         }
     }
 
-    pub fn set_due_iso8601(&mut self, s: String) {
-        self.due = Todo::_parse_iso8601(s)
+    pub fn is_overdue(&self) -> bool {
+        self.is_overdue_at(Local::now().naive_local())
     }
 
-    pub fn set_start_iso8601(&mut self, s: String) {
-        self.start = Todo::_parse_iso8601(s)
+    pub fn duration(&self) -> Option<chrono::Duration> {
+        self.duration.as_ref().map(|d| d.0)
     }
 
-    pub fn set_completed_iso8601(&mut self, s: String) {
-        self.completed = Todo::_parse_iso8601(s)
+    pub fn priority(&self) -> Priority {
+        self.priority
     }
 
-    pub fn set_repeat(&mut self, rule: String) {
-        // First try to read the cron expression.
-        self.repeat = match Schedule::from_str(&rule) {
-            Ok(d) => Some(Repeat::Every(d)),
-            // If that does not work, parse as plaintext.
-            Err(_) => {
-                let rulel = rule.to_lowercase();
-                // We can say from completed, ot from due.
-                let v = rulel.split("from").collect::<Vec<&str>>();
+    pub fn set_priority(&mut self, p: Priority) {
+        self.priority = p;
+    }
 
-                // Use hman duration to parse the duration.
-                let dur = match DurationHuman::try_from(v[0]) {
-                    Ok(e) => match Duration::from_std(StdDuration::from(&e)) {
-                        Ok(e) => e,
-                        Err(_) => return,
-                    },
-                    Err(_) => return,
-                };
-                // Finally check if it's from completed.
-                if v.len() > 1 && String::from(v[1]).contains("c") {
-                    Some(Repeat::FromCompleted(dur))
-                } else {
-                    Some(Repeat::FromDue(dur))
-                }
+    pub fn has_repeat(&self) -> bool {
+        self.repeat.is_some()
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+
+    /// Where the task was parsed from, tasks created in code have none.
+    pub fn source(&self) -> Option<&Source> {
+        self.source.as_ref()
+    }
+
+    pub fn set_source(&mut self, source: Option<Source>) {
+        self.source = source;
+    }
+
+    /// Give this task and everything nested in it fresh ids, used when a
+    /// copy of a task is created.
+    fn regenerate_ids(&mut self) {
+        self.id = generate_id();
+        for i in 0..self.sub_tasks.len() {
+            self.sub_tasks[i].regenerate_ids();
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        match (self.status, &self.completed, &self.cancelled) {
+            (Some(e), _, _) => e,
+            (None, Some(_), _) => Status::Done,
+            (None, None, Some(_)) => Status::Cancelled,
+            (None, None, None) => Status::Open,
+        }
+    }
+
+    /// Set the status, stamping or clearing the completed and cancelled
+    /// dates to match. This never repeats the task, see `Todo::complete`.
+    pub fn set_status(&mut self, status: Status) {
+        let now = Date::Time(DateTime::now(&Local));
+        match status {
+            Status::Done => {
+                self.completed = self.completed.take().or(Some(now));
+                self.cancelled = None;
+            }
+            Status::Cancelled => {
+                self.cancelled = self.cancelled.take().or(Some(now));
+                self.completed = None;
+            }
+            _ => {
+                self.completed = None;
+                self.cancelled = None;
             }
         }
+        self.status = Some(status);
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.status() == Status::Done
     }
 
-    pub fn set_duration(&mut self, rule: String) {
+    /// Whether the task still needs doing.
+    pub fn is_open(&self) -> bool {
+        self.status().is_open()
+    }
+
+    /// Reset this task and its sub tasks to open, used for the next instance
+    /// of a repeating task.
+    fn _reopen(&mut self) {
+        self.status = Some(Status::Open);
+        self.completed = None;
+        self.cancelled = None;
+        for i in 0..self.sub_tasks.len() {
+            self.sub_tasks[i]._reopen();
+        }
+    }
+
+    fn _parse_iso8601(s: String) -> Result<Date> {
+        match Date::parse(&s) {
+            Ok(e) => Ok(e),
+            Err(_) => Err(Error::InvalidDate(s)),
+        }
+    }
+
+    pub fn set_due_iso8601(&mut self, s: String) -> Result<()> {
+        self.due = Some(Todo::_parse_iso8601(s)?);
+        Ok(())
+    }
+
+    pub fn set_start_iso8601(&mut self, s: String) -> Result<()> {
+        self.start = Some(Todo::_parse_iso8601(s)?);
+        Ok(())
+    }
+
+    pub fn set_due(&mut self, d: Date) {
+        self.due = Some(d);
+    }
+
+    pub fn set_start(&mut self, d: Date) {
+        self.start = Some(d);
+    }
+
+    pub fn set_scheduled(&mut self, d: Date) {
+        self.scheduled = Some(d);
+    }
+
+    pub fn set_completed_iso8601(&mut self, s: String) -> Result<()> {
+        self.completed = Some(Todo::_parse_iso8601(s)?);
+        Ok(())
+    }
+
+    pub fn set_scheduled_iso8601(&mut self, s: String) -> Result<()> {
+        self.scheduled = Some(Todo::_parse_iso8601(s)?);
+        Ok(())
+    }
+
+    pub fn set_created_iso8601(&mut self, s: String) -> Result<()> {
+        self.created = Some(Todo::_parse_iso8601(s)?);
+        Ok(())
+    }
+
+    pub fn set_cancelled_iso8601(&mut self, s: String) -> Result<()> {
+        self.cancelled = Some(Todo::_parse_iso8601(s)?);
+        Ok(())
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.status() == Status::Cancelled
+    }
+
+    /// Drop the task without completing it. Like the Obsidian Tasks plugin
+    /// this doesn't create the next instance of a repeating task.
+    pub fn cancel(&mut self) {
+        if self.is_open() {
+            self.set_status(Status::Cancelled);
+        }
+    }
+
+    /// Set how the task repeats, from text like `every weekday`,
+    /// `every month on the 15th`, `every 2nd tuesday for 3 times`, an
+    /// `RRULE:FREQ=...`, a cron expression, or a duration like `1 week` or
+    /// `3 days from completed`.
+    pub fn set_repeat(&mut self, rule: String) -> Result<()> {
+        let s = rule.trim();
+        let lower = s.to_lowercase();
+        let every = match lower.starts_with("every ") {
+            true => &s[6..],
+            false => s,
+        };
+        if let Ok(e) = Schedule::from_str(every) {
+            self.repeat = Some(Repeat::Every(Box::new(e)));
+            return Ok(());
+        }
+        match Rule::from_str(s) {
+            Ok(e) => {
+                self.repeat = Some(Repeat::Rule(Box::new(e)));
+                return Ok(());
+            }
+            // Say what's wrong with an RRULE rather than that it isn't a
+            // duration.
+            Err(e) if lower.contains("freq=") => return Err(e),
+            Err(_) => (),
+        }
+
+        let (dur, from_completed) = match [" from completed", " after completed", " when done"]
+            .iter()
+            .find(|x| lower.ends_with(*x))
+        {
+            Some(e) => (&lower[..lower.len() - e.len()], true),
+            None => (lower.as_str(), false),
+        };
+        let dur = dur
+            .trim_end_matches(" from due")
+            .trim_start_matches("every ")
+            .trim_start_matches("after ");
+        let dur = match DurationHuman::try_from(dur.trim()) {
+            Ok(e) => match Duration::from_std(StdDuration::from(&e)) {
+                Ok(e) => e,
+                Err(_) => return Err(Error::InvalidRepeat(rule)),
+            },
+            Err(_) => return Err(Error::InvalidRepeat(rule)),
+        };
+        self.repeat = match from_completed {
+            true => Some(Repeat::FromCompleted(dur)),
+            false => Some(Repeat::FromDue(dur)),
+        };
+        Ok(())
+    }
+
+    /// The first time a task with no due date is due if it repeats on
+    /// given days, like the next weekday for `every weekday`.
+    pub(crate) fn first_due(&self, today: chrono::NaiveDate) -> Option<Date> {
+        match &self.repeat {
+            Some(Repeat::Rule(r)) => r.first_from(today).map(Date::Day),
+            _ => None,
+        }
+    }
+
+    pub fn set_duration(&mut self, rule: String) -> Result<()> {
         self.duration = match DurationHuman::try_from(rule.as_str()) {
             Ok(d) => match Duration::from_std(StdDuration::from(&d)) {
                 Ok(d) => Some(d),
-                Err(_) => return,
+                Err(_) => return Err(Error::InvalidDuration(rule)),
             },
-            Err(_) => return,
+            Err(_) => return Err(Error::InvalidDuration(rule)),
         };
+        Ok(())
     }
 
-    pub fn complete(&mut self) -> Option<Todo> {
-        // Because we can't recomplete tasks.
-        if self.completed.is_some() {
-            return None;
+    /// Complete the task, returning the next instance if it repeats.
+    ///
+    /// `is_done` is used to look up whether each dependency is completed,
+    /// see `TodoList::complete` which looks them up in the list.
+    pub fn complete(&mut self, is_done: impl Fn(&str) -> bool) -> Result<Option<Todo>> {
+        self.complete_in(&Local, is_done)
+    }
+
+    /// Complete the task like `complete`, with times without an offset taken
+    /// to be in a zone, which repeats are worked out in.
+    pub fn complete_in<Z: TimeZone>(
+        &mut self,
+        zone: &Z,
+        is_done: impl Fn(&str) -> bool,
+    ) -> Result<Option<Todo>> {
+        // Because we can't recomplete tasks, or complete dropped ones.
+        if !self.is_open() {
+            return Ok(None);
         }
         // Because we can't complete a task with uncompleted dependencies.
-        if self.dependencies.filter(|t| t.completed == None).len() > 0 {
-            return None;
+        if let Some(id) = self.dependencies.ids.iter().find(|id| !is_done(id)) {
+            return Err(Error::Dependency(format!(
+                "'{}' is blocked by uncompleted task {}",
+                self.title, id
+            )));
         }
 
         let mut t = self.clone();
-        let dt = Local::now();
-        let d = NaiveDateTime(dt.naive_local());
-        self.completed = Some(d.clone());
+        let dt = Utc::now().with_timezone(zone);
+        let d = DateTime::from_zoned(&dt);
+        self.completed = Some(Date::Time(d.clone()));
+        self.cancelled = None;
+        self.status = Some(Status::Done);
 
         match (&self.due, &self.repeat) {
-            (_, None) => return None,
+            (_, None) => return Ok(None),
             (Some(due), Some(Repeat::FromDue(d))) => {
-                t.due = Some(due.clone() + d.clone());
+                t.due = Some(due.add_in(zone, d.0));
             }
             (_, Some(Repeat::FromDue(dur))) | (_, Some(Repeat::FromCompleted(dur))) => {
-                t.due = Some(d + dur.clone());
+                let next = d.add_in(zone, dur.0);
+                // A task due on a day is due on a day again.
+                t.due = Some(match &self.due {
+                    Some(Date::Day(_)) => Date::Day(next.in_zone(zone).date_naive()),
+                    _ => Date::Time(next),
+                });
+            }
+            (due, Some(Repeat::Rule(r))) => {
+                // Counted from the day it was due, or the day it's done.
+                let today = dt.date_naive();
+                let start = match due {
+                    Some(Date::Day(d)) if !r.is_when_done() => *d,
+                    Some(Date::Time(e)) if !r.is_when_done() => e.in_zone(zone).date_naive(),
+                    _ => today,
+                };
+                // Keeping the day it started on, which a shorter month moves.
+                let r = r.anchored(start);
+                let rule = match r.next_rule() {
+                    Some(e) => e,
+                    None => return Ok(None),
+                };
+                let next = match r.next_after(start, start) {
+                    Some(e) => e,
+                    None => return Ok(None),
+                };
+                // At the same time of day it was due.
+                t.due = Some(match due {
+                    Some(Date::Time(e @ DateTime { offset: None, .. })) => {
+                        Date::floating(next.and_time(e.local.time()))
+                    }
+                    Some(Date::Time(e)) => {
+                        let local = next.and_time(e.in_zone(zone).time());
+                        Date::Time(DateTime::from_zoned(
+                            &DateTime::floating(local).in_zone(zone),
+                        ))
+                    }
+                    _ => Date::Day(next),
+                });
+                t.repeat = Some(Repeat::Rule(Box::new(rule)));
             }
             (_, Some(Repeat::Every(e))) => {
+                // The next time after it was due, or now if that's later.
                 let after = match &self.due {
-                    Some(d) => match Local.from_local_datetime(&d.0) {
-                        LocalResult::None => dt,
-                        LocalResult::Single(e) => max(e, dt),
-                        LocalResult::Ambiguous(_, _) => dt,
-                    },
+                    Some(d) => max(d.end_in(zone), dt),
                     None => dt,
                 };
-                t.due = match e.after(&after).next() {
-                    Some(e) => Some(NaiveDateTime(e.naive_local())),
-                    None => None,
-                };
+                // Keep whole days whole and floating times floating.
+                t.due = e.after(&after).next().map(|x| match &self.due {
+                    Some(Date::Day(_)) => Date::Day(x.date_naive()),
+                    Some(Date::Time(DateTime { offset: None, .. })) => {
+                        Date::Time(DateTime::floating(x.naive_local()))
+                    }
+                    _ => Date::Time(DateTime::from_zoned(&x)),
+                });
             }
         };
 
-        for i in 0..t.dependencies.len() {
-            t.dependencies[i].completed = None;
-        }
-        for i in 0..t.sub_tasks.len() {
-            t.sub_tasks[i].completed = None;
+        t._reopen();
+        t.regenerate_ids();
+
+        Ok(Some(t))
+    }
+
+    pub fn dependencies(&self) -> &[String] {
+        &self.dependencies.ids
+    }
+
+    /// Make this task depend on the task with the given id.
+    pub fn add_dependency(&mut self, id: String) {
+        if !self.dependencies.ids.contains(&id) {
+            self.dependencies.ids.push(id);
         }
+    }
+
+    pub fn remove_dependency(&mut self, id: &str) {
+        self.dependencies.ids.retain(|e| e != id);
+    }
 
-        return Some(t);
+    /// Take the tasks older versions nested as dependencies, each after its
+    /// own, so they can be moved before this task in its list.
+    pub(crate) fn take_nested_dependencies(&mut self) -> Vec<Todo> {
+        let mut lifted = Vec::new();
+        for mut t in std::mem::take(&mut self.dependencies.nested) {
+            lifted.append(&mut t.take_nested_dependencies());
+            lifted.push(t);
+        }
+        lifted
     }
 
-    pub fn get_dependencies(&mut self) -> &mut TodoList {
-        &mut self.dependencies
+    pub fn sub_tasks(&self) -> &TodoList {
+        &self.sub_tasks
     }
 
     pub fn get_sub_tasks(&mut self) -> &mut TodoList {
         &mut self.sub_tasks
     }
 
-    pub fn add_tag(&mut self, t: String) {
+    /// A copy of the task alone, without its sub tasks or where it was read
+    /// from, for comparing just the task itself.
+    pub(crate) fn details(&self) -> Todo {
+        Todo {
+            sub_tasks: TodoList::new(),
+            source: None,
+            ..self.clone()
+        }
+    }
+
+    /// Replace everything about the task but its sub tasks and where it was
+    /// read from.
+    pub(crate) fn replace_details(&mut self, t: Todo) {
+        let sub_tasks = std::mem::take(&mut self.sub_tasks);
+        let source = self.source.take();
+        *self = t;
+        self.sub_tasks = sub_tasks;
+        self.source = source;
+    }
+
+    /// Add a tag, without the `#`. Like Obsidian tags they can't have
+    /// whitespace or be only digits, and they can't have the emoji that start
+    /// task fields.
+    pub fn add_tag(&mut self, t: String) -> Result<()> {
+        let mut tags = Vec::new();
+        Todo::take_tags(&format!("#{}", t), &mut tags);
+        if tags != [t.as_str()] || t.contains(SIGNIFIERS) || t.contains('✈') {
+            return Err(Error::InvalidTag(t));
+        }
         self.tags.push(t);
+        Ok(())
     }
 
     pub fn remove_tag(&mut self, t: String) {
-        match self.tags.binary_search(&t) {
-            Ok(i) => {
-                self.tags.remove(i);
-                return;
-            }
-            Err(_) => return,
+        if let Some(i) = self.tags.iter().position(|x| *x == t) {
+            self.tags.remove(i);
         }
     }
 
     pub fn to_markdown(&self) -> String {
-        let mut s = format!("{}", self.title);
+        let mut s = format!("- {}", self.markdown_line(true));
+
+        for t in self.sub_tasks.iter() {
+            for line in t.to_markdown().lines() {
+                s = format!("{}\n\t{}", s, line);
+            }
+        }
+
+        s
+    }
+
+    /// The task's own line from the checkbox on, without the list bullet or
+    /// sub tasks. The id can be left out for tasks nothing refers to.
+    pub(crate) fn markdown_line(&self, with_id: bool) -> String {
+        let mut s = self.title.to_string();
+        for t in &self.tags {
+            s = format!("{} #{}", s, t);
+        }
+        if with_id {
+            s = format!("{} 🆔 {}", s, self.id);
+        }
+        if !self.dependencies.ids.is_empty() {
+            s = format!("{} ⛔ {}", s, self.dependencies.ids.join(","));
+        }
+        if let Some(e) = self.priority.emoji() {
+            s = format!("{} {}", s, e);
+        }
         s = match &self.duration {
-            Some(e) => format!("{} 🕒 {}", s, format!("{} minutes", e.num_minutes())),
-            None => s,
-        };
-        s = match &self.start {
-            Some(e) => format!("{} ✈️ {}", s, e.to_string()),
-            None => s,
-        };
-        s = match &self.due {
-            Some(e) => format!("{} 📅 {}", s, e.to_string()),
+            Some(e) => format!("{} 🕒 {}", s, human_duration(e)),
             None => s,
         };
         s = match &self.repeat {
-            Some(e) => format!("{} 🔁 {}", s, e.to_string()),
+            Some(e) => format!("{} 🔁 {}", s, e),
             None => s,
         };
-        s = match &self.completed {
-            Some(e) => format!("- [x] {} ✅ {}", s, e.to_string()),
-            None => format!("- [ ] {}", s),
+        // Obsidian Tasks only reads a day in the stamps, their times are only
+        // kept in json.
+        let stamp = |d: &Option<Date>| match d {
+            Some(Date::Time(t)) => Some(Date::Day(t.local.date())),
+            e => e.clone(),
         };
-
-        for t in &self.tags {
-            s = format!("{} #{}", s, t);
+        for (sym, d) in [
+            ('➕', stamp(&self.created)),
+            ('🛫', self.start.clone()),
+            ('⏳', self.scheduled.clone()),
+            ('📅', self.due.clone()),
+            ('❌', stamp(&self.cancelled)),
+            ('✅', stamp(&self.completed)),
+        ] {
+            if let Some(e) = d {
+                s = format!("{} {} {}", s, sym, e);
+            }
         }
+        format!("[{}] {}", self.status().symbol(), s)
+    }
 
-        let deps = self.dependencies.clone();
-        if deps.len() > 0 {
-            s = format!("{}\n  - Dependencies:", s);
-        }
-        for t in deps {
-            let ds = t.to_string().replace("\n", "\n    ");
-            s = format!("{}\n    {}", s, ds);
-        }
+    /// Read a whole task from one line of free text, like
+    /// `Write report tomorrow 5pm #work every monday 30m !high`, with the
+    /// title, due date, repeat, tags, duration and priority taken out of it.
+    pub fn parse_quick_add(s: &str) -> Result<Todo> {
+        Todo::parse_quick_add_at(s, Local::now().naive_local(), Weekday::Mon)
+    }
 
-        let subs = self.sub_tasks.clone();
-        if subs.len() > 0 {
-            s = format!("{}\n  - Sub Tasks:", s);
-        }
-        for t in subs.into_iter() {
-            let ds = t.to_string().replace("\n", "\n    ");
-            s = format!("{}\n    {}", s, ds);
-        }
+    /// Like `parse_quick_add` with dates relative to `now`, see
+    /// `dates::parse`.
+    pub fn parse_quick_add_at(s: &str, now: NaiveDateTime, week_start: Weekday) -> Result<Todo> {
+        quick_add::parse(s, now, week_start)
+    }
 
-        return s;
+    /// Parse a single task, with its sub tasks.
+    pub fn from_markdown(s: &str) -> Result<Todo> {
+        Todo::from_markdown_with(s, Dialect::Compat)
     }
 
-    pub fn from_markdown(s: &str) -> Todo {
-        let s = String::from(s);
+    pub fn from_markdown_with(s: &str, dialect: Dialect) -> Result<Todo> {
+        let mut tdl = TodoList::from_markdown_with(s, dialect)?;
+        // Dependencies nested by older versions are moved before the task.
+        match tdl.len() {
+            0 => Err(Error::parse(1, 1, "Expected a task")),
+            n => Ok(tdl.remove(n - 1)),
+        }
+    }
 
-        // Start by parsing the sub lists.
-        let mut lists = Vec::from(["\n\t- Dependencies:", "\n\t- Sub Tasks:"]);
-        lists.sort_by_key(|x| s.find(x));
-        let found: Vec<&str> = lists.into_iter().filter(|x| s.find(*x) != None).collect();
+    pub(crate) fn set_parsed_status(&mut self, status: Status) {
+        self.status = Some(status);
+    }
 
-        let mut parts: Vec<&str> = s.split("\n\t- Dependencies:\n").collect();
-        if parts.len() == 1 {
-            parts = s.split("\n\t- Sub Tasks:").collect();
-        } else {
-            let next_parts: Vec<&str> = parts[1].split("\n\t- Sub Tasks:\n").collect();
-            parts[1] = next_parts[0];
-            if next_parts.len() > 1 {
-                parts.push(next_parts[1]);
-            }
+    /// Parse the text of a task line, after the checkbox. Error positions are
+    /// relative to the text.
+    pub(crate) fn from_markdown_line(s: &str, dialect: Dialect) -> Result<Todo> {
+        // The airplane emoji used to mark start dates is 2 characters and we
+        // need singles.
+        let mut s = match dialect {
+            Dialect::Compat => s.replace("✈️", "🛫"),
+            Dialect::Tasks => String::from(s),
+        };
+        // Emoji variation selectors after a symbol aren't part of the value,
+        // others are part of the text.
+        for c in SIGNIFIERS {
+            s = s.replace(&format!("{}\u{fe0f}", c), &String::from(c));
         }
 
-        let mut dependencies = TodoList::new();
-        let mut sub_tasks = TodoList::new();
+        // Split into the title and a value for each symbol, with the column
+        // of each symbol.
+        let mut parts: Vec<(Option<char>, usize, String)> = vec![(None, 1, String::new())];
+        for (i, c) in s.chars().enumerate() {
+            match (SIGNIFIERS.contains(&c), parts.last_mut()) {
+                (true, _) | (false, None) => parts.push((Some(c), i + 1, String::new())),
+                (false, Some(e)) => e.2.push(c),
+            }
+        }
 
-        for i in 1..parts.len() {
-            match found[i - 1] {
-                "\n\t- Dependencies:" => {
-                    dependencies = TodoList::from_markdown(&parts[i].replace("\n\t\t", "\n")[2..])
+        let mut tags = Vec::new();
+        let mut title = Todo::take_tags(&parts[0].2, &mut tags);
+        let mut task = Todo::from_title(String::new());
+
+        for (sym, column, value) in parts.iter().skip(1) {
+            let value = Todo::take_tags(value, &mut tags);
+            let r = match sym {
+                Some('🆔') => match value.split_whitespace().next() {
+                    Some(id) => {
+                        task.set_id(String::from(id));
+                        Ok(())
+                    }
+                    None => Err(Error::parse(1, *column, "Missing id")),
+                },
+                Some('⛔') => {
+                    let ids = value.split(',');
+                    for id in ids.map(|x| x.trim()).filter(|x| !x.is_empty()) {
+                        task.add_dependency(String::from(id));
+                    }
+                    Ok(())
                 }
-                "\n\t- Sub Tasks:" => {
-                    sub_tasks = TodoList::from_markdown(&parts[i].replace("\n\t\t", "\n")[2..])
+                Some(c @ ('🔺' | '⏫' | '🔼' | '🔽' | '⏬')) => {
+                    task.priority = Priority::from_emoji(*c).unwrap_or_default();
+                    // Priorities have no value, keep any text after them.
+                    if !value.is_empty() {
+                        title = format!("{} {}", title, value);
+                    }
+                    Ok(())
                 }
-                _ => panic!("Cannot parse sub list {}", found[i - 1]),
-            }
+                Some('🕒') => task.set_duration(value.replace("minutes", "min")),
+                Some('🔁') => task.set_repeat(value),
+                Some('➕') => task.set_created_iso8601(value),
+                Some('🛫') => task.set_start_iso8601(value),
+                Some('⏳') => task.set_scheduled_iso8601(value),
+                Some('📅') => task.set_due_iso8601(value),
+                Some('❌') => task.set_cancelled_iso8601(value),
+                Some('✅') => task.set_completed_iso8601(value),
+                _ => Err(Error::parse(1, *column, "Unexpected symbol")),
+            };
+            r.map_err(|e| e.at(1, *column))?;
         }
 
-        // Since the airplane emoji is 2 characters and we need singles.
-        let s = parts[0].replace("✈️", "✝");
-
-        let mut symbols = Vec::from(['🕒', '✝', '📅', '🔁', '✅']);
-
-        symbols.sort_by_cached_key(|x| s.find(*x));
-
-        let task_parts: Vec<String> = s
-            .split(|c| match c {
-                e if symbols.contains(&e) => true,
-                _ => false,
-            })
-            .map(|x| String::from(x.trim()))
-            .collect();
-
-        let mut title = task_parts[0].replace("- [ ]", "");
-        title = title.replace("- [x]", "");
-        title = String::from(title.trim());
-
-        let mut task = Todo::from_title(title);
-
-        let syms = symbols.clone();
-        let found = syms.into_iter().filter(|x| s.find(*x) != None);
-        for (i, sym) in found.into_iter().enumerate() {
-            match sym {
-                '🕒' => task.set_duration(task_parts[i + 1].replace("minutes", "min")),
-                '✝' => task.set_start_iso8601(task_parts[i + 1].clone()),
-                '📅' => task.set_due_iso8601(task_parts[i + 1].clone()),
-                '🔁' => task.set_repeat(
-                    task_parts[i + 1]
-                        .replace("after ", "")
-                        .replace("every ", ""),
-                ),
-                '✅' => task.set_completed_iso8601(task_parts[i + 1].clone()),
-                _ => panic!("Cannot parse symbol {}", sym),
+        task.title = title;
+        task.tags = tags;
+        Ok(task)
+    }
+
+    /// Remove `#tags` from text, adding them to `tags`.
+    pub(crate) fn take_tags(s: &str, tags: &mut Vec<String>) -> String {
+        let mut words = Vec::new();
+        for w in s.split_whitespace() {
+            match w.strip_prefix('#') {
+                // Headings like `#` and issue numbers like `#12` aren't tags.
+                Some(t) if !t.is_empty() && !t.chars().all(|c| c.is_ascii_digit() || c == '#') => {
+                    tags.push(String::from(t))
+                }
+                _ => words.push(w),
             }
         }
-
-        task.dependencies = dependencies;
-        task.sub_tasks = sub_tasks;
-
-        return task;
+        words.join(" ")
     }
 }
 
-impl ToString for Todo {
-    fn to_string(&self) -> String {
-        return self.to_markdown();
+impl fmt::Display for Todo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_markdown())
     }
 }
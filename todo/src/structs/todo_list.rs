@@ -1,161 +1,527 @@
-use crate::Todo;
-use serde::{Deserialize, Serialize};
+use crate::storage;
+use crate::structs::markdown;
+use crate::structs::{Date, Dialect, Zone};
+use crate::{dates, DependencyGraph, Error, Query, Result, Status, Todo};
+use chrono::NaiveDateTime;
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 use std::ops::Index;
 use std::ops::IndexMut;
 use std::path::Path;
+use std::str::FromStr;
+
+/// Field used to sort a list, tasks without the field sort last.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortKey {
+    Due,
+    Start,
+    Scheduled,
+    Duration,
+    Title,
+    Completed,
+    /// Highest priority first.
+    Priority,
+}
+
+impl FromStr for SortKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<SortKey> {
+        match s.to_lowercase().as_str() {
+            "due" => Ok(SortKey::Due),
+            "start" => Ok(SortKey::Start),
+            "scheduled" => Ok(SortKey::Scheduled),
+            "dur" | "duration" => Ok(SortKey::Duration),
+            "title" => Ok(SortKey::Title),
+            "done" | "completed" => Ok(SortKey::Completed),
+            "priority" => Ok(SortKey::Priority),
+            _ => Err(Error::parse(1, 1, format!("Unknown sort key {}", s))),
+        }
+    }
+}
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct TodoList {
+    #[serde(deserialize_with = "_lift_dependencies")]
     items: Vec<Todo>,
+    /// Zone that times without an offset are in, only used for the top
+    /// level list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    zone: Option<Zone>,
 }
 
 impl TodoList {
     pub fn new() -> TodoList {
         let v = Vec::new();
-        return TodoList { items: v };
+        TodoList {
+            items: v,
+            zone: None,
+        }
+    }
+
+    /// The zone times without an offset are in, or None for the local one.
+    pub fn zone(&self) -> Option<Tz> {
+        self.zone.map(|x| x.0)
+    }
+
+    pub fn set_zone(&mut self, zone: Option<Tz>) {
+        self.zone = zone.map(Zone);
+    }
+
+    /// The time now on the clock in the list's zone.
+    pub fn now(&self) -> NaiveDateTime {
+        dates::now(self.zone())
     }
 
     pub fn add(&mut self, e: Todo) {
         self.items.push(e);
     }
 
+    pub fn insert(&mut self, i: usize, e: Todo) {
+        self.items.insert(i, e);
+    }
+
     pub fn remove(&mut self, i: usize) -> Todo {
-        return self.items.remove(i);
+        self.items.remove(i)
     }
 
-    pub fn filter(&self, predicate: for<'a> fn(&'a Todo) -> bool) -> TodoList {
-        let tdl = self.clone();
-        return tdl.into_iter().filter(predicate).collect();
+    /// Insert a task just before another, in the same list as it.
+    pub fn insert_before(&mut self, id: &str, e: Todo) -> Result<()> {
+        if let Some(i) = self.position(id) {
+            self.items.insert(i, e);
+            return Ok(());
+        }
+        match self
+            .items
+            .iter_mut()
+            .find(|t| t.sub_tasks().get_by_id(id).is_some())
+        {
+            Some(t) => t.get_sub_tasks().insert_before(id, e),
+            None => Err(Error::NotFound(String::from(id))),
+        }
     }
 
-    pub fn len(&self) -> usize {
-        self.items.len()
+    /// Remove a task by id, searching nested sub tasks.
+    pub fn remove_by_id(&mut self, id: &str) -> Option<Todo> {
+        if let Some(i) = self.position(id) {
+            return Some(self.items.remove(i));
+        }
+        self.items
+            .iter_mut()
+            .find_map(|t| t.get_sub_tasks().remove_by_id(id))
     }
 
-    pub fn to_json(&self) -> String {
-        match serde_json::to_string_pretty(self) {
-            Ok(e) => e,
-            Err(_) => panic!("Couldn't convert to json."),
+    /// The id of the task every task is a sub task of, None for top level
+    /// tasks.
+    pub(crate) fn parents(&self) -> HashMap<String, Option<String>> {
+        fn walk(
+            tdl: &TodoList,
+            parent: Option<&str>,
+            parents: &mut HashMap<String, Option<String>>,
+        ) {
+            for t in tdl {
+                parents.insert(String::from(t.id()), parent.map(String::from));
+                walk(t.sub_tasks(), Some(t.id()), parents);
+            }
         }
+        let mut parents = HashMap::new();
+        walk(self, None, &mut parents);
+        parents
+    }
+
+    /// Top level tasks matching the predicate.
+    pub fn filter(&self, predicate: impl Fn(&Todo) -> bool) -> Vec<&Todo> {
+        self.items.iter().filter(|t| predicate(t)).collect()
+    }
+
+    /// Sort the list, and every sub task list, by the given field. Times are
+    /// compared on the clock in the list's zone.
+    pub fn sort_by(&mut self, key: SortKey) {
+        let zone = self.zone();
+        self._sort_by(key, zone);
     }
 
-    pub fn from_json(s: &str) -> Self {
-        match serde_json::from_str(s) {
-            Ok(e) => e,
-            Err(e) => panic!("Couldn't convert from json. {}", e),
+    fn _sort_by(&mut self, key: SortKey, zone: Option<Tz>) {
+        let time = |d: Option<&Date>| d.map(|x| x.naive_in(zone));
+        match key {
+            SortKey::Due => self
+                .items
+                .sort_by_key(|t| (t.due().is_none(), time(t.due_date()))),
+            SortKey::Start => self
+                .items
+                .sort_by_key(|t| (t.start().is_none(), time(t.start_date()))),
+            SortKey::Scheduled => self
+                .items
+                .sort_by_key(|t| (t.scheduled().is_none(), time(t.scheduled_date()))),
+            SortKey::Duration => self
+                .items
+                .sort_by_key(|t| (t.duration().is_none(), t.duration())),
+            SortKey::Title => self.items.sort_by_key(|t| t.title().to_lowercase()),
+            SortKey::Completed => self
+                .items
+                .sort_by_key(|t| (!t.is_open(), time(t.completed_date()))),
+            SortKey::Priority => self.items.sort_by_key(|t| std::cmp::Reverse(t.priority())),
+        }
+        for t in self.items.iter_mut() {
+            t.get_sub_tasks()._sort_by(key, zone);
         }
     }
 
-    pub fn to_markdown(&self) -> String {
-        let mut s = String::new();
-        let tdl = self.clone();
-        for (i, todo) in tdl.into_iter().enumerate() {
-            if i > 0 {
-                s.push_str("\n");
+    /// Find a task by id, searching nested sub tasks.
+    pub fn get_by_id(&self, id: &str) -> Option<&Todo> {
+        for t in &self.items {
+            if t.id() == id {
+                return Some(t);
+            }
+            if let Some(e) = t.sub_tasks().get_by_id(id) {
+                return Some(e);
             }
-            s.push_str(todo.to_markdown().as_str());
         }
+        None
+    }
 
-        return s;
+    pub fn get_by_id_mut(&mut self, id: &str) -> Option<&mut Todo> {
+        for t in self.items.iter_mut() {
+            if t.id() == id {
+                return Some(t);
+            }
+            if let Some(e) = t.get_sub_tasks().get_by_id_mut(id) {
+                return Some(e);
+            }
+        }
+        None
     }
 
-    pub fn from_markdown(s: &str) -> Self {
-        let mut tasks: Vec<&str> = s.split("\n- [").collect();
-        tasks[0] = &tasks[0][3..];
+    /// Complete the task with the given id, looking up its dependencies in
+    /// this list. A task is blocked while any of its transitive dependencies
+    /// is uncompleted, dependencies that are not in the list don't block it.
+    /// Returns the next instance of the task if it repeats.
+    pub fn complete(&mut self, id: &str) -> Result<Option<Todo>> {
+        let graph = self.dependency_graph();
+        let zone = self.zone;
+        match self.get_by_id_mut(id) {
+            Some(t) => match zone {
+                Some(z) => t.complete_in(&z.0, |d| graph.is_done(d)),
+                None => t.complete(|d| graph.is_done(d)),
+            },
+            None => Err(Error::NotFound(String::from(id))),
+        }
+    }
 
-        let mut tdl = TodoList::new();
+    /// Set the status of the task with the given id, see `Todo::set_status`.
+    /// Like `complete` it can't be done while it's blocked, but it doesn't
+    /// repeat.
+    pub fn set_status(&mut self, id: &str, status: Status) -> Result<()> {
+        let blocker = match status {
+            Status::Done => self.dependency_graph().blocked_by(id).into_iter().next(),
+            _ => None,
+        };
+        let t = match self.get_by_id_mut(id) {
+            Some(e) => e,
+            None => return Err(Error::NotFound(String::from(id))),
+        };
+        if let Some(e) = blocker {
+            return Err(Error::Dependency(format!(
+                "'{}' is blocked by uncompleted task {}",
+                t.title(),
+                e
+            )));
+        }
+        t.set_status(status);
+        Ok(())
+    }
+
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        DependencyGraph::new(self)
+    }
+
+    /// Make `id` depend on `dependency`, rejecting dependencies that would
+    /// create a cycle.
+    pub fn add_dependency(&mut self, id: &str, dependency: &str) -> Result<()> {
+        if self.get_by_id(dependency).is_none() {
+            return Err(Error::NotFound(String::from(dependency)));
+        }
+        let graph = self.dependency_graph();
+        if id == dependency || graph.blocks(id).iter().any(|x| x == dependency) {
+            return Err(Error::Dependency(format!(
+                "{} already depends on {}",
+                dependency, id
+            )));
+        }
+        match self.get_by_id_mut(id) {
+            Some(t) => {
+                t.add_dependency(String::from(dependency));
+                Ok(())
+            }
+            None => Err(Error::NotFound(String::from(id))),
+        }
+    }
 
-        for task in tasks {
-            let mut s = task.replace("x] ", "");
-            s = s.replace(" ] ", "");
-            tdl.add(Todo::from_markdown(s.as_str()));
+    /// Every task by id, including sub tasks.
+    fn _index(&self) -> HashMap<&str, &Todo> {
+        let mut index = HashMap::new();
+        for (_, t) in self.walk() {
+            index.entry(t.id()).or_insert(t);
         }
+        index
+    }
+
+    fn _lookup(&self, ids: Vec<String>) -> Vec<&Todo> {
+        let index = self._index();
+        ids.iter()
+            .filter_map(|x| index.get(x.as_str()).copied())
+            .collect()
+    }
+
+    /// Uncompleted tasks that transitively block the task with the given id.
+    pub fn blocked_by(&self, id: &str) -> Vec<&Todo> {
+        self._lookup(self.dependency_graph().blocked_by(id))
+    }
+
+    /// Tasks that are transitively blocked by the task with the given id.
+    pub fn blocks(&self, id: &str) -> Vec<&Todo> {
+        self._lookup(self.dependency_graph().blocks(id))
+    }
+
+    /// Uncompleted tasks that have every dependency done.
+    pub fn ready(&self) -> Vec<&Todo> {
+        self._lookup(self.dependency_graph().ready())
+    }
+
+    /// Uncompleted tasks ordered so each comes after its dependencies,
+    /// errors if there is a dependency cycle.
+    pub fn topological_order(&self) -> Result<Vec<&Todo>> {
+        Ok(self._lookup(self.dependency_graph().topological_order()?))
+    }
+
+    pub fn find_cycle(&self) -> Option<Vec<&Todo>> {
+        self.dependency_graph()
+            .find_cycle()
+            .map(|c| self._lookup(c))
+    }
+
+    /// Put the given top level tasks in this order, in the places they take
+    /// up between them. Other tasks stay where they are.
+    pub(crate) fn reorder(&mut self, ids: &[String]) {
+        let mut slots: Vec<usize> = ids.iter().filter_map(|x| self.position(x)).collect();
+        slots.sort_unstable();
+        let mut tasks: HashMap<String, Todo> = slots
+            .iter()
+            .map(|i| (String::from(self.items[*i].id()), self.items[*i].clone()))
+            .collect();
+        let order = ids.iter().filter_map(|x| tasks.remove(x));
+        for (i, t) in slots.into_iter().zip(order) {
+            self.items[i] = t;
+        }
+    }
 
-        return tdl;
+    /// Position of a top level task.
+    pub fn position(&self, id: &str) -> Option<usize> {
+        self.items.iter().position(|t| t.id() == id)
     }
 
-    pub fn from_mixed_markdown(s: &str) -> Self {
-        let mut md = String::from("");
-        let lines = s.split("\n");
+    pub fn iter(&self) -> std::slice::Iter<'_, Todo> {
+        self.items.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Todo> {
+        self.items.iter_mut()
+    }
 
-        for line in lines {
-            if line.trim().starts_with("- [ ]")
-                || line.trim().starts_with("- [x]")
-                || line.trim().starts_with("- Dependencies:")
-                || line.trim().starts_with("- Sub Tasks:")
-            {
-                md += line;
-                md += "\n";
+    /// Depth first walk over every task and its sub tasks, yielding the
+    /// path of indices to each task along with it.
+    pub fn walk(&self) -> Walk<'_> {
+        Walk {
+            stack: vec![self.items.iter().enumerate()],
+            path: Vec::new(),
+        }
+    }
+
+    /// Every task, including sub tasks, under a heading of the note it was
+    /// read from.
+    pub fn section(&self, heading: &str) -> Vec<&Todo> {
+        Query::Section(String::from(heading)).run(self, true)
+    }
+
+    /// Record the file every parsed task in the list was read from.
+    pub fn set_source_file(&mut self, file: &str) {
+        for t in self.items.iter_mut() {
+            if let Some(mut e) = t.source().cloned() {
+                e.file = Some(String::from(file));
+                t.set_source(Some(e));
             }
+            t.get_sub_tasks().set_source_file(file);
         }
-        if md.len() == 0 {
-            return Self::new();
+    }
+
+    /// Tasks matching a query, optionally searching sub tasks too.
+    pub fn query(&self, q: &Query, recursive: bool) -> Vec<&Todo> {
+        q.run(self, recursive)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(s: &str) -> Result<Self> {
+        Ok(serde_json::from_str(s)?)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut s = String::new();
+        for (i, todo) in self.items.iter().enumerate() {
+            if i > 0 {
+                s.push('\n');
+            }
+            s.push_str(todo.to_markdown().as_str());
         }
-        md = String::from(&md[0..md.len() - 1]);
 
-        return Self::from_markdown(md.as_str());
+        s
+    }
+
+    pub fn from_markdown(s: &str) -> Result<Self> {
+        TodoList::from_markdown_with(s, Dialect::Compat)
+    }
+
+    pub fn from_markdown_with(s: &str, dialect: Dialect) -> Result<Self> {
+        markdown::parse(s, dialect, false, None)
+    }
+
+    /// Parse the tasks in a note, skipping everything else.
+    pub fn from_mixed_markdown(s: &str) -> Result<Self> {
+        markdown::parse(s, Dialect::Compat, true, None)
     }
 
-    fn _from_file(file_name: &str, f: fn(&str) -> Self) -> Self {
+    /// Parse the tasks in a note of a vault, like `from_mixed_markdown` with
+    /// `path` as their source file. Tasks without an id get one from the
+    /// path too, so copies of a task in two notes don't share an id.
+    pub fn from_note(s: &str, path: &str) -> Result<Self> {
+        let mut tdl = markdown::parse(s, Dialect::Compat, true, Some(path))?;
+        tdl.set_source_file(path);
+        Ok(tdl)
+    }
+
+    fn _from_file(file_name: &str, f: fn(&str) -> Result<Self>) -> Result<Self> {
         let path = Path::new(file_name);
-        let display = path.display();
 
         // Open the path in read-only mode, returns `io::Result<File>`
-        let mut file = match File::open(&path) {
-            Err(why) => panic!("couldn't open {}: {}", display, why),
-            Ok(file) => file,
-        };
+        let mut file = File::open(path).map_err(|e| Error::io(path, e))?;
 
         // Read the file contents into a string, returns `io::Result<usize>`
         let mut s = String::new();
-        match file.read_to_string(&mut s) {
-            Err(why) => panic!("couldn't read {}: {}", display, why),
-            Ok(_) => return f(s.as_str()),
-        }
+        file.read_to_string(&mut s)
+            .map_err(|e| Error::io(path, e))?;
+        let mut tdl = f(s.as_str())?;
+        tdl.set_source_file(file_name);
+        Ok(tdl)
     }
 
-    pub fn from_json_file(s: &str) -> Self {
+    pub fn from_json_file(s: &str) -> Result<Self> {
         TodoList::_from_file(s, TodoList::from_json)
     }
 
-    pub fn from_markdown_file(s: &str) -> Self {
+    pub fn from_markdown_file(s: &str) -> Result<Self> {
         TodoList::_from_file(s, TodoList::from_markdown)
     }
 
-    pub fn from_mixed_markdown_file(s: &str) -> Self {
+    pub fn from_mixed_markdown_file(s: &str) -> Result<Self> {
         TodoList::_from_file(s, TodoList::from_mixed_markdown)
     }
 
-    fn _to_file<'a>(file_name: &str, s: String) -> Result<String, String> {
-        let path = Path::new(file_name);
-        let display = path.display();
+    pub fn to_json_file(&self, s: &str) -> Result<()> {
+        self.to_json_file_with(s, 0)
+    }
 
-        // Open the path in read-only mode, returns `io::Result<File>`
-        let mut file = match File::create(&path) {
-            Err(why) => return Err(format!("couldn't open {}: {}", display, why)),
-            Ok(file) => file,
-        };
-        // Write the `LOREM_IPSUM` string to `file`, returns `io::Result<()>`
-        match file.write_all(s.as_bytes()) {
-            Err(why) => return Err(format!("couldn't write to {}: {}", display, why)),
-            Ok(_) => return Ok(String::from("okay")),
-        }
+    /// Save as JSON, keeping the previous `backups` versions of the file.
+    pub fn to_json_file_with(&self, s: &str, backups: usize) -> Result<()> {
+        storage::write_atomic(s, &self.to_json()?, backups)
+    }
+
+    pub fn to_markdown_file(&self, s: &str) -> Result<()> {
+        self.to_markdown_file_with(s, 0)
+    }
+
+    /// Save as markdown, keeping the previous `backups` versions of the file.
+    pub fn to_markdown_file_with(&self, s: &str, backups: usize) -> Result<()> {
+        storage::write_atomic(s, &self.to_markdown(), backups)
+    }
+}
+
+/// Read the tasks of a list, moving tasks that older versions nested as
+/// dependencies before the task that depends on them.
+fn _lift_dependencies<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Vec<Todo>, D::Error> {
+    let mut items = Vec::new();
+    for mut t in Vec::<Todo>::deserialize(d)? {
+        items.append(&mut t.take_nested_dependencies());
+        items.push(t);
     }
+    Ok(items)
+}
+
+impl Default for TodoList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for TodoList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_markdown())
+    }
+}
+
+pub struct Walk<'a> {
+    stack: Vec<std::iter::Enumerate<std::slice::Iter<'a, Todo>>>,
+    path: Vec<usize>,
+}
 
-    pub fn to_json_file(&self, s: &str) -> Result<String, String> {
-        return TodoList::_to_file(s, self.to_json());
+impl<'a> Iterator for Walk<'a> {
+    type Item = (Vec<usize>, &'a Todo);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some((i, t)) => {
+                    self.path.push(i);
+                    self.stack.push(t.sub_tasks().iter().enumerate());
+                    return Some((self.path.clone(), t));
+                }
+                None => {
+                    self.stack.pop();
+                    self.path.pop();
+                }
+            }
+        }
     }
+}
 
-    pub fn to_markdown_file(&self, s: &str) -> Result<String, String> {
-        TodoList::_to_file(s, self.to_markdown())
+impl<'a> IntoIterator for &'a TodoList {
+    type Item = &'a Todo;
+    type IntoIter = std::slice::Iter<'a, Todo>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
     }
 }
 
-impl ToString for TodoList {
-    fn to_string(&self) -> String {
-        self.to_markdown()
+impl<'a> IntoIterator for &'a mut TodoList {
+    type Item = &'a mut Todo;
+    type IntoIter = std::slice::IterMut<'a, Todo>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter_mut()
     }
 }
 
@@ -175,7 +541,7 @@ impl FromIterator<Todo> for TodoList {
         for e in l.into_iter() {
             tdl.add(e);
         }
-        return tdl;
+        tdl
     }
 }
 
@@ -183,12 +549,12 @@ impl Index<usize> for TodoList {
     type Output = Todo;
 
     fn index(&self, index: usize) -> &Self::Output {
-        return &self.items[index];
+        &self.items[index]
     }
 }
 
 impl IndexMut<usize> for TodoList {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        return &mut self.items[index];
+        &mut self.items[index]
     }
 }
@@ -1,12 +1,60 @@
 use chrono::{
-    offset::TimeZone, DateTime, Duration as ChronoDuration, NaiveDateTime as ChronoNaiveDateTime,
-    OutOfRangeError,
+    offset::{LocalResult, TimeZone},
+    DateTime as ChronoDateTime, Duration as ChronoDuration, FixedOffset, Local, NaiveDate,
+    NaiveDateTime as ChronoNaiveDateTime, NaiveTime, Offset, OutOfRangeError, Utc,
 };
+use chrono_tz::Tz;
 use core::str::FromStr;
 use core::time::Duration as StdDuration;
 use cron::Schedule as CronSchedule;
 use serde::{de::Visitor, Deserialize, Deserializer, Serialize, Serializer};
-use std::ops::Add;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+const ID_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+const ID_LENGTH: usize = 6;
+
+fn encode_id(mut n: u64) -> String {
+    (0..ID_LENGTH)
+        .map(|_| {
+            let c = ID_ALPHABET[(n % ID_ALPHABET.len() as u64) as usize];
+            n /= ID_ALPHABET.len() as u64;
+            c as char
+        })
+        .collect()
+}
+
+/// Generate a short random id, in the style of the Obsidian Tasks plugin.
+pub fn generate_id() -> String {
+    let mut h = RandomState::new().build_hasher();
+    h.write_u64(ID_COUNTER.fetch_add(1, Ordering::Relaxed));
+    h.write_i64(Utc::now().timestamp_nanos_opt().unwrap_or_default());
+    encode_id(h.finish())
+}
+
+/// A short id derived from some text, the same every time it's derived.
+pub fn derive_id(s: &str) -> String {
+    encode_id(fnv(s))
+}
+
+/// FNV-1a, unlike `DefaultHasher` it's the same on every platform and Rust
+/// version, so it can be persisted.
+fn fnv(s: &str) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in s.bytes() {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h
+}
+
+/// Hash of some text as hex.
+pub fn content_hash(s: &str) -> String {
+    format!("{:016x}", fnv(s))
+}
 
 #[derive(Clone)]
 pub struct Duration(pub ChronoDuration);
@@ -20,10 +68,6 @@ impl Duration {
         Duration(ChronoDuration::milliseconds(v))
     }
 
-    pub fn num_minutes(&self) -> i64 {
-        self.0.num_milliseconds()
-    }
-
     pub fn from_std(s: StdDuration) -> Result<Duration, OutOfRangeError> {
         match ChronoDuration::from_std(s) {
             Ok(e) => Ok(Duration(e)),
@@ -36,14 +80,6 @@ impl Duration {
     }
 }
 
-impl Add<Duration> for NaiveDateTime {
-    type Output = NaiveDateTime;
-
-    fn add(self, rhs: Duration) -> NaiveDateTime {
-        NaiveDateTime(self.0.add(rhs.0))
-    }
-}
-
 impl Serialize for Duration {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -84,14 +120,14 @@ impl<'de> Deserialize<'de> for Duration {
 pub struct Schedule(pub CronSchedule);
 
 impl Schedule {
-    pub fn after<Z: TimeZone>(&self, d: &DateTime<Z>) -> cron::ScheduleIterator<'_, Z> {
+    pub fn after<Z: TimeZone>(&self, d: &ChronoDateTime<Z>) -> cron::ScheduleIterator<'_, Z> {
         self.0.after(d)
     }
 }
 
-impl ToString for Schedule {
-    fn to_string(&self) -> String {
-        self.0.to_string()
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
 }
 
@@ -144,35 +180,112 @@ impl<'de> Deserialize<'de> for Schedule {
     }
 }
 
-#[derive(Clone)]
-pub struct NaiveDateTime(pub ChronoNaiveDateTime);
+/// A date and time as read on a clock, with its offset from UTC when it's
+/// known. Times without one are floating, they're in whatever zone the list
+/// they're in uses, see `TodoList::zone`.
+#[derive(Clone, PartialEq)]
+pub struct DateTime {
+    pub local: ChronoNaiveDateTime,
+    pub offset: Option<FixedOffset>,
+}
 
-impl NaiveDateTime {
-    pub fn parse_from_str(s: &str, fmt: &str) -> chrono::ParseResult<NaiveDateTime> {
-        match ChronoNaiveDateTime::parse_from_str(s, fmt) {
-            Ok(e) => Ok(NaiveDateTime(e)),
-            Err(e) => Err(e),
+impl DateTime {
+    pub fn floating(local: ChronoNaiveDateTime) -> DateTime {
+        DateTime {
+            local,
+            offset: None,
         }
     }
-}
 
-impl ToString for NaiveDateTime {
-    fn to_string(&self) -> String {
-        self.0.format("%Y-%m-%d %H:%M:%S").to_string()
+    pub fn from_zoned<Z: TimeZone>(d: &ChronoDateTime<Z>) -> DateTime {
+        DateTime {
+            local: d.naive_local(),
+            offset: Some(d.offset().fix()),
+        }
+    }
+
+    pub fn now<Z: TimeZone>(zone: &Z) -> DateTime {
+        DateTime::from_zoned(&Utc::now().with_timezone(zone))
+    }
+
+    /// The time in a zone. A floating time is taken as a time on the clock
+    /// there, if the clocks skipped over it it's moved forward by the gap,
+    /// and if they passed it twice it's the first.
+    pub fn in_zone<Z: TimeZone>(&self, zone: &Z) -> ChronoDateTime<Z> {
+        if let Some(e) = self.offset {
+            if let Some(d) = e.from_local_datetime(&self.local).single() {
+                return d.with_timezone(zone);
+            }
+        }
+        match zone.from_local_datetime(&self.local) {
+            LocalResult::Single(e) | LocalResult::Ambiguous(e, _) => e,
+            LocalResult::None => {
+                let offset = |d| zone.offset_from_utc_datetime(&d).fix().local_minus_utc();
+                let gap = offset(self.local + ChronoDuration::days(1))
+                    - offset(self.local - ChronoDuration::days(1));
+                let local = self.local + ChronoDuration::seconds(gap.abs() as i64);
+                match zone.from_local_datetime(&local).earliest() {
+                    Some(e) => e,
+                    None => zone.from_utc_datetime(&self.local),
+                }
+            }
+        }
+    }
+
+    /// The time on the clock here, floating times are taken as they are.
+    pub fn naive_local(&self) -> ChronoNaiveDateTime {
+        self.naive_in(None)
+    }
+
+    /// The time on the clock in a list's zone, see `TodoList::zone`, or
+    /// here for None. Floating times are taken as they are.
+    pub fn naive_in(&self, zone: Option<Tz>) -> ChronoNaiveDateTime {
+        match (self.offset, zone) {
+            (None, _) => self.local,
+            (Some(_), Some(z)) => self.in_zone(&z).naive_local(),
+            (Some(_), None) => self.in_zone(&Local).naive_local(),
+        }
     }
-}
 
-impl PartialEq<NaiveDateTime> for NaiveDateTime {
-    fn eq(&self, other: &NaiveDateTime) -> bool {
-        self.0.eq(&other.0)
+    /// Move the time on the clock in a zone, so a task due at 9:00 is still
+    /// due at 9:00 a day later when the clocks change in between. Floating
+    /// times stay floating.
+    pub fn add_in<Z: TimeZone>(&self, zone: &Z, d: ChronoDuration) -> DateTime {
+        match self.offset {
+            Some(_) => {
+                let local = self.in_zone(zone).naive_local() + d;
+                DateTime::from_zoned(&DateTime::floating(local).in_zone(zone))
+            }
+            None => DateTime::floating(self.local + d),
+        }
     }
 
-    fn ne(&self, other: &NaiveDateTime) -> bool {
-        self.0.ne(&other.0)
+    /// Parse `yyyy-mm-dd hh:mm:ss` with an optional offset like `+02:00`, or
+    /// RFC 3339.
+    pub fn parse(s: &str) -> chrono::ParseResult<DateTime> {
+        let s = s.trim();
+        match ChronoDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S %:z") {
+            Ok(e) => Ok(DateTime::from_zoned(&e)),
+            Err(_) => match ChronoDateTime::parse_from_rfc3339(s) {
+                Ok(e) => Ok(DateTime::from_zoned(&e)),
+                Err(_) => ChronoNaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+                    .map(DateTime::floating),
+            },
+        }
     }
 }
 
-impl Serialize for NaiveDateTime {
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.local.format("%Y-%m-%d %H:%M:%S"))?;
+        match self.offset {
+            Some(e) => write!(f, " {}", e),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Serialize for DateTime {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
@@ -181,19 +294,20 @@ impl Serialize for NaiveDateTime {
     }
 }
 
-struct NaiveDateTimeVisitor;
+struct DateTimeVisitor;
 
-impl<'de> Visitor<'de> for NaiveDateTimeVisitor {
-    type Value = NaiveDateTime;
+impl<'de> Visitor<'de> for DateTimeVisitor {
+    type Value = DateTime;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let fmt = "%Y-%m-%d %H:%M:%S";
-        write!(formatter, "Datetime {}", fmt)
+        write!(
+            formatter,
+            "Datetime %Y-%m-%d %H:%M:%S with an optional offset"
+        )
     }
 
     fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Self::Value, E> {
-        let fmt = "%Y-%m-%d %H:%M:%S";
-        match NaiveDateTime::parse_from_str(s, fmt) {
+        match DateTime::parse(s) {
             Ok(e) => Ok(e),
             Err(_) => Err(serde::de::Error::custom(format!(
                 "Could not parse datetime {}",
@@ -203,11 +317,156 @@ impl<'de> Visitor<'de> for NaiveDateTimeVisitor {
     }
 }
 
-impl<'de> Deserialize<'de> for NaiveDateTime {
-    fn deserialize<D>(deserializer: D) -> Result<NaiveDateTime, D::Error>
+impl<'de> Deserialize<'de> for DateTime {
+    fn deserialize<D>(deserializer: D) -> Result<DateTime, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(DateTimeVisitor)
+    }
+}
+
+/// A task's date, either a whole day, like an Obsidian `📅 2026-11-01`, or a
+/// time on it.
+#[derive(Clone, PartialEq)]
+pub enum Date {
+    Day(NaiveDate),
+    Time(DateTime),
+}
+
+impl Date {
+    /// A time on the clock, in whatever zone the list uses.
+    pub fn floating(d: ChronoNaiveDateTime) -> Date {
+        Date::Time(DateTime::floating(d))
+    }
+
+    pub fn is_all_day(&self) -> bool {
+        matches!(self, Date::Day(_))
+    }
+
+    /// The day on the clock here.
+    pub fn date(&self) -> NaiveDate {
+        self.date_in(None)
+    }
+
+    /// The day on the clock in a list's zone, or here for None.
+    pub fn date_in(&self, zone: Option<Tz>) -> NaiveDate {
+        match self {
+            Date::Day(d) => *d,
+            Date::Time(t) => t.naive_in(zone).date(),
+        }
+    }
+
+    /// The time on the clock here, a whole day starts at midnight.
+    pub fn naive_local(&self) -> ChronoNaiveDateTime {
+        self.naive_in(None)
+    }
+
+    /// The time on the clock in a list's zone, or here for None.
+    pub fn naive_in(&self, zone: Option<Tz>) -> ChronoNaiveDateTime {
+        match self {
+            Date::Day(d) => d.and_time(NaiveTime::MIN),
+            Date::Time(t) => t.naive_in(zone),
+        }
+    }
+
+    /// Whether it's over at `now`, a whole day only once the day has ended.
+    pub fn is_past(&self, now: ChronoNaiveDateTime) -> bool {
+        self.is_past_in(None, now)
+    }
+
+    /// Whether it's over at `now` on the clock in a list's zone, or here for
+    /// None.
+    pub fn is_past_in(&self, zone: Option<Tz>, now: ChronoNaiveDateTime) -> bool {
+        match self {
+            Date::Day(d) => now.date() > *d,
+            Date::Time(t) => now > t.naive_in(zone),
+        }
+    }
+
+    /// The last moment of it in a zone, the end of a whole day.
+    pub fn end_in<Z: TimeZone>(&self, zone: &Z) -> ChronoDateTime<Z> {
+        match self {
+            Date::Day(d) => {
+                let end = d.and_time(NaiveTime::MIN) + ChronoDuration::days(1);
+                DateTime::floating(end - ChronoDuration::seconds(1)).in_zone(zone)
+            }
+            Date::Time(t) => t.in_zone(zone),
+        }
+    }
+
+    /// Move it on like `DateTime::add_in`, a whole day stays a whole day.
+    pub fn add_in<Z: TimeZone>(&self, zone: &Z, d: ChronoDuration) -> Date {
+        match self {
+            Date::Day(e) => Date::Day((e.and_time(NaiveTime::MIN) + d).date()),
+            Date::Time(t) => Date::Time(t.add_in(zone, d)),
+        }
+    }
+
+    /// Parse `yyyy-mm-dd` as a whole day, or a time like `DateTime::parse`.
+    pub fn parse(s: &str) -> chrono::ParseResult<Date> {
+        match NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d") {
+            Ok(e) => Ok(Date::Day(e)),
+            Err(_) => DateTime::parse(s).map(Date::Time),
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Date::Day(d) => write!(f, "{}", d.format("%Y-%m-%d")),
+            Date::Time(t) => write!(f, "{}", t),
+        }
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D>(deserializer: D) -> Result<Date, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Date::parse(&s).map_err(|_| serde::de::Error::custom(format!("Could not parse date {}", s)))
+    }
+}
+
+/// A time zone from the IANA database, like `Europe/Berlin`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Zone(pub Tz);
+
+impl FromStr for Zone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Zone, Self::Err> {
+        s.parse().map(Zone).map_err(|e| e.to_string())
+    }
+}
+
+impl Serialize for Zone {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.0.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Zone {
+    fn deserialize<D>(deserializer: D) -> Result<Zone, D::Error>
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_str(NaiveDateTimeVisitor)
+        let s = String::deserialize(deserializer)?;
+        Zone::from_str(&s).map_err(serde::de::Error::custom)
     }
 }
@@ -0,0 +1,309 @@
+use crate::storage;
+use crate::structs::content_hash;
+use crate::{Error, Query, Result, Todo, TodoList};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Bumped when the index format changes, older indexes are ignored.
+const INDEX_VERSION: u32 = 3;
+
+/// A note's tasks along with what's needed to tell if the note changed.
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry {
+    /// Modification time as seconds and nanoseconds since the epoch.
+    modified: Option<(u64, u32)>,
+    size: u64,
+    hash: String,
+    tasks: TodoList,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Index {
+    version: u32,
+    files: BTreeMap<String, Entry>,
+}
+
+/// Notes whose tasks changed in a `refresh`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Changes {
+    pub added: Vec<String>,
+    pub modified: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// The tasks in every markdown note under a directory, like an Obsidian
+/// vault. Notes are keyed by their path relative to the vault, with `/`
+/// separators, the same paths Obsidian uses.
+///
+/// The index can be saved and loaded again later, and `refresh` only
+/// re-parses notes that changed since, going by their modification time and
+/// size, then their content hash.
+pub struct Vault {
+    root: PathBuf,
+    files: BTreeMap<String, Entry>,
+    /// Notes that couldn't be read or parsed, with why.
+    errors: Vec<(String, Error)>,
+}
+
+impl Vault {
+    /// Index every `.md` file under a directory, skipping hidden files and
+    /// directories like `.obsidian`. A note that fails to parse doesn't fail
+    /// the whole vault, it's left out and its error kept, see `errors`.
+    pub fn open(root: &str) -> Result<Vault> {
+        let mut v = Vault::new(root);
+        v.refresh()?;
+        Ok(v)
+    }
+
+    /// Open a vault starting from an index saved with `save_index`, so only
+    /// notes changed since are parsed. A missing or outdated index just means
+    /// every note is parsed.
+    pub fn open_with_index(root: &str, index: &str) -> Result<Vault> {
+        let mut v = Vault::new(root);
+        match fs::read_to_string(index) {
+            Ok(s) => {
+                // The index is only a cache, start over if it's unreadable.
+                if let Ok(e) = serde_json::from_str::<Index>(&s) {
+                    if e.version == INDEX_VERSION {
+                        v.files = e.files;
+                    }
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => (),
+            Err(e) => return Err(Error::io(index, e)),
+        }
+        v.refresh()?;
+        Ok(v)
+    }
+
+    pub fn save_index(&self, index: &str) -> Result<()> {
+        let s = serde_json::to_string(&Index {
+            version: INDEX_VERSION,
+            files: self.files.clone(),
+        })?;
+        storage::write_atomic(index, &s, 0)
+    }
+
+    /// An empty vault, notes are added with `insert`.
+    pub fn new(root: &str) -> Vault {
+        Vault {
+            root: PathBuf::from(root),
+            files: BTreeMap::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Bring the index up to date with the directory, parsing new and changed
+    /// notes and dropping deleted ones.
+    pub fn refresh(&mut self) -> Result<Changes> {
+        let mut changes = Changes::default();
+        let paths = self._scan()?;
+
+        let found: HashSet<&String> = paths.iter().collect();
+        let removed: Vec<String> = self
+            .files
+            .keys()
+            .filter(|x| !found.contains(x))
+            .cloned()
+            .collect();
+        for path in removed {
+            self.files.remove(&path);
+            changes.removed.push(path);
+        }
+        self.errors.retain(|(p, _)| found.contains(p));
+
+        for path in paths {
+            let existed = self.files.contains_key(&path);
+            match self.reload(&path) {
+                Ok(false) => (),
+                Ok(true) if existed => changes.modified.push(path),
+                Ok(true) => changes.added.push(path),
+                Err(e) => {
+                    if self.files.remove(&path).is_some() {
+                        changes.removed.push(path.clone());
+                    }
+                    self.errors.push((path, e));
+                }
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Paths of every note under the root, relative to it.
+    fn _scan(&self) -> Result<Vec<String>> {
+        let mut paths = Vec::new();
+        let mut dirs = vec![self.root.clone()];
+        while let Some(dir) = dirs.pop() {
+            let entries = fs::read_dir(&dir).map_err(|e| Error::io(&dir, e))?;
+            for entry in entries {
+                let path = entry.map_err(|e| Error::io(&dir, e))?.path();
+                let hidden = match path.file_name() {
+                    Some(e) => e.to_string_lossy().starts_with('.'),
+                    None => true,
+                };
+                if hidden {
+                    continue;
+                }
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().map(|x| x == "md") == Some(true) {
+                    paths.push(self._relative(&path));
+                }
+            }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn _relative(&self, path: &Path) -> String {
+        let path = path.strip_prefix(&self.root).unwrap_or(path);
+        let parts: Vec<String> = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        parts.join("/")
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Full path of a note in the vault.
+    pub fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// Notes that couldn't be indexed.
+    pub fn errors(&self) -> &[(String, Error)] {
+        &self.errors
+    }
+
+    /// Re-read a note from disk if it changed, returning whether its tasks
+    /// were parsed again.
+    pub fn reload(&mut self, path: &str) -> Result<bool> {
+        let file = self.path(path);
+        let meta = fs::metadata(&file).map_err(|e| Error::io(&file, e))?;
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+            .map(|x| (x.as_secs(), x.subsec_nanos()));
+
+        if let Some(e) = self.files.get(path) {
+            if modified.is_some() && e.modified == modified && e.size == meta.len() {
+                return Ok(false);
+            }
+        }
+
+        let s = fs::read_to_string(&file).map_err(|e| Error::io(&file, e))?;
+        let changed = self.insert(path, &s)?;
+        if let Some(e) = self.files.get_mut(path) {
+            e.modified = modified;
+            e.size = meta.len();
+        }
+        Ok(changed)
+    }
+
+    /// Parse the content of a note, replacing any tasks already indexed for
+    /// its path. Returns false, without parsing, if the content is the same
+    /// as what was indexed.
+    pub fn insert(&mut self, path: &str, content: &str) -> Result<bool> {
+        let hash = content_hash(content);
+        if self.files.get(path).map(|x| &x.hash) == Some(&hash) {
+            return Ok(false);
+        }
+
+        let tasks = TodoList::from_note(content, path)?;
+        self.errors.retain(|(p, _)| p != path);
+        self.files.insert(
+            String::from(path),
+            Entry {
+                modified: None,
+                size: content.len() as u64,
+                hash,
+                tasks,
+            },
+        );
+        Ok(true)
+    }
+
+    pub fn remove(&mut self, path: &str) -> Option<TodoList> {
+        self.errors.retain(|(p, _)| p != path);
+        self.files.remove(path).map(|x| x.tasks)
+    }
+
+    pub fn get(&self, path: &str) -> Option<&TodoList> {
+        self.files.get(path).map(|x| &x.tasks)
+    }
+
+    pub fn get_mut(&mut self, path: &str) -> Option<&mut TodoList> {
+        self.files.get_mut(path).map(|x| &mut x.tasks)
+    }
+
+    /// Every note with its tasks, ordered by path.
+    pub fn files(&self) -> impl Iterator<Item = (&str, &TodoList)> {
+        self.files.iter().map(|(p, x)| (p.as_str(), &x.tasks))
+    }
+
+    /// Number of notes.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Every task in the vault, including sub tasks.
+    pub fn walk(&self) -> impl Iterator<Item = &Todo> {
+        self.files
+            .values()
+            .flat_map(|x| x.tasks.walk().map(|(_, t)| t))
+    }
+
+    /// Find a task by id in any note.
+    pub fn get_by_id(&self, id: &str) -> Option<&Todo> {
+        self.files.values().find_map(|x| x.tasks.get_by_id(id))
+    }
+
+    /// Tasks in any note matching a query, optionally searching sub tasks.
+    /// Each task's `source` says which note it's from.
+    pub fn query(&self, q: &Query, recursive: bool) -> Vec<&Todo> {
+        self.files
+            .values()
+            .flat_map(|x| x.tasks.query(q, recursive))
+            .collect()
+    }
+
+    /// Every task under a heading in any note.
+    pub fn section(&self, heading: &str) -> Vec<&Todo> {
+        self.files
+            .values()
+            .flat_map(|x| x.tasks.section(heading))
+            .collect()
+    }
+
+    /// All the notes' tasks in one list, so dependencies between tasks in
+    /// different notes can be followed.
+    pub fn merged(&self) -> TodoList {
+        self.files
+            .values()
+            .flat_map(|x| x.tasks.iter().cloned())
+            .collect()
+    }
+
+    /// Hash used to tell if a note's content changed.
+    pub fn content_hash(content: &str) -> String {
+        content_hash(content)
+    }
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use todo::storage::{self, Lock};
+use todo::{Document, Error, Todo, TodoList};
+
+fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("todo-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn read(path: PathBuf) -> String {
+    fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn writes_replace_the_whole_file() {
+    let dir = dir("atomic");
+    let file = dir.join("todo.json");
+    let file = file.to_str().unwrap();
+
+    storage::write_atomic(file, "a much longer first version", 0).unwrap();
+    storage::write_atomic(file, "second", 0).unwrap();
+    assert_eq!(fs::read_to_string(file).unwrap(), "second");
+
+    // No temporary files or backups are left behind.
+    let names: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|x| x.unwrap().file_name())
+        .collect();
+    assert_eq!(names, vec!["todo.json"]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn backups_keep_the_newest_versions() {
+    let dir = dir("backups");
+    let file = dir.join("todo.md");
+    let file = file.to_str().unwrap();
+
+    for n in 1..=4 {
+        storage::write_atomic(file, &format!("version {}", n), 2).unwrap();
+    }
+    assert_eq!(fs::read_to_string(file).unwrap(), "version 4");
+    assert_eq!(read(storage::backup_path(file, 1)), "version 3");
+    assert_eq!(read(storage::backup_path(file, 2)), "version 2");
+    assert!(!storage::backup_path(file, 3).exists());
+    assert_eq!(storage::backup_path(file, 1), dir.join("todo.md.1"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn lists_and_notes_save_with_backups() {
+    let dir = dir("save-backups");
+    let json = dir.join("todo.json");
+    let json = json.to_str().unwrap();
+
+    let mut tdl = TodoList::new();
+    tdl.add(Todo::from_title(String::from("First")));
+    tdl.to_json_file_with(json, 1).unwrap();
+    tdl.add(Todo::from_title(String::from("Second")));
+    tdl.to_json_file_with(json, 1).unwrap();
+    assert_eq!(TodoList::from_json_file(json).unwrap().len(), 2);
+    let backup = storage::backup_path(json, 1);
+    assert_eq!(
+        TodoList::from_json_file(backup.to_str().unwrap())
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let md = dir.join("Inbox.md");
+    let md = md.to_str().unwrap();
+    fs::write(md, "# Inbox\n- [ ] Buy milk\n").unwrap();
+    let doc = Document::from_markdown_file(md).unwrap();
+    doc.to_markdown_file_with(md, 1).unwrap();
+    assert_eq!(
+        read(storage::backup_path(md, 1)),
+        "# Inbox\n- [ ] Buy milk\n"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn locks_are_exclusive_until_dropped() {
+    let dir = dir("lock");
+    let file = dir.join("todo.json");
+    let file = file.to_str().unwrap();
+
+    let lock = Lock::acquire(file, Duration::from_secs(1)).unwrap();
+    assert_eq!(lock.path(), dir.join(".todo.json.lock"));
+
+    let start = Instant::now();
+    match Lock::acquire(file, Duration::from_millis(200)).err() {
+        Some(Error::Locked(p)) => assert_eq!(p, PathBuf::from(file)),
+        _ => panic!("expected the file to be locked"),
+    }
+    assert!(start.elapsed() >= Duration::from_millis(200));
+
+    drop(lock);
+    assert!(!dir.join(".todo.json.lock").exists());
+    assert!(Lock::acquire(file, Duration::ZERO).is_ok());
+    fs::remove_dir_all(&dir).unwrap();
+}
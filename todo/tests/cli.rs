@@ -0,0 +1,132 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A task file in a directory of its own.
+fn file(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("todo-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.join("todo.md")
+}
+
+/// Run the binary on a file, giving its exit code and output.
+fn todo(file: &PathBuf, args: &[&str]) -> (i32, String) {
+    let out = Command::new(env!("CARGO_BIN_EXE_todo"))
+        .env("TODO_CONFIG", file.with_file_name("config.json"))
+        .arg("--file")
+        .arg(file)
+        .args(args)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    (out.status.code().unwrap(), stdout.trim().to_string())
+}
+
+#[test]
+fn tasks_are_added_and_done_in_a_note() {
+    let f = file("note");
+    fs::write(&f, "# Today\nSome notes.\n").unwrap();
+    let (code, id) = todo(&f, &["add", "Write report", "--tag", "work"]);
+    assert_eq!(code, 0);
+    let (_, sub) = todo(&f, &["add", "Outline", "--parent", &id]);
+    assert_eq!(todo(&f, &["done", &sub]).0, 0);
+
+    let note = fs::read_to_string(&f).unwrap();
+    assert!(
+        note.starts_with("# Today\nSome notes.\n- [ ] Write report"),
+        "{}",
+        note
+    );
+    assert!(note.contains("#work"), "{}", note);
+    assert!(note.contains("\t- [x] Outline"), "{}", note);
+
+    let (_, listed) = todo(&f, &["list", "--filter", "tag:work"]);
+    assert!(listed.contains("Write report"), "{}", listed);
+    assert_eq!(
+        todo(&f, &["list", "--filter", "tag:home"]),
+        (0, String::new())
+    );
+
+    assert_eq!(todo(&f, &["rm", &id]).0, 0);
+    assert_eq!(fs::read_to_string(&f).unwrap(), "# Today\nSome notes.\n");
+    fs::remove_dir_all(f.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn other_files_are_json() {
+    let f = file("json").with_file_name("tasks.json");
+    let (_, id) = todo(&f, &["add", "Pay rent", "--due", "2026-11-01 09:00:00"]);
+    assert_eq!(todo(&f, &["edit", &id, "--title", "Pay the rent"]).0, 0);
+
+    let (_, json) = todo(&f, &["export", "--format", "json"]);
+    let v: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        v,
+        serde_json::from_str::<serde_json::Value>(&fs::read_to_string(&f).unwrap()).unwrap()
+    );
+    let (_, md) = todo(&f, &["export"]);
+    assert!(md.starts_with("- [ ] Pay the rent"), "{}", md);
+    fs::remove_dir_all(f.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn exit_codes_say_what_went_wrong() {
+    let f = file("exit-codes");
+    let (_, first) = todo(&f, &["add", "Write report"]);
+    let (_, second) = todo(&f, &["add", "Send report", "--depends-on", &first]);
+
+    assert_eq!(todo(&f, &["done", "nosuchtask"]).0, 3);
+    assert_eq!(todo(&f, &["edit", &first, "--depends-on", &second]).0, 4);
+    assert_eq!(todo(&f, &["done", &second]).0, 4);
+    assert_eq!(todo(&f, &["frobnicate"]).0, 2);
+    assert_eq!(todo(&f, &["add", "Bad", "--due", "someday"]).0, 1);
+    fs::remove_dir_all(f.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn quick_add_reads_the_title() {
+    let f = file("quick");
+    let (code, _) = todo(&f, &["add", "--quick", "Pay rent by nov 1 #home !high"]);
+    assert_eq!(code, 0);
+    let note = fs::read_to_string(&f).unwrap();
+    assert!(note.starts_with("- [ ] Pay rent #home 🆔"), "{}", note);
+    assert!(note.contains("⏫"), "{}", note);
+    assert!(
+        note.contains("📅 2026-11-01") || note.contains("📅 2027-11-01"),
+        "{}",
+        note
+    );
+    fs::remove_dir_all(f.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn blocked_tasks_cant_be_marked_done() {
+    let f = file("blocked");
+    let (_, first) = todo(&f, &["add", "Write report"]);
+    let (_, second) = todo(&f, &["add", "Send report", "--depends-on", &first]);
+    let before = fs::read_to_string(&f).unwrap();
+
+    assert_eq!(todo(&f, &["done", &second]).0, 4);
+    assert_eq!(todo(&f, &["edit", &second, "--status", "done"]).0, 4);
+    assert_eq!(fs::read_to_string(&f).unwrap(), before);
+
+    assert_eq!(todo(&f, &["edit", &first, "--status", "done"]).0, 0);
+    assert_eq!(todo(&f, &["edit", &second, "--status", "done"]).0, 0);
+    let after = fs::read_to_string(&f).unwrap();
+    assert!(after.contains("- [x] Send report"), "{}", after);
+    fs::remove_dir_all(f.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn dates_are_read_in_the_date_format() {
+    let f = file("date-format");
+    let args = ["--date-format", "%d/%m/%Y", "add", "Pay rent", "--due"];
+    assert_eq!(todo(&f, &[&args[..], &["01/11/2026"]].concat()).0, 0);
+    assert_eq!(todo(&f, &[&args[..], &["2026-11-02"]].concat()).0, 0);
+    let after = fs::read_to_string(&f).unwrap();
+    assert!(after.contains("Pay rent 🆔"), "{}", after);
+    assert!(after.contains("📅 2026-11-01"), "{}", after);
+    assert!(after.contains("📅 2026-11-02"), "{}", after);
+    fs::remove_dir_all(f.parent().unwrap()).unwrap();
+}
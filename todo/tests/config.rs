@@ -0,0 +1,129 @@
+use chrono::{NaiveDate, NaiveDateTime, Weekday};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use todo::config::Format;
+use todo::Config;
+
+fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("todo-config-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn read(dir: &Path, s: &str) -> todo::Result<Config> {
+    let path = dir.join("config.json");
+    fs::write(&path, s).unwrap();
+    Config::from_file(&path)
+}
+
+/// Sunday 2026-10-18, 10:00.
+fn now() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2026, 10, 18)
+        .unwrap()
+        .and_hms_opt(10, 0, 0)
+        .unwrap()
+}
+
+#[test]
+fn missing_settings_are_the_defaults() {
+    let dir = dir("defaults");
+    let missing = Config::from_file(&dir.join("nothing.json")).unwrap();
+    assert_eq!(missing, Config::default());
+    assert_eq!(read(&dir, "{}").unwrap(), Config::default());
+    assert_eq!(
+        Config::default().reminder_before().unwrap(),
+        Some(chrono::Duration::minutes(15))
+    );
+
+    let config = read(
+        &dir,
+        r#"{"date_format": "%d/%m/%Y", "week_start": "sunday", "reminders": {"enabled": false}}"#,
+    )
+    .unwrap();
+    assert_eq!(config.date_format, "%d/%m/%Y");
+    assert_eq!(config.week_start, Weekday::Sun);
+    assert_eq!(config.reminder_before().unwrap(), None);
+    assert_eq!(config.file, Config::default().file);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn bad_settings_are_errors() {
+    let dir = dir("bad");
+    for s in [
+        r#"{"colour": "blue"}"#,
+        r#"{"week_start": "someday"}"#,
+        r#"{"format": "yaml"}"#,
+        r#"{"reminders": {"before": "soonish"}}"#,
+        "{",
+    ] {
+        assert!(read(&dir, s).is_err(), "{}", s);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn formats_go_by_extension_unless_set() {
+    let mut config = Config::default();
+    assert_eq!(config.format_of("Inbox.md"), Format::Markdown);
+    assert_eq!(config.format_of("todo.json"), Format::Json);
+    assert_eq!(config.format_of("todo.txt"), Format::Json);
+    config.format = Some(Format::Markdown);
+    assert_eq!(config.format_of("todo.txt"), Format::Markdown);
+}
+
+#[test]
+fn weeks_end_on_the_configured_day() {
+    let day = |d| NaiveDate::from_ymd_opt(2026, 10, d).unwrap();
+    let mut config = Config::default();
+    // 2026-10-18 is a Sunday.
+    assert_eq!(config.end_of_week(day(18)), day(18));
+    assert_eq!(config.end_of_week(day(12)), day(18));
+    config.week_start = Weekday::Sun;
+    assert_eq!(config.end_of_week(day(18)), day(24));
+    assert_eq!(config.end_of_week(day(17)), day(17));
+}
+
+#[test]
+fn reads_dates_in_the_date_format_setting() {
+    let config = Config {
+        date_format: String::from("%d/%m/%Y"),
+        ..Config::default()
+    };
+    let read = |s| config.read_date(s, now()).unwrap().to_string();
+    assert_eq!(read("01/11/2026"), "2026-11-01");
+    assert_eq!(read("2026-11-01"), "2026-11-01");
+    assert_eq!(read("tomorrow"), "2026-10-19");
+    assert!(config.read_date("31/02/2026", now()).is_err());
+}
+
+#[test]
+fn flags_override_the_environment_which_overrides_the_file() {
+    let dir = dir("overrides");
+    let config = dir.join("config.json");
+    let (a, b, c) = (dir.join("a.md"), dir.join("b.md"), dir.join("c.md"));
+    let json = serde_json::json!({ "file": a });
+    fs::write(&config, json.to_string()).unwrap();
+
+    let add = |env: Option<&Path>, flag: Option<&Path>| {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_todo"));
+        cmd.env("TODO_CONFIG", &config).env_remove("TODO_FILE");
+        if let Some(e) = env {
+            cmd.env("TODO_FILE", e);
+        }
+        if let Some(e) = flag {
+            cmd.arg("--file").arg(e);
+        }
+        assert!(cmd.args(["add", "Task"]).status().unwrap().success());
+    };
+    add(None, None);
+    add(Some(&b), None);
+    add(Some(&b), Some(&c));
+    for f in [&a, &b, &c] {
+        let s = fs::read_to_string(f).unwrap();
+        assert_eq!(s.lines().count(), 1, "{}", s);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
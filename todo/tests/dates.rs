@@ -0,0 +1,119 @@
+use chrono::{NaiveDate, NaiveDateTime, Weekday};
+use todo::{dates, Config, Date, Error};
+
+/// Sunday 2026-10-18, 10:00.
+fn now() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2026, 10, 18)
+        .unwrap()
+        .and_hms_opt(10, 0, 0)
+        .unwrap()
+}
+
+fn parse(s: &str) -> String {
+    dates::parse(s, now(), Weekday::Mon).unwrap().to_string()
+}
+
+#[test]
+fn reads_dates_as_people_write_them() {
+    assert_eq!(parse("today"), "2026-10-18");
+    assert_eq!(parse("tomorrow"), "2026-10-19");
+    assert_eq!(parse("next friday"), "2026-10-23");
+    assert_eq!(parse("in 3 days"), "2026-10-21");
+    assert_eq!(parse("eow"), "2026-10-18");
+    assert_eq!(parse("2026-11-01 14:00"), "2026-11-01 14:00:00");
+    assert_eq!(parse("nov 3"), "2026-11-03");
+    assert_eq!(parse("tomorrow 9am"), "2026-10-19 09:00:00");
+}
+
+#[test]
+fn reads_weekdays_months_and_amounts() {
+    assert_eq!(parse("Friday"), "2026-10-23");
+    assert_eq!(parse("sun"), "2026-10-25");
+    assert_eq!(parse("this sunday"), "2026-10-18");
+    assert_eq!(parse("yesterday"), "2026-10-17");
+    assert_eq!(parse("next week"), "2026-10-25");
+    assert_eq!(parse("eom"), "2026-10-31");
+    assert_eq!(parse("end of month"), "2026-10-31");
+    assert_eq!(parse("in a week"), "2026-10-25");
+    assert_eq!(parse("in 2 months"), "2026-12-18");
+    assert_eq!(parse("in 1 year"), "2027-10-18");
+    assert_eq!(parse("in 2 hours"), "2026-10-18 12:00:00");
+    assert_eq!(parse("3 November"), "2026-11-03");
+    assert_eq!(parse("oct 1"), "2027-10-01");
+    assert_eq!(parse("march 3, 2030"), "2030-03-03");
+    assert_eq!(parse("friday 9:30pm"), "2026-10-23 21:30:00");
+    assert_eq!(parse("noon"), "2026-10-18 12:00:00");
+    // A time that's already passed today is tomorrow.
+    assert_eq!(parse("9am"), "2026-10-19 09:00:00");
+}
+
+#[test]
+fn weeks_end_on_the_day_before_they_start() {
+    let eow = |start| dates::parse("eow", now(), start).unwrap().to_string();
+    assert_eq!(eow(Weekday::Mon), "2026-10-18");
+    assert_eq!(eow(Weekday::Sun), "2026-10-24");
+
+    let config = Config {
+        date_format: String::from("%d/%m/%Y"),
+        week_start: Weekday::Sun,
+        ..Config::default()
+    };
+    let read = |s| config.read_date(s, now()).unwrap().to_string();
+    assert_eq!(read("01/11/2026"), "2026-11-01");
+    assert_eq!(read("end of week"), "2026-10-24");
+}
+
+#[test]
+fn rejects_what_isnt_a_date() {
+    for s in ["", "someday", "next blursday", "in 3 fortnights"] {
+        assert!(dates::parse(s, now(), Weekday::Mon).is_err(), "{}", s);
+    }
+}
+
+#[test]
+fn errors_point_at_the_word_not_understood() {
+    match dates::parse("next blursday", now(), Weekday::Mon) {
+        Err(Error::Parse { column, .. }) => assert_eq!(column, 6),
+        e => panic!("expected a parse error, got {:?}", e.map(|x| x.to_string())),
+    }
+}
+
+#[test]
+fn rejects_amounts_too_big_for_a_date() {
+    for s in [
+        "in 9223372036854775807 days",
+        "in 9223372036854775807 weeks",
+        "in 9223372036854775807 hours",
+        "in 9223372036854775807 minutes",
+        "in 999999999 days",
+        "in 9223372036854775807 months",
+        "in 9223372036854775807 years",
+        "in -9223372036854775808 days",
+    ] {
+        match dates::parse(s, now(), Weekday::Mon) {
+            Err(Error::InvalidDate(_)) => (),
+            Err(e) => panic!("{}: {}", s, e),
+            Ok(e) => panic!("{}: {}", s, e),
+        }
+    }
+    assert!(matches!(
+        dates::parse("in 2 days", now(), Weekday::Mon),
+        Ok(Date::Day(_))
+    ));
+}
+
+#[test]
+fn leap_days_are_the_next_one() {
+    let parse_at = |s, y, m, d| {
+        let now = NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        dates::parse(s, now, Weekday::Mon).unwrap().to_string()
+    };
+    assert_eq!(parse_at("feb 29", 2026, 10, 18), "2028-02-29");
+    assert_eq!(parse_at("29 feb", 2027, 3, 1), "2028-02-29");
+    assert_eq!(parse_at("feb 29", 2028, 2, 29), "2028-02-29");
+    assert_eq!(parse_at("feb 29", 2097, 3, 1), "2104-02-29");
+    assert!(dates::parse("feb 30", now(), Weekday::Mon).is_err());
+}
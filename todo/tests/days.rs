@@ -0,0 +1,89 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use chrono_tz::Tz;
+use std::str::FromStr;
+use todo::{Date, Document, Query, Todo, TodoList};
+
+fn at(s: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+}
+
+fn due(s: &str) -> Todo {
+    let mut t = Todo::from_title(String::from("Pay rent"));
+    t.set_id(String::from("rent"));
+    t.set_due_iso8601(String::from(s)).unwrap();
+    t
+}
+
+#[test]
+fn dates_without_a_time_are_whole_days() {
+    let t = due("2026-11-01");
+    let day = NaiveDate::from_ymd_opt(2026, 11, 1).unwrap();
+    assert!(t.due_date() == Some(&Date::Day(day)));
+    assert!(t.due_date().unwrap().is_all_day());
+    assert_eq!(t.due(), Some(at("2026-11-01 00:00")));
+    assert_eq!(serde_json::to_value(&t).unwrap()["due"], "2026-11-01");
+
+    let t = due("2026-11-01 09:30:00");
+    assert!(!t.due_date().unwrap().is_all_day());
+    assert_eq!(t.due_date().unwrap().date(), day);
+    assert_eq!(
+        serde_json::to_value(&t).unwrap()["due"],
+        "2026-11-01 09:30:00"
+    );
+}
+
+#[test]
+fn whole_days_are_written_back_as_they_were_read() {
+    let s = "- [ ] Pay rent 🛫 2026-10-25 📅 2026-11-01\n";
+    let doc = Document::from_markdown(s).unwrap();
+    assert_eq!(doc.to_markdown(), s);
+
+    let tdl = TodoList::from_markdown(s).unwrap();
+    let back = TodoList::from_json(&tdl.to_json().unwrap()).unwrap();
+    assert_eq!(back.to_markdown(), tdl.to_markdown());
+    assert!(back.iter().next().unwrap().due_date().unwrap().is_all_day());
+}
+
+#[test]
+fn tasks_due_on_a_day_are_overdue_the_day_after() {
+    let t = due("2026-11-01");
+    assert!(!t.is_overdue_at(at("2026-11-01 00:00")));
+    assert!(!t.is_overdue_at(at("2026-11-01 23:59")));
+    assert!(t.is_overdue_at(at("2026-11-02 00:00")));
+
+    let t = due("2026-11-01 09:30:00");
+    assert!(!t.is_overdue_at(at("2026-11-01 09:30")));
+    assert!(t.is_overdue_at(at("2026-11-01 09:31")));
+
+    let mut done = due("2026-11-01");
+    done.complete(|_| true).unwrap();
+    assert!(!done.is_overdue_at(at("2026-11-02 00:00")));
+    assert!(!Todo::from_title(String::from("Someday")).is_overdue_at(at("2026-11-02 00:00")));
+}
+
+#[test]
+fn overdue_can_be_queried() {
+    let q = Query::from_str("overdue").unwrap();
+    assert_eq!(q, Query::Overdue);
+    assert!(!q.matches_at(&due("2026-11-01"), at("2026-11-01 18:00")));
+    assert!(q.matches_at(&due("2026-11-01"), at("2026-11-02 08:00")));
+    assert!(q.matches_at(&due("2026-11-01 09:30:00"), at("2026-11-01 18:00")));
+}
+
+#[test]
+fn whole_days_repeat_as_whole_days() {
+    for (repeat, next) in [
+        ("1 day from due", "2030-10-27"),
+        ("1 week from due", "2030-11-02"),
+        // Days are over at midnight, so the next 9:00 is the day after.
+        ("0 0 9 * * *", "2030-10-27"),
+    ] {
+        let mut tdl = TodoList::new();
+        let mut t = due("2030-10-26");
+        t.set_repeat(String::from(repeat)).unwrap();
+        tdl.add(t);
+        tdl.set_zone(Some(Tz::Europe__Berlin));
+        let t = tdl.complete("rent").unwrap().unwrap();
+        assert_eq!(serde_json::to_value(&t).unwrap()["due"], next, "{}", repeat);
+    }
+}
@@ -0,0 +1,67 @@
+use todo::{Error, Todo, TodoList};
+
+/// A list where "Ship" depends on "Test".
+fn list() -> (TodoList, String, String) {
+    let test = Todo::from_title(String::from("Test"));
+    let mut ship = Todo::from_title(String::from("Ship"));
+    ship.add_dependency(String::from(test.id()));
+    let (test_id, ship_id) = (String::from(test.id()), String::from(ship.id()));
+
+    let mut tdl = TodoList::new();
+    tdl.add(test);
+    tdl.add(ship);
+    (tdl, test_id, ship_id)
+}
+
+#[test]
+fn dependencies_are_ids() {
+    let mut t = Todo::from_title(String::from("Ship"));
+    t.add_dependency(String::from("abc123"));
+    t.add_dependency(String::from("abc123"));
+    t.add_dependency(String::from("def456"));
+    assert_eq!(t.dependencies(), ["abc123", "def456"]);
+
+    t.remove_dependency("abc123");
+    assert_eq!(t.dependencies(), ["def456"]);
+}
+
+#[test]
+fn dependencies_survive_markdown_and_json() {
+    let (tdl, test_id, _) = list();
+
+    let md = tdl.to_markdown();
+    assert!(md.contains(&format!("⛔ {}", test_id)), "{}", md);
+    let back = TodoList::from_markdown(&md).unwrap();
+    assert_eq!(back[1].dependencies(), [test_id.as_str()]);
+
+    let back = TodoList::from_json(&tdl.to_json().unwrap()).unwrap();
+    assert_eq!(back[1].dependencies(), [test_id.as_str()]);
+}
+
+#[test]
+fn blocked_tasks_cant_be_completed() {
+    let (mut tdl, test_id, ship_id) = list();
+
+    match tdl.complete(&ship_id) {
+        Err(Error::Dependency(e)) => assert!(e.contains(&test_id), "{}", e),
+        e => panic!("expected a dependency error, got {:?}", e.err()),
+    }
+    assert!(!tdl.get_by_id(&ship_id).unwrap().is_completed());
+
+    tdl.complete(&test_id).unwrap();
+    tdl.complete(&ship_id).unwrap();
+    assert!(tdl.get_by_id(&ship_id).unwrap().is_completed());
+}
+
+#[test]
+fn missing_dependencies_dont_block() {
+    let mut t = Todo::from_title(String::from("Ship"));
+    t.add_dependency(String::from("gone00"));
+    let id = String::from(t.id());
+    let mut tdl = TodoList::new();
+    tdl.add(t);
+
+    tdl.complete(&id).unwrap();
+    assert!(tdl.get_by_id(&id).unwrap().is_completed());
+    assert!(matches!(tdl.complete("nope"), Err(Error::NotFound(e)) if e == "nope"));
+}
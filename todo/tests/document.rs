@@ -0,0 +1,211 @@
+use todo::{Document, Todo};
+
+const NOTE: &str = "# Week
+
+Some prose about the week, with a - dash and [brackets].
+
+- [ ] Write report #work
+\t- [ ] Outline
+\t- [x]  Draft  with odd   spacing
+* [ ] Call Sam
+
+```rust
+fn main() {
+    println!(\"- not a task\");
+}
+```
+
+## Later
+1. [ ] Plan trip
+   - a plain bullet
+2. [/] Book hotel
+
+Closing words.
+";
+
+fn lines(s: &str) -> Vec<&str> {
+    s.lines().collect()
+}
+
+/// Lines of `b` that aren't in `a`, and of `a` that aren't in `b`.
+fn diff<'a>(a: &'a str, b: &'a str) -> (Vec<&'a str>, Vec<&'a str>) {
+    let (a, b) = (lines(a), lines(b));
+    (
+        b.iter().filter(|x| !a.contains(x)).copied().collect(),
+        a.iter().filter(|x| !b.contains(x)).copied().collect(),
+    )
+}
+
+/// A new task with its title as its id.
+fn task(title: &str) -> Todo {
+    let mut t = Todo::from_title(String::from(title));
+    t.set_id(title.to_lowercase());
+    t
+}
+
+fn find(doc: &mut Document, title: &str) -> String {
+    let t = doc.tasks().walk().find(|(_, t)| t.title() == title);
+    String::from(t.unwrap().1.id())
+}
+
+#[test]
+fn unchanged_notes_are_identical() {
+    for note in [
+        String::from(NOTE),
+        NOTE.replace('\n', "\r\n"),
+        String::from(NOTE.trim_end()),
+        String::new(),
+        String::from("Just prose."),
+    ] {
+        let doc = Document::from_markdown(&note).unwrap();
+        assert_eq!(doc.to_markdown(), note);
+    }
+    let doc = Document::from_markdown(NOTE).unwrap();
+    assert_eq!(doc.tasks().len(), 4);
+    assert_eq!(doc.tasks()[0].sub_tasks().len(), 2);
+}
+
+#[test]
+fn edits_rewrite_only_their_line() {
+    let mut doc = Document::from_markdown(NOTE).unwrap();
+    let id = find(&mut doc, "Outline");
+    doc.tasks_mut()
+        .get_by_id_mut(&id)
+        .unwrap()
+        .set_due_iso8601(String::from("2026-10-20 09:00:00"))
+        .unwrap();
+
+    let out = doc.to_markdown();
+    let (added, removed) = diff(NOTE, &out);
+    assert_eq!(removed, ["\t- [ ] Outline"]);
+    assert_eq!(added.len(), 1);
+    assert!(
+        added[0].starts_with("\t- [ ] Outline 📅 2026-10-20"),
+        "{}",
+        added[0]
+    );
+    assert_eq!(lines(&out).len(), lines(NOTE).len());
+    assert_eq!(lines(&out)[5], added[0]);
+}
+
+#[test]
+fn edits_keep_the_bullet() {
+    let mut doc = Document::from_markdown(NOTE).unwrap();
+    for title in ["Call Sam", "Book hotel"] {
+        let id = find(&mut doc, title);
+        doc.complete(&id).unwrap();
+    }
+    let out = doc.to_markdown();
+    assert!(lines(&out)[7].starts_with("* [x] Call Sam ✅"), "{}", out);
+    assert!(
+        lines(&out)[18].starts_with("2. [x] Book hotel ✅"),
+        "{}",
+        out
+    );
+    assert_eq!(diff(NOTE, &out).1, ["* [ ] Call Sam", "2. [/] Book hotel"]);
+}
+
+#[test]
+fn inserted_tasks_go_next_to_their_neighbours() {
+    let mut doc = Document::from_markdown(NOTE).unwrap();
+    doc.tasks_mut().insert(1, task("Between"));
+    doc.tasks_mut()[0].get_sub_tasks().add(task("Proofread"));
+    let mut trip = task("Pack");
+    trip.get_sub_tasks().add(task("Socks"));
+    doc.tasks_mut()[3].get_sub_tasks().add(trip);
+    doc.tasks_mut().add(task("Last"));
+
+    let out = doc.to_markdown();
+    let mut expected = lines(NOTE);
+    expected.insert(7, "\t- [ ] Proofread 🆔 proofread");
+    expected.insert(8, "- [ ] Between 🆔 between");
+    expected.insert(19, "\t- [ ] Pack 🆔 pack");
+    expected.insert(20, "\t\t- [ ] Socks 🆔 socks");
+    expected.insert(23, "- [ ] Last 🆔 last");
+    assert_eq!(lines(&out), expected, "{}", out);
+
+    let back = Document::from_markdown(&out).unwrap();
+    let titles: Vec<&str> = back.tasks().iter().map(|t| t.title()).collect();
+    assert_eq!(
+        titles,
+        [
+            "Write report",
+            "Between",
+            "Call Sam",
+            "Plan trip",
+            "Book hotel",
+            "Last"
+        ]
+    );
+}
+
+#[test]
+fn tasks_are_added_to_notes_without_any() {
+    let mut doc = Document::from_markdown("# Empty\n").unwrap();
+    doc.tasks_mut().add(task("First"));
+    assert_eq!(doc.to_markdown(), "# Empty\n- [ ] First 🆔 first\n");
+
+    let mut doc = Document::from_markdown("").unwrap();
+    doc.tasks_mut().add(task("First"));
+    assert_eq!(doc.to_markdown(), "- [ ] First 🆔 first\n");
+}
+
+#[test]
+fn removed_tasks_take_their_sub_tasks_with_them() {
+    let mut doc = Document::from_markdown(NOTE).unwrap();
+    doc.tasks_mut().remove(0);
+
+    let out = doc.to_markdown();
+    let (added, removed) = diff(NOTE, &out);
+    assert!(added.is_empty(), "{:?}", added);
+    assert_eq!(
+        removed,
+        [
+            "- [ ] Write report #work",
+            "\t- [ ] Outline",
+            "\t- [x]  Draft  with odd   spacing"
+        ]
+    );
+    assert_eq!(lines(&out).len(), lines(NOTE).len() - 3);
+}
+
+#[test]
+fn removing_a_task_keeps_other_list_items() {
+    let mut doc = Document::from_markdown(NOTE).unwrap();
+    doc.tasks_mut().remove(2);
+    let out = doc.to_markdown();
+    let (_, removed) = diff(NOTE, &out);
+    assert_eq!(removed, ["1. [ ] Plan trip"]);
+}
+
+#[test]
+fn ids_are_only_written_when_needed() {
+    let mut doc = Document::from_markdown(NOTE).unwrap();
+    let call = find(&mut doc, "Call Sam");
+    let trip = find(&mut doc, "Plan trip");
+    doc.tasks_mut().add_dependency(&trip, &call).unwrap();
+
+    let out = doc.to_markdown();
+    let (added, _) = diff(NOTE, &out);
+    assert_eq!(
+        added,
+        [
+            format!("* [ ] Call Sam 🆔 {}", call),
+            format!("1. [ ] Plan trip ⛔ {}", call)
+        ]
+    );
+}
+
+#[test]
+fn repeating_tasks_put_the_next_one_above() {
+    let mut doc = Document::from_markdown("- [ ] Water plants 🔁 every 1 week\n").unwrap();
+    let id = String::from(doc.tasks()[0].id());
+    doc.complete(&id).unwrap();
+
+    let out = doc.to_markdown();
+    let out = lines(&out);
+    assert_eq!(out.len(), 2);
+    // The next one is new, so it's written with an id.
+    assert!(out[0].starts_with("- [ ] Water plants 🆔"), "{}", out[0]);
+    assert!(out[1].starts_with("- [x] Water plants 🔁"), "{}", out[1]);
+}
@@ -0,0 +1,44 @@
+use todo::{Error, Todo, TodoList};
+
+#[test]
+fn bad_values_are_errors() {
+    let mut t = Todo::from_title(String::from("Pay rent"));
+
+    match t.set_due_iso8601(String::from("tomorrowish")) {
+        Err(Error::InvalidDate(e)) => assert_eq!(e, "tomorrowish"),
+        e => panic!("expected an invalid date, got {:?}", e),
+    }
+    match t.set_repeat(String::from("every so often")) {
+        Err(Error::InvalidRepeat(e)) => assert_eq!(e, "every so often"),
+        e => panic!("expected an invalid repeat, got {:?}", e),
+    }
+    match t.set_duration(String::from("a while")) {
+        Err(Error::InvalidDuration(e)) => assert_eq!(e, "a while"),
+        e => panic!("expected an invalid duration, got {:?}", e),
+    }
+}
+
+#[test]
+fn bad_json_is_a_parse_error() {
+    match TodoList::from_json("[{\"title\": ").err() {
+        Some(Error::Parse { line, .. }) => assert_eq!(line, 1),
+        e => panic!("expected a parse error, got {:?}", e),
+    }
+}
+
+#[test]
+fn bad_markdown_is_a_parse_error() {
+    assert!(matches!(
+        TodoList::from_markdown("- [ ] a\nprose"),
+        Err(Error::Parse { .. })
+    ));
+}
+
+#[test]
+fn missing_files_name_the_path() {
+    let path = "/nonexistent/todo.json";
+    match TodoList::from_json_file(path).err() {
+        Some(e @ Error::Io { .. }) => assert!(e.to_string().starts_with(path)),
+        e => panic!("expected an io error, got {:?}", e),
+    }
+}
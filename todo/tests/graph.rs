@@ -0,0 +1,106 @@
+use todo::{Error, Todo, TodoList};
+
+fn task(id: &str, dependencies: &[&str]) -> Todo {
+    let mut t = Todo::from_title(format!("Task {}", id));
+    t.set_id(String::from(id));
+    for d in dependencies {
+        t.add_dependency(String::from(*d));
+    }
+    t
+}
+
+fn list(tasks: Vec<Todo>) -> TodoList {
+    let mut tdl = TodoList::new();
+    for t in tasks {
+        tdl.add(t);
+    }
+    tdl
+}
+
+fn ids(tasks: Vec<&Todo>) -> Vec<&str> {
+    tasks.into_iter().map(|t| t.id()).collect()
+}
+
+/// `d` needs `b` and `c`, which both need `a`.
+fn diamond() -> TodoList {
+    list(vec![
+        task("d", &["b", "c"]),
+        task("c", &["a"]),
+        task("b", &["a"]),
+        task("a", &[]),
+    ])
+}
+
+#[test]
+fn cycles_are_reported_with_their_path() {
+    let tdl = list(vec![
+        task("a", &["b"]),
+        task("b", &["c"]),
+        task("c", &["a"]),
+    ]);
+
+    let cycle = tdl.dependency_graph().find_cycle().unwrap();
+    assert_eq!(cycle, ["a", "b", "c", "a"]);
+    assert_eq!(ids(tdl.find_cycle().unwrap()), ["a", "b", "c", "a"]);
+
+    match tdl.topological_order() {
+        Err(Error::Dependency(e)) => assert!(e.contains("a -> b -> c -> a"), "{}", e),
+        e => panic!("expected a cycle, got {:?}", e.map(ids)),
+    }
+    assert!(diamond().find_cycle().is_none());
+}
+
+#[test]
+fn adding_a_dependency_cant_make_a_cycle() {
+    let mut tdl = list(vec![task("a", &["b"]), task("b", &["c"]), task("c", &[])]);
+
+    assert!(matches!(
+        tdl.add_dependency("c", "a"),
+        Err(Error::Dependency(_))
+    ));
+    assert!(matches!(
+        tdl.add_dependency("a", "a"),
+        Err(Error::Dependency(_))
+    ));
+    assert!(matches!(
+        tdl.add_dependency("a", "z"),
+        Err(Error::NotFound(_))
+    ));
+    assert!(tdl.get_by_id("c").unwrap().dependencies().is_empty());
+    assert!(tdl.find_cycle().is_none());
+
+    tdl.add_dependency("a", "c").unwrap();
+    assert_eq!(tdl.get_by_id("a").unwrap().dependencies(), ["b", "c"]);
+}
+
+#[test]
+fn topological_order_is_deterministic() {
+    let tdl = diamond();
+    for _ in 0..10 {
+        assert_eq!(ids(tdl.topological_order().unwrap()), ["a", "b", "c", "d"]);
+    }
+}
+
+#[test]
+fn blockers_are_transitive() {
+    let tdl = diamond();
+    assert_eq!(ids(tdl.blocked_by("d")), ["c", "b", "a"]);
+    assert_eq!(ids(tdl.blocks("a")), ["d", "c", "b"]);
+    assert!(tdl.blocked_by("a").is_empty());
+}
+
+#[test]
+fn ready_tasks_have_no_open_dependencies() {
+    let mut tdl = diamond();
+    assert_eq!(ids(tdl.ready()), ["a"]);
+
+    tdl.complete("a").unwrap();
+    assert_eq!(ids(tdl.ready()), ["c", "b"]);
+
+    tdl.complete("b").unwrap();
+    assert_eq!(ids(tdl.ready()), ["c"]);
+    assert!(matches!(tdl.complete("d"), Err(Error::Dependency(_))));
+
+    tdl.complete("c").unwrap();
+    assert_eq!(ids(tdl.ready()), ["d"]);
+}
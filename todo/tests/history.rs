@@ -0,0 +1,218 @@
+use todo::{Error, History, Todo, TodoList};
+
+fn task(id: &str) -> Todo {
+    let mut t = Todo::from_title(id.to_uppercase());
+    t.set_id(String::from(id));
+    t
+}
+
+/// a, b with sub tasks b1 and b2, then c.
+fn list() -> TodoList {
+    let mut tdl = TodoList::new();
+    tdl.add(task("a"));
+    let mut b = task("b");
+    b.get_sub_tasks().add(task("b1"));
+    b.get_sub_tasks().add(task("b2"));
+    tdl.add(b);
+    tdl.add(task("c"));
+    tdl
+}
+
+fn json(tdl: &TodoList) -> String {
+    tdl.to_json().unwrap()
+}
+
+/// Record a change, then check undoing and redoing it gives back exactly
+/// the list before and after.
+fn check(f: impl FnOnce(&mut TodoList) -> todo::Result<()>) {
+    let mut tdl = list();
+    let mut h = History::new(10);
+    let before = json(&tdl);
+    h.record(&mut tdl, "change", f).unwrap();
+    let after = json(&tdl);
+    assert_ne!(before, after);
+
+    assert_eq!(h.undo(&mut tdl).unwrap().unwrap().label(), "change");
+    assert_eq!(json(&tdl), before);
+    assert!(!h.can_undo());
+    assert_eq!(h.redo(&mut tdl).unwrap().unwrap().label(), "change");
+    assert_eq!(json(&tdl), after);
+    assert!(!h.can_redo());
+}
+
+#[test]
+fn added_tasks_are_undone() {
+    check(|tdl| {
+        tdl.insert(1, task("d"));
+        Ok(())
+    });
+    check(|tdl| {
+        let mut e = task("e");
+        e.get_sub_tasks().add(task("e1"));
+        tdl.get_by_id_mut("b").unwrap().get_sub_tasks().insert(1, e);
+        Ok(())
+    });
+}
+
+#[test]
+fn edited_tasks_are_undone() {
+    check(|tdl| {
+        tdl.get_by_id_mut("b1")
+            .unwrap()
+            .set_title(String::from("B one"))
+            .unwrap();
+        tdl.get_by_id_mut("c")
+            .unwrap()
+            .add_tag(String::from("home"))
+            .unwrap();
+        Ok(())
+    });
+    check(|tdl| tdl.complete("a").map(|_| ()));
+}
+
+#[test]
+fn removed_tasks_are_undone() {
+    check(|tdl| {
+        tdl.remove_by_id("b").unwrap();
+        Ok(())
+    });
+    check(|tdl| {
+        tdl.remove_by_id("b2").unwrap();
+        tdl.remove(0);
+        Ok(())
+    });
+}
+
+#[test]
+fn reordered_tasks_are_undone() {
+    check(|tdl| {
+        let c = tdl.remove(2);
+        tdl.insert(0, c);
+        Ok(())
+    });
+    check(|tdl| {
+        let b = tdl.get_by_id_mut("b").unwrap().get_sub_tasks();
+        let b2 = b.remove(1);
+        b.insert(0, b2);
+        Ok(())
+    });
+    // Moving a task under another one.
+    check(|tdl| {
+        let a = tdl.remove(0);
+        tdl.get_by_id_mut("c").unwrap().get_sub_tasks().add(a);
+        Ok(())
+    });
+}
+
+#[test]
+fn failed_changes_arent_recorded() {
+    let mut tdl = list();
+    let mut h = History::new(10);
+    let before = json(&tdl);
+    let res = h.record(&mut tdl, "fail", |tdl| {
+        tdl.remove(0);
+        Err::<(), _>(Error::NotFound(String::from("x")))
+    });
+    assert!(res.is_err());
+    assert_eq!(json(&tdl), before);
+    assert!(!h.can_undo());
+
+    assert!(!h.push("nothing", &tdl, &tdl.clone()).unwrap());
+    assert!(h.undo(&mut tdl).unwrap().is_none());
+    assert!(h.redo(&mut tdl).unwrap().is_none());
+}
+
+#[test]
+fn new_changes_drop_the_redo_steps() {
+    let mut tdl = list();
+    let mut h = History::new(10);
+    h.record(&mut tdl, "add d", |tdl| {
+        tdl.add(task("d"));
+        Ok(())
+    })
+    .unwrap();
+    h.undo(&mut tdl).unwrap();
+    assert!(h.can_redo());
+
+    h.record(&mut tdl, "add e", |tdl| {
+        tdl.add(task("e"));
+        Ok(())
+    })
+    .unwrap();
+    assert!(!h.can_redo());
+    assert!(h.redo(&mut tdl).unwrap().is_none());
+    assert!(tdl.get_by_id("d").is_none());
+}
+
+#[test]
+fn only_the_latest_steps_are_kept() {
+    let mut tdl = TodoList::new();
+    let mut h = History::new(2);
+    for id in ["a", "b", "c"] {
+        h.record(&mut tdl, id, |tdl| {
+            tdl.add(task(id));
+            Ok(())
+        })
+        .unwrap();
+    }
+    let labels: Vec<_> = h.undo_steps().map(|x| x.label()).collect();
+    assert_eq!(labels, vec!["c", "b"]);
+
+    h.undo(&mut tdl).unwrap();
+    h.undo(&mut tdl).unwrap();
+    assert!(h.undo(&mut tdl).unwrap().is_none());
+    assert_eq!(tdl.len(), 1);
+    let labels: Vec<_> = h.redo_steps().map(|x| x.label()).collect();
+    assert_eq!(labels, vec!["b", "c"]);
+}
+
+#[test]
+fn changes_made_since_are_conflicts() {
+    let mut tdl = list();
+    let mut h = History::new(10);
+    h.record(&mut tdl, "remove a", |tdl| {
+        tdl.remove_by_id("a").unwrap();
+        Ok(())
+    })
+    .unwrap();
+
+    // Changed without being recorded.
+    tdl.get_by_id_mut("c")
+        .unwrap()
+        .set_title(String::from("Changed"))
+        .unwrap();
+    let before = json(&tdl);
+    match h.undo(&mut tdl).err() {
+        Some(Error::Conflict(s)) => assert!(s.contains("remove a"), "{}", s),
+        _ => panic!("expected a conflict"),
+    }
+    assert_eq!(json(&tdl), before);
+    assert!(h.can_undo());
+}
+
+#[test]
+fn saved_histories_can_be_undone() {
+    let mut tdl = list();
+    let mut h = History::new(10);
+    let before = json(&tdl);
+    h.record(&mut tdl, "remove b", |tdl| {
+        tdl.remove_by_id("b").unwrap();
+        Ok(())
+    })
+    .unwrap();
+
+    let s = h.to_json().unwrap();
+    let mut h = History::from_json(&s).unwrap();
+    assert_eq!(h.to_json().unwrap(), s);
+    h.undo(&mut tdl).unwrap();
+    assert_eq!(json(&tdl), before);
+
+    let file = std::env::temp_dir().join(format!("todo-history-{}.json", std::process::id()));
+    let file = file.to_str().unwrap();
+    assert!(!History::from_file(file, 10).unwrap().can_undo());
+    h.to_file(file).unwrap();
+    let mut h = History::from_file(file, 10).unwrap();
+    assert!(h.redo(&mut tdl).unwrap().is_some());
+    assert!(tdl.get_by_id("b").is_none());
+    std::fs::remove_file(file).unwrap();
+}
@@ -0,0 +1,72 @@
+use todo::{Todo, TodoList};
+
+fn list() -> TodoList {
+    let mut tdl = TodoList::new();
+    let mut t = Todo::from_title(String::from("Plan trip"));
+    t.get_sub_tasks()
+        .add(Todo::from_title(String::from("Book flights")));
+    tdl.add(t);
+    tdl.add(Todo::from_title(String::from("Pack")));
+    tdl
+}
+
+#[test]
+fn ids_are_unique() {
+    let ids: Vec<String> = (0..100)
+        .map(|_| String::from(Todo::from_title(String::from("a")).id()))
+        .collect();
+    for (i, e) in ids.iter().enumerate() {
+        assert!(!ids[i + 1..].contains(e), "{} repeated", e);
+    }
+}
+
+#[test]
+fn ids_survive_json() {
+    let tdl = list();
+    let back = TodoList::from_json(&tdl.to_json().unwrap()).unwrap();
+    assert_eq!(back[0].id(), tdl[0].id());
+    assert_eq!(back[1].id(), tdl[1].id());
+}
+
+#[test]
+fn ids_survive_markdown() {
+    let tdl = list();
+    let back = TodoList::from_markdown(&tdl.to_markdown()).unwrap();
+    assert_eq!(back[0].id(), tdl[0].id());
+    assert_eq!(back[1].id(), tdl[1].id());
+}
+
+#[test]
+fn json_without_ids_gets_them() {
+    let mut json: serde_json::Value = serde_json::from_str(&list().to_json().unwrap()).unwrap();
+    for t in json["items"].as_array_mut().unwrap() {
+        t.as_object_mut().unwrap().remove("id");
+    }
+
+    let back = TodoList::from_json(&json.to_string()).unwrap();
+    assert_eq!(back[0].id().len(), 6);
+    assert_ne!(back[0].id(), back[1].id());
+}
+
+#[test]
+fn lookups_find_nested_tasks() {
+    let mut tdl = list();
+    let id = String::from(tdl[0].get_sub_tasks()[0].id());
+
+    assert!(tdl
+        .get_by_id(&id)
+        .unwrap()
+        .to_string()
+        .contains("Book flights"));
+    tdl.get_by_id_mut(&id)
+        .unwrap()
+        .set_title(String::from("Book trains"))
+        .unwrap();
+    assert!(tdl[0].get_sub_tasks()[0]
+        .to_string()
+        .contains("Book trains"));
+    assert!(tdl.get_by_id("nope").is_none());
+
+    assert_eq!(tdl.position(&String::from(tdl[1].id())), Some(1));
+    assert_eq!(tdl.position(&id), None);
+}
@@ -0,0 +1,227 @@
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use todo::structs::Changes;
+use todo::{Error, Query, Vault};
+
+/// A vault in a fresh temp directory, removed when dropped.
+struct Dir(PathBuf);
+
+impl Dir {
+    fn new(name: &str) -> Dir {
+        let dir = std::env::temp_dir().join(format!("todo-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Dir(dir)
+    }
+
+    fn write(&self, path: &str, content: &str) {
+        let path = self.0.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for Dir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn vault(name: &str) -> Dir {
+    let dir = Dir::new(name);
+    dir.write(
+        "Inbox.md",
+        "# Inbox\n- [ ] Buy milk #home\n- [x] Pay rent\n",
+    );
+    dir.write(
+        "Projects/Garden.md",
+        "Notes\n## Spring\n- [ ] Plant beans 🆔 beans1\n\t- [ ] Dig bed\n",
+    );
+    dir.write(
+        "Projects/Deep/Kitchen.md",
+        "- [ ] Paint walls ⛔ beans1 #home\n",
+    );
+    dir.write("Projects/readme.txt", "- [ ] not a note\n");
+    dir.write(".obsidian/templates.md", "- [ ] hidden\n");
+    dir.write(".trash/old.md", "- [ ] hidden\n");
+    dir.write("Projects/.draft.md", "- [ ] hidden\n");
+    dir
+}
+
+#[test]
+fn every_note_is_indexed() {
+    let dir = vault("vault-open");
+    let v = Vault::open(dir.path()).unwrap();
+
+    let paths: Vec<&str> = v.files().map(|(p, _)| p).collect();
+    assert_eq!(
+        paths,
+        ["Inbox.md", "Projects/Deep/Kitchen.md", "Projects/Garden.md"]
+    );
+    assert_eq!(v.len(), 3);
+    assert!(v.errors().is_empty());
+    assert_eq!(v.walk().count(), 5);
+    assert_eq!(v.get("Inbox.md").unwrap().len(), 2);
+    assert_eq!(v.path("Inbox.md"), dir.0.join("Inbox.md"));
+}
+
+#[test]
+fn tasks_know_their_note() {
+    let dir = vault("vault-source");
+    let v = Vault::open(dir.path()).unwrap();
+
+    let dig = v.walk().find(|t| t.title() == "Dig bed").unwrap();
+    let source = dig.source().unwrap();
+    assert_eq!(source.file.as_deref(), Some("Projects/Garden.md"));
+    assert_eq!(source.line, 4);
+    assert_eq!(source.headings, ["Spring"]);
+    assert_eq!(v.get_by_id("beans1").unwrap().title(), "Plant beans");
+}
+
+#[test]
+fn queries_span_notes() {
+    let dir = vault("vault-query");
+    let v = Vault::open(dir.path()).unwrap();
+
+    let q = Query::from_str("tag:home open").unwrap();
+    let found: Vec<&str> = v.query(&q, true).iter().map(|t| t.title()).collect();
+    assert_eq!(found, ["Buy milk", "Paint walls"]);
+    let spring: Vec<&str> = v.section("spring").iter().map(|t| t.title()).collect();
+    assert_eq!(spring, ["Plant beans", "Dig bed"]);
+
+    // Dependencies are followed between notes.
+    let merged = v.merged();
+    let paint = merged.iter().find(|t| t.title() == "Paint walls").unwrap();
+    let blockers: Vec<&str> = merged
+        .blocked_by(paint.id())
+        .iter()
+        .map(|t| t.title())
+        .collect();
+    assert_eq!(blockers, ["Plant beans"]);
+}
+
+#[test]
+fn bad_notes_dont_fail_the_vault() {
+    let dir = vault("vault-errors");
+    dir.write("Broken.md", "- [ ] Due someday 📅 whenever\n");
+    let mut v = Vault::open(dir.path()).unwrap();
+
+    assert_eq!(v.len(), 3);
+    assert_eq!(v.errors().len(), 1);
+    assert_eq!(v.errors()[0].0, "Broken.md");
+    assert!(matches!(v.errors()[0].1, Error::Parse { line: 1, .. }));
+
+    dir.write("Broken.md", "- [ ] Due soon 📅 2026-10-20\n");
+    v.reload("Broken.md").unwrap();
+    assert!(v.errors().is_empty());
+    assert_eq!(v.len(), 4);
+
+    assert!(Vault::open("/nonexistent/vault").is_err());
+}
+
+#[test]
+fn notes_can_be_changed_in_memory() {
+    let mut v = Vault::new("/nonexistent");
+    assert!(v.is_empty());
+    v.insert("a.md", "- [ ] one\n- [ ] two\n").unwrap();
+    v.insert("a.md", "- [ ] three\n").unwrap();
+    assert_eq!(v.get("a.md").unwrap()[0].title(), "three");
+    assert!(v.insert("b.md", "- [ ] 📅 never").is_err());
+    assert!(v.get("b.md").is_none());
+
+    assert_eq!(v.remove("a.md").unwrap().len(), 1);
+    assert!(v.is_empty());
+}
+
+/// Retitle the first task of a note in memory only, so it's possible to
+/// tell if the note was parsed again.
+fn mark(v: &mut Vault, path: &str) {
+    v.get_mut(path).unwrap()[0]
+        .set_title(String::from("marked"))
+        .unwrap();
+}
+
+fn marked(v: &Vault, path: &str) -> bool {
+    v.get(path).unwrap()[0].title() == "marked"
+}
+
+#[test]
+fn refresh_reports_what_changed() {
+    let dir = vault("vault-refresh");
+    let mut v = Vault::open(dir.path()).unwrap();
+    assert!(v.refresh().unwrap().is_empty());
+    mark(&mut v, "Inbox.md");
+
+    dir.write("Projects/Garden.md", "## Spring\n- [ ] Plant peas\n");
+    dir.write("New.md", "- [ ] New task\n");
+    fs::remove_file(dir.0.join("Projects/Deep/Kitchen.md")).unwrap();
+
+    let changes = v.refresh().unwrap();
+    assert_eq!(
+        changes,
+        Changes {
+            added: vec![String::from("New.md")],
+            modified: vec![String::from("Projects/Garden.md")],
+            removed: vec![String::from("Projects/Deep/Kitchen.md")],
+        }
+    );
+    assert_eq!(
+        v.get("Projects/Garden.md").unwrap()[0].title(),
+        "Plant peas"
+    );
+    assert!(marked(&v, "Inbox.md"));
+}
+
+#[test]
+fn touched_notes_with_the_same_content_arent_changes() {
+    let dir = vault("vault-touch");
+    let mut v = Vault::open(dir.path()).unwrap();
+    mark(&mut v, "Inbox.md");
+
+    let content = fs::read_to_string(dir.0.join("Inbox.md")).unwrap();
+    fs::remove_file(dir.0.join("Inbox.md")).unwrap();
+    dir.write("Inbox.md", &content);
+    assert!(v.refresh().unwrap().is_empty());
+    assert!(marked(&v, "Inbox.md"));
+
+    assert!(!v.insert("Inbox.md", &content).unwrap());
+    assert!(v.insert("Inbox.md", "- [ ] Other\n").unwrap());
+}
+
+#[test]
+fn saved_indexes_skip_unchanged_notes() {
+    let dir = vault("vault-index");
+    let index = dir.0.join(".index.json");
+    let index = index.to_str().unwrap();
+
+    let mut v = Vault::open_with_index(dir.path(), index).unwrap();
+    mark(&mut v, "Inbox.md");
+    mark(&mut v, "Projects/Garden.md");
+    v.save_index(index).unwrap();
+
+    dir.write(
+        "Inbox.md",
+        "# Inbox\n- [ ] Buy oat milk #home\n- [x] Pay rent\n",
+    );
+    let v = Vault::open_with_index(dir.path(), index).unwrap();
+    assert!(marked(&v, "Projects/Garden.md"));
+    assert_eq!(v.get("Inbox.md").unwrap()[0].title(), "Buy oat milk");
+
+    // A broken index is only a cache, every note is read again.
+    fs::write(index, "{not json").unwrap();
+    let v = Vault::open_with_index(dir.path(), index).unwrap();
+    assert_eq!(v.len(), 3);
+    assert!(!marked(&v, "Projects/Garden.md"));
+}
+
+#[test]
+fn content_hashes_are_stable() {
+    assert_eq!(Vault::content_hash(""), "cbf29ce484222325");
+    assert_eq!(Vault::content_hash("a"), "af63dc4c8601ec8c");
+    assert_ne!(Vault::content_hash("ab"), Vault::content_hash("ba"));
+}
@@ -0,0 +1,171 @@
+use std::fs::{self, OpenOptions};
+use std::io::prelude::*;
+use std::path::PathBuf;
+use todo::{Journal, Todo, TodoList};
+
+struct Dir(PathBuf);
+
+impl Dir {
+    fn new(name: &str) -> Dir {
+        let dir = std::env::temp_dir().join(format!("todo-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Dir(dir)
+    }
+
+    fn file(&self) -> String {
+        self.0.join("todo.jsonl").to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for Dir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn task(id: &str) -> Todo {
+    let mut t = Todo::from_title(id.to_uppercase());
+    t.set_id(String::from(id));
+    t
+}
+
+fn json(tdl: &TodoList) -> String {
+    tdl.to_json().unwrap()
+}
+
+/// What every event in the journal was.
+fn events(file: &str) -> Vec<String> {
+    Journal::entries(file)
+        .unwrap()
+        .iter()
+        .map(|x| x.event().to_string())
+        .collect()
+}
+
+/// Make every kind of change to a journal, saving after each.
+fn history(j: &mut Journal) {
+    let tdl = j.tasks_mut();
+    tdl.add(task("a"));
+    let mut b = task("b");
+    b.get_sub_tasks().add(task("b1"));
+    tdl.add(b);
+    let mut w = task("water");
+    w.set_due_iso8601(String::from("2026-10-18 09:00:00"))
+        .unwrap();
+    w.set_repeat(String::from("1 week")).unwrap();
+    tdl.add(w);
+    assert_eq!(j.save().unwrap(), 4);
+
+    j.tasks_mut()
+        .get_by_id_mut("a")
+        .unwrap()
+        .set_title(String::from("Renamed"))
+        .unwrap();
+    assert_eq!(j.save().unwrap(), 1);
+
+    let tdl = j.tasks_mut();
+    tdl.complete("b1").unwrap();
+    let next = tdl.complete("water").unwrap().unwrap();
+    tdl.insert_before("water", next).unwrap();
+    j.save().unwrap();
+
+    let tdl = j.tasks_mut();
+    let a = tdl.remove_by_id("a").unwrap();
+    tdl.get_by_id_mut("b").unwrap().get_sub_tasks().add(a);
+    tdl.remove_by_id("b1").unwrap();
+    j.save().unwrap();
+}
+
+#[test]
+fn replaying_the_journal_gives_back_the_tasks() {
+    let dir = Dir::new("journal-replay");
+    let file = dir.file();
+    let mut j = Journal::open(&file).unwrap();
+    assert!(j.tasks().is_empty());
+    history(&mut j);
+    assert_eq!(j.save().unwrap(), 0);
+
+    let events = events(&file);
+    let next = j
+        .tasks()
+        .iter()
+        .find(|x| x.is_open() && x.title() == "WATER");
+    let next = next.unwrap().id();
+    assert_eq!(
+        events,
+        vec![
+            String::from("created a A"),
+            String::from("created b B"),
+            String::from("created water WATER"),
+            String::from("created b1 B1"),
+            String::from("edited a title = \"Renamed\""),
+            format!("repeated water as {}", next),
+            String::from("completed water"),
+            String::from("completed b1"),
+            String::from("moved a"),
+            String::from("deleted b1"),
+        ]
+    );
+
+    let reopened = Journal::open(&file).unwrap();
+    assert_eq!(json(reopened.tasks()), json(j.tasks()));
+}
+
+#[test]
+fn snapshots_and_later_events_match_a_full_replay() {
+    let dir = Dir::new("journal-snapshot");
+    let file = dir.file();
+    let mut j = Journal::open(&file).unwrap();
+    j.tasks_mut().add(task("x"));
+    j.tasks_mut().add(task("y"));
+    j.save().unwrap();
+    assert_eq!(j.pending(), 2);
+    j.compact().unwrap();
+    assert_eq!(j.pending(), 0);
+
+    history(&mut j);
+    j.tasks_mut().remove_by_id("x").unwrap();
+    j.save().unwrap();
+    let expected = json(j.tasks());
+
+    let from_snapshot = Journal::open(&file).unwrap();
+    assert_eq!(json(from_snapshot.tasks()), expected);
+    assert_eq!(from_snapshot.pending(), events(&file).len() - 2);
+
+    // Without the snapshot every event is replayed.
+    fs::remove_file(Journal::snapshot_path(&file)).unwrap();
+    let full = Journal::open(&file).unwrap();
+    assert_eq!(json(full.tasks()), expected);
+}
+
+#[test]
+fn a_cut_off_last_line_is_ignored() {
+    let dir = Dir::new("journal-truncated");
+    let file = dir.file();
+    let mut j = Journal::open(&file).unwrap();
+    j.tasks_mut().add(task("a"));
+    j.tasks_mut().add(task("b"));
+    j.save().unwrap();
+    let expected = json(j.tasks());
+
+    // A crash halfway through appending the next event.
+    let mut f = OpenOptions::new().append(true).open(&file).unwrap();
+    f.write_all(b"{\"time\":\"2026-10-18T12:00:00\",\"event\":\"del")
+        .unwrap();
+    drop(f);
+
+    let mut j = Journal::open(&file).unwrap();
+    assert_eq!(json(j.tasks()), expected);
+    assert_eq!(events(&file).len(), 2);
+
+    // The next save writes over the partial line.
+    j.tasks_mut().remove_by_id("a").unwrap();
+    assert_eq!(j.save().unwrap(), 1);
+    let s = fs::read_to_string(&file).unwrap();
+    assert!(s.ends_with("\"id\":\"a\"}\n"), "{}", s);
+    assert_eq!(events(&file).last().unwrap(), "deleted a");
+    let j = Journal::open(&file).unwrap();
+    assert_eq!(j.tasks().len(), 1);
+    assert!(j.tasks().get_by_id("b").is_some());
+}
@@ -0,0 +1,37 @@
+use todo::TodoList;
+
+/// A list saved by the first version, which nested dependencies as tasks.
+const BASELINE: &str = include_str!("data/baseline.json");
+
+#[test]
+fn loads_baseline_json() {
+    let tdl = TodoList::from_json(BASELINE).unwrap();
+    let titles: Vec<&str> = tdl.iter().map(|x| x.title()).collect();
+    assert_eq!(titles, ["Collect numbers", "Write report", "Water plants"]);
+
+    let report = &tdl[1];
+    assert_eq!(report.dependencies(), [tdl[0].id()]);
+    assert_eq!(report.tags(), ["work"]);
+    assert_eq!(report.sub_tasks().len(), 1);
+    assert_eq!(tdl[0].sub_tasks()[0].title(), "Ask finance");
+    assert!(!tdl[2].is_open());
+}
+
+#[test]
+fn blocks_on_lifted_dependencies() {
+    let mut tdl = TodoList::from_json(BASELINE).unwrap();
+    let report = String::from(tdl[1].id());
+    assert!(tdl.complete(&report).is_err());
+
+    let numbers = String::from(tdl[0].id());
+    tdl.complete(&numbers).unwrap();
+    tdl.complete(&report).unwrap();
+}
+
+#[test]
+fn saves_dependencies_as_ids() {
+    let tdl = TodoList::from_json(BASELINE).unwrap();
+    let again = TodoList::from_json(&tdl.to_json().unwrap()).unwrap();
+    assert_eq!(again.len(), 3);
+    assert_eq!(again[1].dependencies(), [tdl[0].id()]);
+}
@@ -0,0 +1,177 @@
+use proptest::prelude::*;
+use todo::{Date, Priority, Status, Todo};
+
+const REPEATS: [&str; 10] = [
+    "every day",
+    "every 2 weeks",
+    "every weekday",
+    "every month on the 15th",
+    "every 2nd tuesday",
+    "every month on the last weekday for 6 times",
+    "every week when done",
+    "RRULE:FREQ=MONTHLY;BYDAY=TU;BYSETPOS=2",
+    "3 days from completed",
+    "0 0 9 * * Mon *",
+];
+
+/// Text with the characters most likely to upset the parser.
+fn text() -> impl Strategy<Value = String> {
+    let c = prop_oneof![
+        8 => prop::char::range(' ', '~'),
+        1 => prop::sample::select(vec!['⛔', '⏫', '📅', '✈', '\u{fe0f}', '❤', '\n', '\t', '#']),
+        1 => any::<char>(),
+    ];
+    prop::collection::vec(c, 0..24).prop_map(|x| x.into_iter().collect())
+}
+
+/// A day, and a time on it for some.
+type Moment = ((i32, u32, u32), Option<(u32, u32, u32)>);
+
+fn moment() -> impl Strategy<Value = Moment> {
+    let day = (2000..2100i32, 1..=12u32, 1..=28u32);
+    (day, prop::option::of((0..24u32, 0..60u32, 0..60u32)))
+}
+
+fn date(((y, m, d), time): &Moment) -> Date {
+    let day = chrono::NaiveDate::from_ymd_opt(*y, *m, *d).unwrap();
+    match time {
+        Some((h, mi, s)) => Date::floating(day.and_hms_opt(*h, *mi, *s).unwrap()),
+        None => Date::Day(day),
+    }
+}
+
+fn status() -> impl Strategy<Value = Status> {
+    prop_oneof![
+        Just(Status::Open),
+        Just(Status::InProgress),
+        Just(Status::Done),
+        Just(Status::Cancelled),
+        prop::sample::select(vec!['>', '?', '!', '*']).prop_map(Status::Custom),
+    ]
+}
+
+fn priority() -> impl Strategy<Value = Priority> {
+    prop::sample::select(vec![
+        Priority::Lowest,
+        Priority::Low,
+        Priority::Normal,
+        Priority::Medium,
+        Priority::High,
+        Priority::Highest,
+    ])
+}
+
+/// What to build a task from, `Todo` isn't `Debug` so it can't be shrunk
+/// directly.
+#[derive(Clone, Debug)]
+struct Spec {
+    title: String,
+    tags: Vec<String>,
+    status: Status,
+    priority: Priority,
+    dates: Vec<Option<Moment>>,
+    stamp: Moment,
+    duration: Option<u32>,
+    repeat: Option<&'static str>,
+    dependencies: Vec<String>,
+}
+
+prop_compose! {
+    fn spec()(
+        title in text(),
+        tags in prop::collection::vec(text(), 0..3),
+        status in status(),
+        priority in priority(),
+        dates in prop::collection::vec(prop::option::of(moment()), 4),
+        stamp in moment(),
+        duration in prop::option::of(1..10_000u32),
+        repeat in prop::option::of(prop::sample::select(REPEATS.to_vec())),
+        dependencies in prop::collection::vec("[a-z0-9]{6}", 0..3),
+    ) -> Spec {
+        Spec { title, tags, status, priority, dates, stamp, duration, repeat, dependencies }
+    }
+}
+
+/// The task, None if the title doesn't read back.
+fn build(spec: &Spec) -> Option<Todo> {
+    let mut t = Todo::from_title(String::new());
+    t.set_title(spec.title.clone()).ok()?;
+    for e in &spec.tags {
+        // Tags that wouldn't read back are rejected.
+        let _ = t.add_tag(e.clone());
+    }
+    let day = date(&spec.stamp).date().to_string();
+    match spec.status {
+        Status::Done => t.set_completed_iso8601(day).unwrap(),
+        Status::Cancelled => t.set_cancelled_iso8601(day).unwrap(),
+        _ => (),
+    }
+    t.set_status(spec.status);
+    t.set_priority(spec.priority);
+    let dates: Vec<Option<Date>> = spec.dates.iter().map(|x| x.as_ref().map(date)).collect();
+    if let Some(e) = &dates[0] {
+        t.set_due(e.clone());
+    }
+    if let Some(e) = &dates[1] {
+        t.set_start(e.clone());
+    }
+    if let Some(e) = &dates[2] {
+        t.set_scheduled(e.clone());
+    }
+    if let Some(e) = &dates[3] {
+        t.set_created_iso8601(e.date().to_string()).unwrap();
+    }
+    if let Some(m) = spec.duration {
+        t.set_duration(format!("{}min", m)).unwrap();
+    }
+    if let Some(e) = spec.repeat {
+        t.set_repeat(String::from(e)).unwrap();
+    }
+    for e in &spec.dependencies {
+        t.add_dependency(e.clone());
+    }
+    Some(t)
+}
+
+fn json(t: &Todo) -> serde_json::Value {
+    serde_json::to_value(t).unwrap()
+}
+
+proptest! {
+    #[test]
+    fn round_trips(spec in spec(), sub_tasks in prop::collection::vec(spec(), 0..3)) {
+        let mut t = match build(&spec) {
+            Some(e) => e,
+            None => return Ok(()),
+        };
+        for e in sub_tasks.iter().filter_map(build) {
+            t.get_sub_tasks().add(e);
+        }
+        let markdown = t.to_markdown();
+        let mut read = Todo::from_markdown(&markdown).unwrap();
+        read.set_source(None);
+        for e in read.get_sub_tasks().iter_mut() {
+            e.set_source(None);
+        }
+        prop_assert_eq!(json(&read), json(&t), "{}", markdown);
+    }
+}
+
+#[test]
+fn rejects_titles_that_dont_read_back() {
+    let mut t = Todo::from_title(String::new());
+    for title in ["Fix ⛔ sign", "Ship v2 ⏫ soon", "Two\nlines", "Fix #bug"] {
+        assert!(t.set_title(String::from(title)).is_err(), "{}", title);
+    }
+    t.set_title(String::from("Fix  issue #12 ❤️")).unwrap();
+    assert_eq!(t.title(), "Fix issue #12 ❤️");
+}
+
+#[test]
+fn rejects_tags_that_dont_read_back() {
+    let mut t = Todo::from_title(String::new());
+    for tag in ["two words", "12", "", "⛔"] {
+        assert!(t.add_tag(String::from(tag)).is_err(), "{:?}", tag);
+    }
+    t.add_tag(String::from("q3")).unwrap();
+}
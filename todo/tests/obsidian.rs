@@ -0,0 +1,90 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use todo::{Dialect, Todo, TodoList};
+
+fn at(d: u32, h: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2026, 10, d)
+        .unwrap()
+        .and_hms_opt(h, 0, 0)
+        .unwrap()
+}
+
+type Set = fn(&mut Todo, String) -> todo::Result<()>;
+type Get = fn(&Todo) -> Option<NaiveDateTime>;
+
+fn parse(s: &str, dialect: Dialect) -> Todo {
+    let mut tdl = TodoList::from_markdown_with(s, dialect).unwrap();
+    assert_eq!(tdl.len(), 1);
+    tdl.remove(0)
+}
+
+#[test]
+fn every_date_emoji_round_trips() {
+    // Stamps of when something happened are written as days, like Obsidian
+    // Tasks does, the dates a task is planned for keep their time.
+    let dates: [(char, Set, Get, u32); 6] = [
+        ('➕', Todo::set_created_iso8601, Todo::created, 0),
+        ('🛫', Todo::set_start_iso8601, Todo::start, 9),
+        ('⏳', Todo::set_scheduled_iso8601, Todo::scheduled, 9),
+        ('📅', Todo::set_due_iso8601, Todo::due, 9),
+        ('❌', Todo::set_cancelled_iso8601, Todo::cancelled, 0),
+        ('✅', Todo::set_completed_iso8601, Todo::completed, 0),
+    ];
+    for (i, (sym, set, get, hour)) in dates.iter().enumerate() {
+        let mut t = Todo::from_title(String::from("Water plants"));
+        set(&mut t, format!("2026-10-{} 09:00:00", 10 + i)).unwrap();
+
+        let md = t.to_markdown();
+        assert!(md.contains(*sym), "{}", md);
+        for dialect in [Dialect::Tasks, Dialect::Compat] {
+            let back = parse(&md, dialect);
+            assert_eq!(get(&back), Some(at(10 + i as u32, *hour)), "{}", md);
+            assert_eq!(back.title(), "Water plants");
+        }
+    }
+}
+
+#[test]
+fn all_dates_together_round_trip() {
+    let md = "- [x] Water plants ➕ 2026-10-10 09:00:00 🛫 2026-10-11 09:00:00 \
+              ⏳ 2026-10-12 09:00:00 📅 2026-10-13 09:00:00 ✅ 2026-10-14 09:00:00";
+    let t = parse(md, Dialect::Tasks);
+    assert_eq!(t.created(), Some(at(10, 9)));
+    assert_eq!(t.start(), Some(at(11, 9)));
+    assert_eq!(t.scheduled(), Some(at(12, 9)));
+    assert_eq!(t.due(), Some(at(13, 9)));
+    assert_eq!(t.completed(), Some(at(14, 9)));
+
+    let back = parse(&t.to_markdown(), Dialect::Tasks);
+    assert_eq!(back.to_markdown(), t.to_markdown());
+}
+
+#[test]
+fn cancelled_tasks_use_a_dash() {
+    let mut t = Todo::from_title(String::from("Water plants"));
+    t.cancel();
+    assert!(t.is_cancelled());
+    let md = t.to_markdown();
+    assert!(md.starts_with("- [-] Water plants"), "{}", md);
+
+    let back = parse(&md, Dialect::Tasks);
+    assert!(back.is_cancelled());
+    assert!(!back.is_completed());
+    assert_eq!(back.title(), "Water plants");
+}
+
+#[test]
+fn compat_reads_the_old_start_marker() {
+    let md = "- [ ] Water plants ✈️ 2026-10-11 09:00:00 📅 2026-10-13 09:00:00";
+
+    let t = parse(md, Dialect::Compat);
+    assert_eq!(t.start(), Some(at(11, 9)));
+    assert_eq!(t.due(), Some(at(13, 9)));
+    assert_eq!(t.title(), "Water plants");
+    // The old marker is never written back.
+    assert!(!t.to_markdown().contains('✈'));
+    assert!(t.to_markdown().contains("🛫"));
+
+    let t = parse(md, Dialect::Tasks);
+    assert_eq!(t.start(), None);
+    assert!(t.title().contains('✈'), "{}", t.title());
+}
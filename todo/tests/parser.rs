@@ -0,0 +1,143 @@
+use todo::{Error, Status, Todo, TodoList};
+
+fn titles(tdl: &TodoList) -> Vec<(Vec<usize>, String)> {
+    tdl.walk()
+        .map(|(p, t)| (p, String::from(t.title())))
+        .collect()
+}
+
+fn path(p: &[usize], title: &str) -> (Vec<usize>, String) {
+    (Vec::from(p), String::from(title))
+}
+
+fn error_at(s: &str) -> (usize, usize) {
+    match TodoList::from_markdown(s).err() {
+        Some(Error::Parse { line, column, .. }) => (line, column),
+        e => panic!("expected a parse error, got {:?}", e),
+    }
+}
+
+#[test]
+fn nesting_follows_indentation() {
+    for indent in ["\t", "  ", "    "] {
+        let md = "- [ ] a\n\
+                  {i}- [ ] b\n\
+                  {i}{i}- [ ] c\n\
+                  {i}{i}{i}- [ ] d\n\
+                  {i}- [ ] e\n\
+                  - [ ] f"
+            .replace("{i}", indent);
+        let tdl = TodoList::from_markdown(&md).unwrap();
+        assert_eq!(
+            titles(&tdl),
+            [
+                path(&[0], "a"),
+                path(&[0, 0], "b"),
+                path(&[0, 0, 0], "c"),
+                path(&[0, 0, 0, 0], "d"),
+                path(&[0, 1], "e"),
+                path(&[1], "f"),
+            ],
+            "{:?}",
+            indent
+        );
+    }
+}
+
+#[test]
+fn tabs_and_spaces_mix() {
+    let md = "- [ ] a\n    - [ ] b\n\t- [ ] c\n\t  - [ ] d";
+    let tdl = TodoList::from_markdown(md).unwrap();
+    assert_eq!(
+        titles(&tdl),
+        [
+            path(&[0], "a"),
+            path(&[0, 0], "b"),
+            path(&[0, 1], "c"),
+            path(&[0, 1, 0], "d"),
+        ]
+    );
+}
+
+#[test]
+fn any_bullet_works() {
+    let md = "* [ ] a\n+ [x] b\n1. [ ] c\n  2) [/] d\n- [ ]";
+    let tdl = TodoList::from_markdown(md).unwrap();
+    assert_eq!(
+        titles(&tdl),
+        [
+            path(&[0], "a"),
+            path(&[1], "b"),
+            path(&[2], "c"),
+            path(&[2, 0], "d"),
+            path(&[3], ""),
+        ]
+    );
+    assert_eq!(tdl[1].status(), Status::Done);
+    assert_eq!(tdl[2].sub_tasks()[0].status(), Status::InProgress);
+}
+
+#[test]
+fn nested_output_round_trips() {
+    let mut a = Todo::from_title(String::from("a"));
+    let mut b = Todo::from_title(String::from("b"));
+    b.get_sub_tasks().add(Todo::from_title(String::from("c")));
+    a.get_sub_tasks().add(b);
+    a.get_sub_tasks().add(Todo::from_title(String::from("d")));
+    let mut tdl = TodoList::new();
+    tdl.add(a);
+    tdl.add(Todo::from_title(String::from("e")));
+
+    let md = tdl.to_markdown();
+    let back = TodoList::from_markdown(&md).unwrap();
+    assert_eq!(titles(&back), titles(&tdl));
+    assert_eq!(back.to_markdown(), md);
+
+    let t = Todo::from_markdown(&tdl[0].to_markdown()).unwrap();
+    assert_eq!(t.id(), tdl[0].id());
+    assert_eq!(t.sub_tasks().len(), 2);
+}
+
+#[test]
+fn tags_are_read_from_anywhere_in_the_line() {
+    let tdl = TodoList::from_markdown("- [ ] Fix #bug in #12 📅 2026-10-20 #work/urgent").unwrap();
+    assert_eq!(tdl[0].title(), "Fix in #12");
+    assert_eq!(tdl[0].tags(), ["bug", "work/urgent"]);
+    assert!(tdl[0].due().is_some());
+}
+
+#[test]
+fn old_nested_dependencies_become_references() {
+    let md = "- [ ] ship\n\t- Dependencies:\n\t\t- [x] test\n\t- Sub Tasks:\n\t\t- [ ] tag";
+    let tdl = TodoList::from_markdown(md).unwrap();
+    assert_eq!(
+        titles(&tdl),
+        [path(&[0], "test"), path(&[1], "ship"), path(&[1, 0], "tag")]
+    );
+    assert_eq!(tdl[1].dependencies(), [tdl[0].id()]);
+    assert!(tdl[0].is_completed());
+}
+
+#[test]
+fn mixed_markdown_skips_prose() {
+    let md = "# Plan\n\nSome text.\n- [ ] a\n\t- [ ] b\n- note\n\t- [ ] c\n\nMore text.\n  - [ ] d";
+    let tdl = TodoList::from_mixed_markdown(md).unwrap();
+    assert_eq!(
+        titles(&tdl),
+        [
+            path(&[0], "a"),
+            path(&[0, 0], "b"),
+            path(&[1], "c"),
+            path(&[2], "d"),
+        ]
+    );
+    assert_eq!(TodoList::from_mixed_markdown("# Nothing").unwrap().len(), 0);
+}
+
+#[test]
+fn errors_point_at_the_problem() {
+    assert_eq!(error_at("- [ ] a\nprose"), (2, 1));
+    assert_eq!(error_at("- [ ] a\n\t- b"), (2, 4));
+    assert_eq!(error_at("- [ ] a\n\t- [ ] b 📅 someday"), (2, 10));
+    assert_eq!(error_at("- [ ] a 🔁 whenever"), (1, 9));
+}
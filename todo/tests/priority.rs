@@ -0,0 +1,86 @@
+use std::str::FromStr;
+use todo::{Error, Priority, Query, SortKey, Todo, TodoList};
+
+const ALL: [Priority; 6] = [
+    Priority::Lowest,
+    Priority::Low,
+    Priority::Normal,
+    Priority::Medium,
+    Priority::High,
+    Priority::Highest,
+];
+
+fn task(title: &str, p: Priority) -> Todo {
+    let mut t = Todo::from_title(String::from(title));
+    t.set_priority(p);
+    t
+}
+
+#[test]
+fn priorities_round_trip_through_markdown() {
+    for p in ALL {
+        let t = task("Call", p);
+        let md = t.to_markdown();
+        match p.emoji() {
+            Some(e) => assert!(md.contains(e), "{}", md),
+            None => assert!(ALL
+                .iter()
+                .filter_map(|x| x.emoji())
+                .all(|e| !md.contains(e))),
+        }
+        let back = TodoList::from_markdown(&md).unwrap();
+        assert_eq!(back[0].priority(), p, "{}", md);
+        assert_eq!(back[0].title(), "Call");
+    }
+}
+
+#[test]
+fn priorities_are_read_with_variation_selectors() {
+    let back = TodoList::from_markdown("- [ ] Call 🔼\u{fe0f} 📅 2026-10-20 09:00:00").unwrap();
+    assert_eq!(back[0].priority(), Priority::Medium);
+    assert!(back[0].due().is_some());
+}
+
+#[test]
+fn priorities_are_read_by_name_and_emoji() {
+    for p in ALL {
+        assert_eq!(Priority::from_str(&p.to_string()).unwrap(), p);
+        if let Some(e) = p.emoji() {
+            assert_eq!(Priority::from_str(&e.to_string()).unwrap(), p);
+            assert_eq!(Priority::from_emoji(e), Some(p));
+        }
+    }
+    assert_eq!(Priority::from_str("MED").unwrap(), Priority::Medium);
+    assert_eq!(Priority::from_str("none").unwrap(), Priority::Normal);
+    assert!(matches!(
+        Priority::from_str("urgent"),
+        Err(Error::InvalidPriority(e)) if e == "urgent"
+    ));
+}
+
+#[test]
+fn priorities_are_ordered() {
+    assert!(ALL.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!(Priority::default(), Priority::Normal);
+}
+
+#[test]
+fn priorities_sort_and_query() {
+    let mut tdl = TodoList::new();
+    tdl.add(task("low", Priority::Low));
+    tdl.add(task("highest", Priority::Highest));
+    tdl.add(task("normal", Priority::Normal));
+    tdl.add(task("high", Priority::High));
+
+    tdl.sort_by(SortKey::Priority);
+    let titles: Vec<&str> = tdl.iter().map(|t| t.title()).collect();
+    assert_eq!(titles, ["highest", "high", "normal", "low"]);
+
+    let q = Query::from_str("priority>=high").unwrap();
+    let found: Vec<&str> = tdl.query(&q, false).iter().map(|t| t.title()).collect();
+    assert_eq!(found, ["highest", "high"]);
+    let q = Query::from_str("priority:normal").unwrap();
+    assert_eq!(tdl.query(&q, false).len(), 1);
+    assert!(Query::from_str("priority:urgent").is_err());
+    assert!(Query::from_str("priority~high").is_err());
+}
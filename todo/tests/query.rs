@@ -0,0 +1,160 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use std::str::FromStr;
+use todo::query::{Cmp, DateField, DateValue, Field};
+use todo::{Error, Query, Todo};
+
+fn q(s: &str) -> Query {
+    Query::from_str(s).unwrap()
+}
+
+fn tag(s: &str) -> Query {
+    Query::Tag(String::from(s))
+}
+
+fn not(q: Query) -> Query {
+    Query::Not(Box::new(q))
+}
+
+/// The column of the parse error for a query.
+fn error_column(s: &str) -> usize {
+    match Query::from_str(s) {
+        Err(Error::Parse { line, column, .. }) => {
+            assert_eq!(line, 1);
+            column
+        }
+        e => panic!("expected a parse error for {}, got {:?}", s, e),
+    }
+}
+
+fn now() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2026, 10, 18)
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap()
+}
+
+fn report() -> Todo {
+    let mut t = Todo::from_title(String::from("Write the Report"));
+    t.set_id(String::from("abc123"));
+    t.add_tag(String::from("work/writing")).unwrap();
+    t.set_due_iso8601(String::from("2026-10-20 09:00:00"))
+        .unwrap();
+    t
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    assert_eq!(q(""), Query::All);
+    assert_eq!(q("tag:a"), tag("a"));
+    assert_eq!(
+        q("tag:a tag:b or tag:c"),
+        Query::Or(vec![Query::And(vec![tag("a"), tag("b")]), tag("c")])
+    );
+    assert_eq!(
+        q("tag:a OR tag:b tag:c"),
+        Query::Or(vec![tag("a"), Query::And(vec![tag("b"), tag("c")])])
+    );
+}
+
+#[test]
+fn not_binds_tightest() {
+    assert_eq!(
+        q("not tag:a tag:b"),
+        Query::And(vec![not(tag("a")), tag("b")])
+    );
+    assert_eq!(
+        q("-tag:a or tag:b"),
+        Query::Or(vec![not(tag("a")), tag("b")])
+    );
+    assert_eq!(q("not not tag:a"), not(not(tag("a"))));
+}
+
+#[test]
+fn parentheses_group() {
+    assert_eq!(
+        q("tag:a (tag:b or tag:c)"),
+        Query::And(vec![tag("a"), Query::Or(vec![tag("b"), tag("c")])])
+    );
+    assert_eq!(
+        q("not (tag:a tag:b)"),
+        not(Query::And(vec![tag("a"), tag("b")]))
+    );
+    assert_eq!(q("((tag:a))"), tag("a"));
+}
+
+#[test]
+fn quoted_titles() {
+    assert_eq!(
+        q("title~\"the report\""),
+        Query::Title(String::from("the report"))
+    );
+    assert_eq!(
+        q("title:\"a (b) or c\" tag:a"),
+        Query::And(vec![Query::Title(String::from("a (b) or c")), tag("a")])
+    );
+    assert!(q("title~\"the report\"").matches(&report()));
+    assert!(!q("title:\"report the\"").matches(&report()));
+}
+
+#[test]
+fn terms() {
+    assert_eq!(q("tag:#Work"), tag("work"));
+    assert_eq!(q("id:abc123"), Query::Id(String::from("abc123")));
+    assert_eq!(q("has:repeat"), Query::Has(Field::Repeat));
+    assert_eq!(q("has:deps"), Query::Has(Field::Dependencies));
+    assert_eq!(q("done"), Query::Done);
+    assert_eq!(
+        q("due<2026-11-01"),
+        Query::Date(
+            DateField::Due,
+            Cmp::Lt,
+            DateValue::Date(NaiveDate::from_ymd_opt(2026, 11, 1).unwrap())
+        )
+    );
+    assert_eq!(
+        q("start>=today"),
+        Query::Date(DateField::Start, Cmp::Ge, DateValue::Relative(0))
+    );
+    assert_eq!(
+        q("done!=yesterday"),
+        Query::Date(DateField::Completed, Cmp::Ne, DateValue::Relative(-1))
+    );
+}
+
+#[test]
+fn terms_match_tasks() {
+    let t = report();
+    let matches = |s: &str| q(s).matches_at(&t, now());
+
+    assert!(matches("tag:work"));
+    assert!(matches("tag:work/writing"));
+    assert!(!matches("tag:wor"));
+    assert!(matches("id:abc123"));
+    assert!(matches("has:due has:tags -has:repeat"));
+    assert!(matches("open"));
+    assert!(!matches("done"));
+    assert!(matches("due>today due<=2026-10-20"));
+    assert!(matches("due=2026-10-20"));
+    assert!(matches("due>\"2026-10-20 08:00\""));
+    assert!(!matches("due>\"2026-10-20 09:00:00\""));
+    assert!(!matches("due<tomorrow"));
+    assert!(!matches("start<2030-01-01"));
+    assert!(matches("tag:home or due>today"));
+}
+
+#[test]
+fn unbalanced_parentheses_point_at_the_paren() {
+    assert_eq!(error_column("tag:a (tag:b"), 7);
+    assert_eq!(error_column("tag:a)"), 6);
+    assert_eq!(error_column("()"), 2);
+}
+
+#[test]
+fn unknown_terms_point_at_the_term() {
+    assert_eq!(error_column("tag:a bogus"), 7);
+    assert_eq!(error_column("tag:a foo:bar"), 7);
+    assert_eq!(error_column("has:wings"), 5);
+    assert_eq!(error_column("due<someday"), 5);
+    assert_eq!(error_column("tag:a not"), 7);
+    assert_eq!(error_column("tag:a title:\"open"), 7);
+}
@@ -0,0 +1,98 @@
+use chrono::{NaiveDate, NaiveDateTime, Weekday};
+use serde_json::Value;
+use todo::Todo;
+
+/// Sunday 2026-10-18, 10:00.
+fn now() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2026, 10, 18)
+        .unwrap()
+        .and_hms_opt(10, 0, 0)
+        .unwrap()
+}
+
+fn quick(s: &str) -> Value {
+    let t = Todo::parse_quick_add_at(s, now(), Weekday::Mon).unwrap();
+    serde_json::to_value(&t).unwrap()
+}
+
+#[test]
+fn reads_every_part_of_a_task() {
+    let v = quick("Write report tomorrow 5pm #work every monday 30m !high");
+    assert_eq!(v["title"], "Write report");
+    assert_eq!(v["due"], "2026-10-19 17:00:00");
+    assert_eq!(v["repeat"]["Rule"], "every week on Monday");
+    assert_eq!(v["tags"], serde_json::json!(["work"]));
+    assert_eq!(v["priority"], "High");
+    assert_eq!(v["duration"], 30 * 60 * 1000);
+}
+
+#[test]
+fn dates_can_follow_a_preposition() {
+    let v = quick("Call Sam by friday");
+    assert_eq!(
+        (&v["title"], &v["due"]),
+        (&"Call Sam".into(), &"2026-10-23".into())
+    );
+    let v = quick("Pay rent on nov 1 ⏫");
+    assert_eq!(
+        (&v["title"], &v["due"]),
+        (&"Pay rent".into(), &"2026-11-01".into())
+    );
+    assert_eq!(v["priority"], "High");
+    // Words that aren't dates stay in the title.
+    let v = quick("Meet at the cafe");
+    assert_eq!(v["title"], "Meet at the cafe");
+    assert_eq!(v["due"], Value::Null);
+}
+
+#[test]
+fn repeats_on_days_start_on_the_first_of_them() {
+    let v = quick("Standup every weekday 15m");
+    assert_eq!(v["title"], "Standup");
+    assert_eq!(v["due"], "2026-10-19");
+    assert_eq!(v["repeat"]["Rule"], "every weekday");
+    assert_eq!(v["duration"], 15 * 60 * 1000);
+
+    let v = quick("Gym every monday and thursday");
+    assert_eq!(v["due"], "2026-10-19");
+    assert_eq!(v["repeat"]["Rule"], "every week on Monday and Thursday");
+
+    // Other rules start today.
+    let v = quick("Read every other week");
+    assert_eq!(v["repeat"]["Rule"], "every 2 weeks");
+    assert_eq!(v["due"], "2026-10-18");
+    let v = quick("Water plants every 3 days from completed");
+    assert_eq!(v["title"], "Water plants");
+    assert_eq!(v["repeat"]["Rule"], "every 3 days when done");
+
+    // Every without a rule after it is part of the title.
+    let v = quick("Every man for himself");
+    assert_eq!(v["title"], "Every man for himself");
+    assert_eq!(v["repeat"], Value::Null);
+}
+
+#[test]
+fn tags_and_durations_are_taken_out() {
+    let v = quick("Buy milk #home #errands 1h30m");
+    assert_eq!(v["title"], "Buy milk");
+    assert_eq!(v["tags"], serde_json::json!(["home", "errands"]));
+    assert_eq!(v["duration"], 90 * 60 * 1000);
+}
+
+#[test]
+fn rejects_tasks_without_a_title() {
+    for s in ["!high", "tomorrow #work"] {
+        assert!(
+            Todo::parse_quick_add_at(s, now(), Weekday::Mon).is_err(),
+            "{}",
+            s
+        );
+    }
+}
+
+#[test]
+fn exclamations_that_arent_priorities_stay_in_the_title() {
+    let v = quick("Fix bug !urgent");
+    assert_eq!(v["title"], "Fix bug !urgent");
+    assert_eq!(v["priority"], "Normal");
+}
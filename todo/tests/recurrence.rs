@@ -0,0 +1,142 @@
+use chrono::{Duration, Local};
+use chrono_tz::Tz;
+use todo::{Document, Error, Todo, TodoList};
+
+fn task(repeat: &str, due: &str) -> Todo {
+    let mut t = Todo::from_title(String::from("Water plants"));
+    t.set_id(String::from("water"));
+    t.set_repeat(String::from(repeat)).unwrap();
+    t.set_due_iso8601(String::from(due)).unwrap();
+    t
+}
+
+/// The due date and repeat rule of the task after completing one.
+fn next(repeat: &str, due: &str) -> Option<(String, String)> {
+    let mut tdl = TodoList::new();
+    tdl.add(task(repeat, due));
+    tdl.set_zone(Some(Tz::Europe__Berlin));
+    let v = serde_json::to_value(tdl.complete("water").unwrap()?).unwrap();
+    let due = String::from(v["due"].as_str().unwrap());
+    let rule = String::from(v["repeat"]["Rule"].as_str().unwrap());
+    Some((due, rule))
+}
+
+fn next_due(repeat: &str, due: &str) -> String {
+    next(repeat, due).unwrap().0
+}
+
+#[test]
+fn rules_are_written_as_text() {
+    for (read, written) in [
+        ("every weekday", "every weekday"),
+        ("Every Month on the 15th", "every month on the 15th"),
+        ("every 2nd tuesday", "every month on the 2nd Tuesday"),
+        ("every year on march 3", "every year on March 3"),
+        (
+            "every month on the last weekday",
+            "every month on the last weekday",
+        ),
+        (
+            "every 2 weeks on tuesday and friday until 2030-12-31",
+            "every 2 weeks on Tuesday and Friday until 2030-12-31",
+        ),
+        (
+            "every week for 3 times when done",
+            "every week for 3 times when done",
+        ),
+        (
+            "FREQ=WEEKLY;BYDAY=MO,WE",
+            "every week on Monday and Wednesday",
+        ),
+        // Text can't say the last Monday or Tuesday of the month.
+        (
+            "RRULE:FREQ=MONTHLY;BYDAY=MO,TU;BYSETPOS=-1",
+            "RRULE:FREQ=MONTHLY;BYDAY=MO,TU;BYSETPOS=-1",
+        ),
+    ] {
+        let t = task(read, "2030-10-15");
+        let v = serde_json::to_value(&t).unwrap();
+        assert_eq!(v["repeat"]["Rule"], written, "{}", read);
+
+        // What's written is read back as the same rule.
+        let expected = format!("- [ ] Water plants 🔁 {} 📅 2030-10-15\n", written);
+        let again = Document::from_markdown(&expected).unwrap();
+        assert_eq!(again.to_markdown(), expected);
+        let tdl = TodoList::from_markdown(&expected).unwrap();
+        let json = TodoList::from_json(&tdl.to_json().unwrap()).unwrap();
+        assert_eq!(json.to_markdown(), tdl.to_markdown());
+    }
+}
+
+#[test]
+fn rules_give_the_next_due_date() {
+    // 2030-10-15 is a Tuesday.
+    for (repeat, due) in [
+        ("every weekday", "2030-10-16"),
+        ("every month on the 15th", "2030-11-15"),
+        ("every 2nd tuesday", "2030-11-12"),
+        ("every month on the last weekday", "2030-10-31"),
+        ("every year on march 3", "2031-03-03"),
+        ("every 2 weeks on tuesday and friday", "2030-10-18"),
+        ("RRULE:FREQ=MONTHLY;BYDAY=MO,TU;BYSETPOS=-1", "2030-10-29"),
+    ] {
+        assert_eq!(next_due(repeat, "2030-10-15"), due, "{}", repeat);
+    }
+    // Fridays are followed by Mondays.
+    assert_eq!(next_due("every weekday", "2030-10-18"), "2030-10-21");
+    // The 31st is the last day of shorter months.
+    assert_eq!(
+        next_due("every month on the 31st", "2031-03-31"),
+        "2031-04-30"
+    );
+}
+
+#[test]
+fn times_of_day_are_kept() {
+    assert_eq!(
+        next_due("every weekday", "2030-10-18 09:00:00"),
+        "2030-10-21 09:00:00"
+    );
+    assert_eq!(
+        next_due("every month on the 15th", "2030-10-15 18:30:00 +02:00"),
+        "2030-11-15 18:30:00 +01:00"
+    );
+}
+
+#[test]
+fn counted_rules_run_out() {
+    let (due, rule) = next("every 2nd tuesday for 3 times", "2030-10-08").unwrap();
+    assert_eq!(due, "2030-11-12");
+    assert_eq!(rule, "every month on the 2nd Tuesday for 2 times");
+    let (_, rule) = next(&rule, &due).unwrap();
+    assert_eq!(rule, "every month on the 2nd Tuesday for 1 time");
+    assert!(next(&rule, &due).is_none());
+
+    assert!(next("every weekday until 2030-10-18", "2030-10-17").is_some());
+    assert!(next("every weekday until 2030-10-18", "2030-10-18").is_none());
+}
+
+#[test]
+fn rules_when_done_count_from_today() {
+    let today = Local::now().date_naive();
+    let expected = (today + Duration::weeks(1)).format("%Y-%m-%d").to_string();
+    assert_eq!(next_due("every week when done", "2030-10-15"), expected);
+}
+
+#[test]
+fn durations_and_cron_still_work() {
+    let mut t = Todo::from_title(String::from("Backup"));
+    for repeat in [
+        "1 week",
+        "every 3 days",
+        "2 days from completed",
+        "0 0 9 * * *",
+    ] {
+        assert!(t.set_repeat(String::from(repeat)).is_ok(), "{}", repeat);
+    }
+    match t.set_repeat(String::from("every fortnight")) {
+        Err(Error::InvalidRepeat(s)) => assert_eq!(s, "every fortnight"),
+        e => panic!("expected an invalid repeat, got {:?}", e),
+    }
+    assert!(t.set_repeat(String::from("RRULE:FREQ=SOMETIMES")).is_err());
+}
@@ -0,0 +1,59 @@
+use chrono::NaiveDate;
+use todo::{Date, Error, Todo};
+
+/// The days a task is due on as it's completed again and again.
+fn series(repeat: &str, due: &str, n: usize) -> Vec<String> {
+    let mut t = Todo::from_title(String::from("Pay rent"));
+    t.set_repeat(String::from(repeat)).unwrap();
+    t.set_due(Date::Day(
+        NaiveDate::parse_from_str(due, "%Y-%m-%d").unwrap(),
+    ));
+    let mut days = Vec::new();
+    for _ in 0..n {
+        t = t.complete(|_| true).unwrap().unwrap();
+        days.push(t.due_date().unwrap().to_string());
+    }
+    days
+}
+
+#[test]
+fn monthly_keeps_the_day_it_started_on() {
+    assert_eq!(
+        series("every month", "2026-01-31", 4),
+        ["2026-02-28", "2026-03-31", "2026-04-30", "2026-05-31"]
+    );
+    assert_eq!(
+        series("every month", "2026-01-15", 2),
+        ["2026-02-15", "2026-03-15"]
+    );
+}
+
+#[test]
+fn yearly_keeps_leap_days() {
+    assert_eq!(
+        series("every year", "2024-02-29", 4),
+        ["2025-02-28", "2026-02-28", "2027-02-28", "2028-02-29"]
+    );
+}
+
+#[test]
+fn rejects_rules_that_dont_fit() {
+    let mut t = Todo::from_title(String::from("Pay rent"));
+    for rule in [
+        "RRULE:FREQ=YEARLY;INTERVAL=4294967295",
+        "RRULE:FREQ=DAILY;INTERVAL=1001",
+        "RRULE:FREQ=MONTHLY;BYDAY=éX",
+        "RRULE:FREQ=MONTHLY;BYDAY=é",
+        "RRULE:FREQ=MONTHLY;BYDAY=-2147483648MO",
+        "RRULE:FREQ=MONTHLY;BYMONTHDAY=-2147483648",
+        "every 4294967295 years",
+    ] {
+        match t.set_repeat(String::from(rule)) {
+            Err(Error::InvalidRepeat(_)) => (),
+            Err(e) => panic!("{}: {}", rule, e),
+            Ok(()) => panic!("{} was accepted", rule),
+        }
+    }
+    t.set_repeat(String::from("RRULE:FREQ=YEARLY;INTERVAL=4"))
+        .unwrap();
+}
@@ -0,0 +1,111 @@
+use std::str::FromStr;
+use todo::{Document, Query, Source, TodoList};
+
+const NOTE: &str = "Intro
+- [ ] loose
+# Projects
+- [ ] projects
+## Garden ##
+- [ ] garden
+\t- [ ] weed
+### Beds
+- [ ] beds
+## Kitchen
+```
+# not a heading
+- [ ] not a task
+```
+- [ ] kitchen
+#notaheading
+- [ ] tagged
+# Errands
+~~~md
+```
+- [ ] still not a task
+~~~
+- [ ] errands
+";
+
+fn headings(tdl: &TodoList) -> Vec<(&str, Vec<&str>)> {
+    tdl.walk()
+        .map(|(_, t)| {
+            let h = t.source().unwrap().headings.iter().map(|x| x.as_str());
+            (t.title(), h.collect())
+        })
+        .collect()
+}
+
+#[test]
+fn tasks_record_their_heading_path() {
+    let tdl = TodoList::from_mixed_markdown(NOTE).unwrap();
+    assert_eq!(
+        headings(&tdl),
+        [
+            ("loose", vec![]),
+            ("projects", vec!["Projects"]),
+            ("garden", vec!["Projects", "Garden"]),
+            ("weed", vec!["Projects", "Garden"]),
+            ("beds", vec!["Projects", "Garden", "Beds"]),
+            ("kitchen", vec!["Projects", "Kitchen"]),
+            ("tagged", vec!["Projects", "Kitchen"]),
+            ("errands", vec!["Errands"]),
+        ]
+    );
+}
+
+#[test]
+fn tasks_record_their_line() {
+    let tdl = TodoList::from_mixed_markdown(NOTE).unwrap();
+    let lines: Vec<usize> = tdl.walk().map(|(_, t)| t.source().unwrap().line).collect();
+    assert_eq!(lines, [2, 4, 6, 7, 9, 15, 17, 23]);
+
+    let beds = tdl.iter().find(|t| t.title() == "beds").unwrap();
+    assert_eq!(
+        beds.source(),
+        Some(&Source {
+            file: None,
+            line: 9,
+            headings: vec![
+                String::from("Projects"),
+                String::from("Garden"),
+                String::from("Beds")
+            ],
+        })
+    );
+    assert_eq!(beds.source().unwrap().section(), Some("Beds"));
+}
+
+#[test]
+fn tasks_record_their_file() {
+    let dir = std::env::temp_dir().join(format!("todo-sections-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("note.md");
+    std::fs::write(&file, NOTE).unwrap();
+    let file = file.to_str().unwrap();
+
+    let tdl = TodoList::from_mixed_markdown_file(file).unwrap();
+    assert!(tdl
+        .walk()
+        .all(|(_, t)| t.source().unwrap().file.as_deref() == Some(file)));
+    let doc = Document::from_markdown_file(file).unwrap();
+    assert_eq!(doc.tasks()[0].source().unwrap().file.as_deref(), Some(file));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sections_are_found_at_any_level() {
+    let tdl = TodoList::from_mixed_markdown(NOTE).unwrap();
+    let titles = |v: Vec<&todo::Todo>| -> Vec<String> {
+        v.iter().map(|t| String::from(t.title())).collect()
+    };
+    assert_eq!(
+        titles(tdl.section("projects")),
+        ["projects", "garden", "weed", "beds", "kitchen", "tagged"]
+    );
+    assert_eq!(titles(tdl.section("Garden")), ["garden", "weed", "beds"]);
+    assert!(tdl.section("Nowhere").is_empty());
+
+    let q = Query::from_str("section:\"## Garden\" -section:beds").unwrap();
+    assert_eq!(titles(tdl.query(&q, true)), ["garden", "weed"]);
+}
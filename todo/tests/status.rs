@@ -0,0 +1,128 @@
+use std::str::FromStr;
+use todo::{Error, Query, Status, Todo, TodoList};
+
+fn task(id: &str, dependencies: &[&str]) -> Todo {
+    let mut t = Todo::from_title(format!("Task {}", id));
+    t.set_id(String::from(id));
+    for d in dependencies {
+        t.add_dependency(String::from(*d));
+    }
+    t
+}
+
+#[test]
+fn checkbox_characters_round_trip() {
+    let md = "- [ ] open\n- [/] doing\n- [x] done\n- [-] dropped\n- [>] later\n- [?] maybe";
+    let tdl = TodoList::from_markdown(md).unwrap();
+    let statuses: Vec<Status> = tdl.iter().map(|t| t.status()).collect();
+    assert_eq!(
+        statuses,
+        [
+            Status::Open,
+            Status::InProgress,
+            Status::Done,
+            Status::Cancelled,
+            Status::Custom('>'),
+            Status::Custom('?'),
+        ]
+    );
+    assert_eq!(tdl[4].title(), "later");
+
+    let back = TodoList::from_markdown(&tdl.to_markdown()).unwrap();
+    for (a, b) in tdl.iter().zip(back.iter()) {
+        assert_eq!(a.status(), b.status());
+        assert_eq!(a.title(), b.title());
+    }
+    for (line, t) in md.lines().zip(back.iter()) {
+        assert!(t.to_markdown().starts_with(&line[..5]), "{}", t);
+    }
+}
+
+#[test]
+fn custom_statuses_are_open() {
+    let tdl = TodoList::from_markdown("- [>] later\n- [X] done").unwrap();
+    assert!(tdl[0].is_open());
+    assert!(!tdl[0].is_completed());
+    assert_eq!(tdl[1].status(), Status::Done);
+}
+
+#[test]
+fn statuses_are_read_by_name_and_symbol() {
+    assert_eq!(Status::from_str("in progress").unwrap(), Status::InProgress);
+    assert_eq!(Status::from_str("Canceled").unwrap(), Status::Cancelled);
+    assert_eq!(Status::from_str("[>]").unwrap(), Status::Custom('>'));
+    assert_eq!(Status::from_str("x").unwrap(), Status::Done);
+    assert!(matches!(
+        Status::from_str("someday"),
+        Err(Error::InvalidStatus(_))
+    ));
+}
+
+#[test]
+fn set_status_stamps_dates() {
+    let mut t = Todo::from_title(String::from("Ship"));
+    t.set_status(Status::Done);
+    assert!(t.completed().is_some());
+    t.set_status(Status::Cancelled);
+    assert!(t.completed().is_none() && t.cancelled().is_some());
+    t.set_status(Status::Custom('>'));
+    assert!(t.completed().is_none() && t.cancelled().is_none());
+    assert!(t.is_open());
+}
+
+#[test]
+fn cancelled_tasks_dont_block() {
+    let mut tdl = TodoList::new();
+    tdl.add(task("a", &[]));
+    tdl.add(task("b", &["a"]));
+    tdl.add(task("c", &["b"]));
+    let ready = |tdl: &TodoList| -> Vec<String> {
+        tdl.ready().iter().map(|t| String::from(t.id())).collect()
+    };
+    assert_eq!(ready(&tdl), ["a"]);
+
+    tdl.get_by_id_mut("a").unwrap().cancel();
+    assert_eq!(ready(&tdl), ["b"]);
+
+    tdl.get_by_id_mut("b")
+        .unwrap()
+        .set_status(Status::InProgress);
+    assert_eq!(ready(&tdl), ["b"]);
+    assert!(matches!(tdl.complete("c"), Err(Error::Dependency(_))));
+
+    tdl.complete("b").unwrap();
+    assert_eq!(ready(&tdl), ["c"]);
+}
+
+#[test]
+fn status_terms() {
+    let tdl = TodoList::from_markdown("- [ ] a\n- [/] b\n- [x] c\n- [-] d\n- [>] e").unwrap();
+    let titles = |s: &str| -> Vec<String> {
+        let q = Query::from_str(s).unwrap();
+        tdl.query(&q, false)
+            .iter()
+            .map(|t| String::from(t.title()))
+            .collect()
+    };
+    assert_eq!(titles("open"), ["a", "b", "e"]);
+    assert_eq!(titles("done"), ["c"]);
+    assert_eq!(titles("cancelled"), ["d"]);
+    assert_eq!(titles("status:\"in progress\""), ["b"]);
+    assert_eq!(titles("status:>"), ["e"]);
+    assert_eq!(titles("not open"), ["c", "d"]);
+    assert!(Query::from_str("status:someday").is_err());
+}
+
+#[test]
+fn old_json_reads_status_from_dates() {
+    let mut tdl = TodoList::new();
+    let mut t = Todo::from_title(String::from("a"));
+    t.set_completed_iso8601(String::from("2026-10-18 09:00:00"))
+        .unwrap();
+    tdl.add(t);
+    let mut json: serde_json::Value = serde_json::from_str(&tdl.to_json().unwrap()).unwrap();
+    json["items"][0].as_object_mut().unwrap().remove("status");
+
+    let back = TodoList::from_json(&json.to_string()).unwrap();
+    assert_eq!(back[0].status(), Status::Done);
+}
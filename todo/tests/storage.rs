@@ -0,0 +1,25 @@
+use std::time::Duration;
+use todo::storage::Lock;
+use todo::Error;
+
+#[test]
+fn lock_file_is_hidden_and_removed() {
+    let dir = std::env::temp_dir().join(format!("todo-lock-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("todo.json");
+    let file = file.to_str().unwrap();
+
+    let lock = Lock::acquire(file, Duration::ZERO).unwrap();
+    assert_eq!(lock.path(), dir.join(".todo.json.lock"));
+    assert!(lock.path().exists());
+    match Lock::acquire(file, Duration::ZERO) {
+        Err(Error::Locked(_)) => (),
+        _ => panic!("locked twice"),
+    }
+
+    let path = lock.path().to_path_buf();
+    drop(lock);
+    assert!(!path.exists());
+    drop(Lock::acquire(file, Duration::ZERO).unwrap());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,35 @@
+use std::str::FromStr;
+use todo::{Date, Query, Todo, TodoList};
+
+#[test]
+fn removes_tags_in_any_order() {
+    let mut t = Todo::from_title(String::from("Write report"));
+    for tag in ["work", "q3", "admin"] {
+        t.add_tag(String::from(tag)).unwrap();
+    }
+    t.remove_tag(String::from("admin"));
+    t.remove_tag(String::from("work"));
+    assert_eq!(t.tags(), ["q3"]);
+}
+
+#[test]
+fn times_are_read_on_the_clock_in_the_list_zone() {
+    // 23:30 in London is 08:30 the next day in Tokyo.
+    let mut t = Todo::from_title(String::from("Call Tokyo office"));
+    t.set_due(Date::parse("2026-10-18 23:30:00 +00:00").unwrap());
+    let mut tdl = TodoList::new();
+    tdl.add(t);
+    tdl.set_zone(Some(chrono_tz::Asia::Tokyo));
+
+    let due = tdl[0].due_date().unwrap();
+    assert_eq!(due.date_in(tdl.zone()).to_string(), "2026-10-19");
+    let q = Query::from_str("due:2026-10-19").unwrap();
+    assert_eq!(q.run(&tdl, false).len(), 1);
+
+    let now = chrono::NaiveDate::from_ymd_opt(2026, 10, 19)
+        .unwrap()
+        .and_hms_opt(8, 0, 0)
+        .unwrap();
+    assert!(!tdl[0].is_overdue_in(tdl.zone(), now));
+    assert!(Query::Overdue.matches_in(&tdl[0], tdl.zone(), now + chrono::Duration::hours(1)));
+}
@@ -0,0 +1,95 @@
+use todo::{SortKey, Todo, TodoList};
+
+fn task(title: &str, due: Option<&str>) -> Todo {
+    let mut t = Todo::from_title(String::from(title));
+    if let Some(e) = due {
+        t.set_due_iso8601(String::from(e)).unwrap();
+    }
+    t
+}
+
+/// Three tasks, the first with two sub tasks, one of which has its own.
+fn list() -> TodoList {
+    let mut home = task("home", None);
+    let mut garden = task("garden", Some("2026-10-20 10:00:00"));
+    garden.get_sub_tasks().add(task("weed", None));
+    home.get_sub_tasks().add(garden);
+    home.get_sub_tasks()
+        .add(task("Attic", Some("2026-10-19 10:00:00")));
+
+    let mut tdl = TodoList::new();
+    tdl.add(home);
+    tdl.add(task("Bills", Some("2026-10-25 10:00:00")));
+    tdl.add(task("call", Some("2026-10-21 10:00:00")));
+    tdl
+}
+
+fn titles<'a>(tasks: impl IntoIterator<Item = &'a Todo>) -> Vec<&'a str> {
+    tasks.into_iter().map(|t| t.title()).collect()
+}
+
+#[test]
+fn iterators_borrow() {
+    let mut tdl = list();
+    assert_eq!(titles(tdl.iter()), ["home", "Bills", "call"]);
+    assert_eq!(titles(&tdl), ["home", "Bills", "call"]);
+
+    for t in &mut tdl {
+        let title = t.title().to_uppercase();
+        t.set_title(title).unwrap();
+    }
+    for t in tdl.iter_mut() {
+        let title = format!("{}!", t.title());
+        t.set_title(title).unwrap();
+    }
+    assert_eq!(titles(&tdl), ["HOME!", "BILLS!", "CALL!"]);
+    assert_eq!(titles(tdl[0].sub_tasks()), ["garden", "Attic"]);
+}
+
+#[test]
+fn walk_visits_sub_tasks_depth_first() {
+    let tdl = list();
+    let walked: Vec<(Vec<usize>, &str)> = tdl.walk().map(|(p, t)| (p, t.title())).collect();
+    assert_eq!(
+        walked,
+        [
+            (vec![0], "home"),
+            (vec![0, 0], "garden"),
+            (vec![0, 0, 0], "weed"),
+            (vec![0, 1], "Attic"),
+            (vec![1], "Bills"),
+            (vec![2], "call"),
+        ]
+    );
+    assert_eq!(TodoList::new().walk().count(), 0);
+}
+
+#[test]
+fn filter_takes_closures() {
+    let tdl = list();
+    let prefix = String::from("b");
+    let found = tdl.filter(|t| t.title().to_lowercase().starts_with(&prefix));
+    assert_eq!(titles(found), ["Bills"]);
+    assert_eq!(titles(tdl.filter(|t| t.due().is_none())), ["home"]);
+}
+
+#[test]
+fn sort_by_puts_missing_fields_last() {
+    let mut tdl = list();
+    tdl.sort_by(SortKey::Due);
+    assert_eq!(titles(&tdl), ["call", "Bills", "home"]);
+    assert_eq!(titles(tdl[2].sub_tasks()), ["Attic", "garden"]);
+
+    tdl.sort_by(SortKey::Title);
+    assert_eq!(titles(&tdl), ["Bills", "call", "home"]);
+    assert_eq!(titles(tdl[2].sub_tasks()), ["Attic", "garden"]);
+}
+
+#[test]
+fn sort_by_puts_completed_tasks_last() {
+    let mut tdl = list();
+    let id = String::from(tdl[0].id());
+    tdl.complete(&id).unwrap();
+    tdl.sort_by(SortKey::Completed);
+    assert_eq!(titles(&tdl), ["Bills", "call", "home"]);
+}
@@ -0,0 +1,25 @@
+use todo::Vault;
+
+#[test]
+fn copies_in_two_notes_get_their_own_ids() {
+    let mut v = Vault::new("vault");
+    v.insert("home.md", "- [ ] Buy milk\n").unwrap();
+    v.insert("work/errands.md", "- [ ] Buy milk\n").unwrap();
+
+    let home = v.get("home.md").unwrap()[0].id().to_string();
+    let errands = v.get("work/errands.md").unwrap()[0].id().to_string();
+    assert_ne!(home, errands);
+
+    let t = v.get_by_id(&errands).unwrap();
+    let file = t.source().and_then(|x| x.file.as_deref());
+    assert_eq!(file, Some("work/errands.md"));
+}
+
+#[test]
+fn ids_stay_the_same_when_a_note_is_read_again() {
+    let mut v = Vault::new("vault");
+    v.insert("home.md", "- [ ] Buy milk\n").unwrap();
+    let before = v.get("home.md").unwrap()[0].id().to_string();
+    v.insert("home.md", "# Errands\n- [ ] Buy milk\n").unwrap();
+    assert_eq!(v.get("home.md").unwrap()[0].id(), before);
+}
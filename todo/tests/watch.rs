@@ -0,0 +1,118 @@
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+/// The `todo watch` process, killed when dropped.
+struct Watch {
+    child: Child,
+    lines: Receiver<String>,
+}
+
+impl Watch {
+    fn start(dir: &Path) -> Watch {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_todo"))
+            .arg("watch")
+            .arg("--vault")
+            .arg(dir)
+            .env("TODO_CONFIG", dir.join("config.json"))
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (tx, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if tx.send(line.unwrap()).is_err() {
+                    break;
+                }
+            }
+        });
+        Watch { child, lines }
+    }
+
+    /// Lines printed until one contains `s`, failing after a while.
+    fn until(&self, s: &str) -> Vec<String> {
+        let deadline = Instant::now() + Duration::from_secs(20);
+        let mut seen = Vec::new();
+        while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+            match self.lines.recv_timeout(left) {
+                Ok(line) => {
+                    let found = line.contains(s);
+                    seen.push(line);
+                    if found {
+                        return seen;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+        panic!("never printed {:?}, printed {:?}", s, seen);
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("todo-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn changed_notes_are_reindexed() {
+    let dir = dir("watch");
+    let today = chrono::Local::now().format("%Y-%m-%d");
+    fs::write(
+        dir.join("Garden.md"),
+        format!("- [ ] Water plants 📅 {}\n", today),
+    )
+    .unwrap();
+    fs::write(dir.join("Old.md"), "- [ ] Old task\n").unwrap();
+
+    let w = Watch::start(&dir);
+    let agenda = w.until("Water plants");
+    assert!(agenda.iter().any(|x| x == "Today:"), "{:?}", agenda);
+    assert!(dir.join(".todo-index.json").exists());
+    // The watcher is started just after the first agenda is printed.
+    std::thread::sleep(Duration::from_millis(500));
+
+    fs::write(
+        dir.join("Garden.md"),
+        format!(
+            "- [ ] Water plants 📅 {}\n- [ ] Feed cat 📅 {}\n",
+            today, today
+        ),
+    )
+    .unwrap();
+    w.until("~ Garden.md");
+    let agenda = w.until("Feed cat");
+    assert!(agenda
+        .iter()
+        .any(|x| x.contains("Water plants (Garden.md:1)")));
+    assert!(agenda.iter().any(|x| x.contains("Feed cat (Garden.md:2)")));
+
+    fs::write(dir.join("New.md"), format!("- [ ] Call Sam 📅 {}\n", today)).unwrap();
+    w.until("+ New.md");
+    w.until("Call Sam (New.md:1)");
+
+    fs::remove_file(dir.join("Old.md")).unwrap();
+    w.until("- Old.md");
+
+    // Files that aren't notes don't print anything.
+    fs::write(dir.join("notes.txt"), "- [ ] Not a task\n").unwrap();
+    fs::write(dir.join("New.md"), "- [ ] Call Sam tomorrow\n").unwrap();
+    let printed = w.until("~ New.md");
+    assert!(!printed.iter().any(|x| x.contains("notes.txt")));
+
+    drop(w);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,86 @@
+use chrono_tz::Tz;
+use todo::{Todo, TodoList};
+
+/// A repeating task due at a time, in a list in Berlin, where the clocks go
+/// back an hour on 2030-10-27.
+fn berlin(due: &str, repeat: &str) -> TodoList {
+    let mut t = Todo::from_title(String::from("Standup"));
+    t.set_id(String::from("standup"));
+    t.set_due_iso8601(String::from(due)).unwrap();
+    t.set_repeat(String::from(repeat)).unwrap();
+    let mut tdl = TodoList::new();
+    tdl.add(t);
+    tdl.set_zone(Some(Tz::Europe__Berlin));
+    tdl
+}
+
+/// Due time of the task repeating the completed one.
+fn next_due(tdl: &mut TodoList) -> String {
+    let next = tdl.complete("standup").unwrap().unwrap();
+    let v = serde_json::to_value(&next).unwrap();
+    String::from(v["due"].as_str().unwrap())
+}
+
+#[test]
+fn offsets_are_kept() {
+    let mut t = Todo::from_title(String::from("Call"));
+    t.set_due_iso8601(String::from("2030-10-20 09:00:00 +02:00"))
+        .unwrap();
+    t.set_start_iso8601(String::from("2030-10-20T07:00:00Z"))
+        .unwrap();
+    let v = serde_json::to_value(&t).unwrap();
+    assert_eq!(v["due"], "2030-10-20 09:00:00 +02:00");
+    assert_eq!(v["start"], "2030-10-20 07:00:00 +00:00");
+
+    let back: Todo = serde_json::from_value(v.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&back).unwrap(), v);
+
+    t.set_due_iso8601(String::from("2030-10-20 09:00:00"))
+        .unwrap();
+    assert_eq!(
+        serde_json::to_value(&t).unwrap()["due"],
+        "2030-10-20 09:00:00"
+    );
+}
+
+#[test]
+fn zones_are_saved_with_lists() {
+    let mut tdl = TodoList::new();
+    assert_eq!(tdl.zone(), None);
+    assert!(!tdl.to_json().unwrap().contains("zone"));
+
+    tdl.set_zone(Some(Tz::America__New_York));
+    let s = tdl.to_json().unwrap();
+    assert!(s.contains("\"America/New_York\""), "{}", s);
+    let back = TodoList::from_json(&s).unwrap();
+    assert_eq!(back.zone(), Some(Tz::America__New_York));
+
+    let bad = s.replace("America/New_York", "Mars/Olympus");
+    assert!(TodoList::from_json(&bad).is_err());
+}
+
+#[test]
+fn repeats_keep_the_time_when_clocks_change() {
+    let mut tdl = berlin("2030-10-26 09:00:00 +02:00", "1 day from due");
+    assert_eq!(next_due(&mut tdl), "2030-10-27 09:00:00 +01:00");
+
+    let mut tdl = berlin("2030-10-26 09:00:00 +02:00", "0 0 9 * * *");
+    assert_eq!(next_due(&mut tdl), "2030-10-27 09:00:00 +01:00");
+}
+
+#[test]
+fn floating_times_stay_floating() {
+    let mut tdl = berlin("2030-10-26 09:00:00", "1 day from due");
+    assert_eq!(next_due(&mut tdl), "2030-10-27 09:00:00");
+
+    let mut tdl = berlin("2030-10-26 09:00:00", "0 0 9 * * *");
+    assert_eq!(next_due(&mut tdl), "2030-10-27 09:00:00");
+}
+
+#[test]
+fn times_with_offsets_are_read_in_the_list_zone() {
+    // 9:00 in New York is 15:00 in Berlin, so the next 9:00 there is the
+    // day after.
+    let mut tdl = berlin("2030-10-26 09:00:00 -04:00", "0 0 9 * * *");
+    assert_eq!(next_due(&mut tdl), "2030-10-27 09:00:00 +01:00");
+}